@@ -0,0 +1,249 @@
+//! The demo game: a title screen, a short prologue, the menus and a sample
+//! of every widget.
+//!
+//! Run with `cargo run --example demo`. Pass `--record <path>` to record the
+//! player actions of the session, or `--replay <path>` to replay them
+//! without a window.
+
+mod widgets;
+
+use avg_dsx::prelude::*;
+use bevy::log::LogPlugin;
+use bevy::prelude::Val::Px;
+use bevy::{prelude::*, window::WindowResolution};
+use widgets::DemoPlugin;
+
+fn main() -> AppExit {
+    let replay = match ReplayPlugin::from_args(std::env::args()) {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("{err}");
+            return AppExit::error();
+        }
+    };
+    // 回放时不创建窗口，也不读取用户配置
+    if let Some(ReplayPlugin::Replay(recording)) = replay {
+        return headless_app()
+            .add_plugins(LogPlugin::default())
+            .add_plugins(
+                AvgPlugin::new()
+                    .audio(false)
+                    .widgets(false)
+                    .scroll_view(false)
+                    .config(false),
+            )
+            .add_plugins(ReplayPlugin::Replay(recording))
+            .run();
+    }
+
+    // 在创建窗口之前读取用户配置
+    let (config, config_store) = ConfigStore::load_from_env();
+    // 存档放在配置文件旁边
+    let saves = SaveStore::next_to(&config_store.path);
+    let history = saves.load().unwrap_or_else(|err| {
+        eprintln!("can not read {}: {}", saves.path.display(), err);
+        StoryHistory::default()
+    });
+    let msaa = match config.msaa_samples {
+        1 => Msaa::Off,
+        2 => Msaa::Sample2,
+        8 => Msaa::Sample8,
+        _ => Msaa::Sample4,
+    };
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                resolution: WindowResolution::new(config.resolution.0, config.resolution.1),
+                title: config.title.clone(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }))
+        .add_systems(OnEnter(GameState::InGame), setup)
+        .insert_resource(config)
+        .insert_resource(config_store)
+        .insert_resource(history)
+        .insert_resource(saves)
+        .insert_resource(msaa) // 启用抗锯齿
+        .add_plugins((AvgPlugin::new(), DemoPlugin));
+    if let Some(record) = replay {
+        app.add_plugins(record);
+    }
+    app.run()
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, ui_root: Res<UiRoot>) {
+    let padding = DESIGN_RESOLUTION.x * 0.01;
+
+    commands.entity(ui_root.0).with_children(|root| {
+        root
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        display: Display::Flex,
+                        position_type: PositionType::Absolute,
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(30.0),
+                        padding: UiRect::all(Px(padding)),
+                        top: Px(DESIGN_RESOLUTION.y * 0.7 - 30.0 - 2.0 * padding),
+                        ..default()
+                    },
+                    ..default()
+                },
+                Name::new("ButtonList"),
+                StateScoped(GameState::InGame),
+            ))
+            .with_children(|button_list| {
+                button_list
+                    .spawn((
+                        ButtonBundle {
+                            style: {
+                                Style {
+                                    width: Px(150.0),
+                                    height: Px(30.0),
+                                    border: UiRect::all(Px(5.0)),
+                                    // horizontally center child text
+                                    justify_content: JustifyContent::Center,
+                                    // vertically center child text
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                }
+                            },
+                            interaction: Interaction::None,
+                            border_color: BorderColor(Color::BLACK),
+                            border_radius: BorderRadius::MAX,
+                            background_color: Color::srgb(0.15, 0.15, 0.15).into(),
+                            ..default()
+                        },
+                        Name::new("TextFiledHidden"),
+                        TextFiledHiddenButton,
+                    ))
+                    .with_children(|button_bundle| {
+                        button_bundle.spawn((
+                            TextBundle::from_section(
+                                "FiledHidden",
+                                TextStyle {
+                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                    font_size: 18.0,
+                                    color: Color::srgb(0.9, 0.9, 0.9),
+                                },
+                            ),
+                            LocalizedText::new("ui.filed_hidden", "FiledHidden"),
+                        ));
+                    });
+
+                button_list
+                    .spawn((
+                        ButtonBundle {
+                            style: {
+                                Style {
+                                    width: Px(150.0),
+                                    height: Px(30.0),
+                                    border: UiRect::all(Px(5.0)),
+                                    // horizontally center child text
+                                    justify_content: JustifyContent::Center,
+                                    // vertically center child text
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                }
+                            },
+                            interaction: Interaction::None,
+                            border_color: BorderColor(Color::BLACK),
+                            border_radius: BorderRadius::MAX,
+                            background_color: Color::srgb(0.15, 0.15, 0.15).into(),
+                            ..default()
+                        },
+                        Name::new("TextFiledHidden"),
+                        AudioPlayControl,
+                    ))
+                    .with_children(|button_bundle| {
+                        button_bundle.spawn((
+                            TextBundle::from_section(
+                                "AudioPlay",
+                                TextStyle {
+                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                    font_size: 18.0,
+                                    color: Color::srgb(0.9, 0.9, 0.9),
+                                },
+                            ),
+                            LocalizedText::new("ui.audio_play", "AudioPlay"),
+                        ));
+                    });
+
+                button_list
+                    .spawn((
+                        ButtonBundle {
+                            style: {
+                                Style {
+                                    width: Px(150.0),
+                                    height: Px(30.0),
+                                    border: UiRect::all(Px(5.0)),
+                                    // horizontally center child text
+                                    justify_content: JustifyContent::Center,
+                                    // vertically center child text
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                }
+                            },
+                            interaction: Interaction::None,
+                            border_color: BorderColor(Color::BLACK),
+                            border_radius: BorderRadius::MAX,
+                            background_color: Color::srgb(0.15, 0.15, 0.15).into(),
+                            ..default()
+                        },
+                        Name::new("ChangeCharacter"),
+                        CharacterControl,
+                    ))
+                    .with_children(|button_bundle| {
+                        button_bundle.spawn((
+                            TextBundle::from_section(
+                                "ChangeCharacter",
+                                TextStyle {
+                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                    font_size: 18.0,
+                                    color: Color::srgb(0.9, 0.9, 0.9),
+                                },
+                            ),
+                            LocalizedText::new("ui.change_character", "ChangeCharacter"),
+                        ));
+                    });
+                button_list
+                    .spawn((
+                        ButtonBundle {
+                            style: {
+                                Style {
+                                    width: Px(150.0),
+                                    height: Px(30.0),
+                                    border: UiRect::all(Px(5.0)),
+                                    // horizontally center child text
+                                    justify_content: JustifyContent::Center,
+                                    // vertically center child text
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                }
+                            },
+                            interaction: Interaction::None,
+                            border_color: BorderColor(Color::BLACK),
+                            border_radius: BorderRadius::MAX,
+                            background_color: Color::srgb(0.15, 0.15, 0.15).into(),
+                            ..default()
+                        },
+                        Name::new("ChangeBackGround"),
+                        BackgroundControl,
+                    ))
+                    .with_children(|button_bundle| {
+                        button_bundle.spawn((
+                            TextBundle::from_section(
+                                "ChangeBackGround",
+                                TextStyle {
+                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                    font_size: 18.0,
+                                    color: Color::srgb(0.9, 0.9, 0.9),
+                                },
+                            ),
+                            LocalizedText::new("ui.change_background", "ChangeBackGround"),
+                        ));
+                    });
+            });
+    });
+}
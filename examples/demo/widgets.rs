@@ -0,0 +1,280 @@
+use bevy::prelude::Val::Px;
+use bevy::prelude::*;
+
+use avg_dsx::prelude::*;
+
+/// A Bevy Plugin spawning sample checkboxes and progress bars on the main
+/// menu, a long scroll view list in the story and the volume and text speed
+/// sliders in the settings.
+pub struct DemoPlugin;
+
+/// Above the menu the widgets are shown in.
+const DEMO_Z_INDEX: ZIndex = ZIndex::Global(MENU_Z_INDEX + 1);
+
+impl Plugin for DemoPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::MainMenu),
+            (spawn_checkbox_demo, spawn_progress_bar_demo),
+        )
+        .add_systems(OnEnter(GameState::InGame), spawn_scroll_view_demo)
+        .add_systems(OnEnter(MenuState::Settings), spawn_slider_demo);
+    }
+}
+
+fn spawn_checkbox_demo(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font: Handle<Font> = asset_server.load("fonts/zfft.ttf");
+
+    // Root node
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    top: Val::Px(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Start,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                },
+                z_index: DEMO_Z_INDEX,
+                ..default()
+            },
+            StateScoped(GameState::MainMenu),
+        ))
+        .with_children(|parent| {
+            CheckboxBuilder::new()
+                .checked(true)
+                .size(24.0)
+                .label("checkbox")
+                .font(font.clone())
+                .spawn(parent);
+            CheckboxBuilder::new()
+                .switch()
+                .label("switch")
+                .font(font.clone())
+                .spawn(parent);
+            for (n, label) in ["radio a", "radio b"].into_iter().enumerate() {
+                CheckboxBuilder::new()
+                    .group(1)
+                    .checked(n == 0)
+                    .label(label)
+                    .font(font.clone())
+                    .spawn(parent);
+            }
+            CheckboxBuilder::new()
+                .disabled(true)
+                .label("disabled")
+                .font(font.clone())
+                .spawn(parent);
+        });
+}
+
+fn spawn_progress_bar_demo(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font: Handle<Font> = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let node = |left: f32, top: f32| {
+        (
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(left),
+                    top: Val::Px(top),
+                    ..default()
+                },
+                z_index: DEMO_Z_INDEX,
+                ..default()
+            },
+            StateScoped(GameState::MainMenu),
+        )
+    };
+    let base = ProgressBar {
+        max_value: 100.0,
+        size: Vec2::new(300.0, 30.0),
+        bar_color: Color::srgb(0.25, 0.75, 0.25),
+        background_color: Color::srgb(0.15, 0.15, 0.15),
+        font: font.clone(),
+        ..default()
+    };
+
+    // 带百分比标签、平滑过渡到目标值
+    commands.spawn((
+        node(100.0, 100.0),
+        ProgressBar {
+            label: ProgressLabel::Percent,
+            ..base.clone()
+        },
+        AnimatedProgressBar {
+            target: 75.0,
+            speed: 25.0,
+        },
+    ));
+    commands.spawn((
+        node(100.0, 140.0),
+        ProgressBar {
+            value: 60.0,
+            variant: ProgressBarVariant::Segmented {
+                count: 10,
+                gap: 4.0,
+            },
+            ..base.clone()
+        },
+    ));
+    // 资源加载界面使用的不确定进度
+    commands.spawn((
+        node(100.0, 180.0),
+        ProgressBar {
+            indeterminate: true,
+            label: ProgressLabel::Text("Loading...".to_string()),
+            ..base.clone()
+        },
+    ));
+    commands.spawn((
+        node(420.0, 100.0),
+        ProgressBar {
+            value: 40.0,
+            size: Vec2::new(20.0, 110.0),
+            orientation: ProgressOrientation::Vertical,
+            ..base.clone()
+        },
+    ));
+    commands.spawn((
+        node(460.0, 100.0),
+        ProgressBar {
+            value: 30.0,
+            size: Vec2::new(60.0, 60.0),
+            variant: ProgressBarVariant::Radial { segments: 12 },
+            indeterminate: true,
+            ..base.clone()
+        },
+    ));
+    commands.spawn((
+        node(540.0, 100.0),
+        ProgressBar {
+            value: 30.0,
+            size: Vec2::new(60.0, 60.0),
+            variant: ProgressBarVariant::Radial { segments: 12 },
+            ..base
+        },
+    ));
+}
+
+/// A long list of demo rows, every fifth one taller than the others.
+struct DemoList {
+    font: Handle<Font>,
+    len: usize,
+}
+
+impl ListDataSource for DemoList {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn estimated_row_height(&self) -> f32 {
+        56.0
+    }
+
+    fn build_row(&self, index: usize, row: &mut ChildBuilder) {
+        let text = if index % 5 == 4 {
+            format!("Item {}\n(a taller row)", index + 1)
+        } else {
+            format!("Item {}", index + 1)
+        };
+        row.spawn(TextBundle {
+            text: Text::from_section(
+                text,
+                TextStyle {
+                    font: self.font.clone(),
+                    font_size: 30.0,
+                    color: Color::BLACK,
+                },
+            ),
+            style: Style {
+                margin: UiRect::all(Px(10.0)),
+                ..default()
+            },
+            ..default()
+        });
+    }
+}
+
+/// Spawns UI entities with scrolling capabilities for a Bevy application.
+///
+/// This system sets up a scroll view with a long virtualised demo list,
+/// which the hidden text field button shows and hides.
+///
+/// # Parameters
+/// - `commands`: Command queue for spawning entities.
+/// - `asset_server`: Access to the asset loader for loading fonts.
+/// - `ui_root`: The design-resolution root node the view is positioned in.
+///
+/// Ensure the appropriate resources (e.g., font files) exist in your asset folder.
+fn spawn_scroll_view_demo(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    ui_root: Res<UiRoot>,
+) {
+    let view_size = Vec2::new(DESIGN_RESOLUTION.x * 0.3, DESIGN_RESOLUTION.y * 0.2);
+    let view_position = Vec2::new(DESIGN_RESOLUTION.x * 0.3, DESIGN_RESOLUTION.y * 0.4);
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+
+    let mut scroll_view = Entity::PLACEHOLDER;
+    commands.entity(ui_root.0).with_children(|root| {
+        scroll_view = ScrollViewBuilder::new(view_size)
+            .style(Style {
+                position_type: PositionType::Absolute,
+                left: Px(view_position.x),
+                top: Px(view_position.y),
+                ..default()
+            })
+            .background_color(Color::srgb(0.25, 0.25, 0.75))
+            .spawn_list(root, DemoList { font, len: 1000 });
+    });
+    commands
+        .entity(scroll_view)
+        .insert((TextFiledHidden, StateScoped(GameState::InGame)));
+}
+
+fn spawn_slider_demo(mut commands: Commands) {
+    let style = |top: f32| Style {
+        width: Val::Px(300.0),
+        height: Val::Px(30.0),
+        position_type: PositionType::Absolute,
+        left: Val::Px(100.0),
+        top: Val::Px(top),
+        ..default()
+    };
+
+    commands.spawn((
+        NodeBundle {
+            style: style(200.0),
+            z_index: DEMO_Z_INDEX,
+            ..default()
+        },
+        StateScoped(MenuState::Settings),
+        Slider {
+            value: 1.0,
+            min: 0.0,
+            max: 1.0,
+            step: 0.05,
+            ..default()
+        },
+        SliderBinding::MasterVolume,
+    ));
+
+    commands.spawn((
+        NodeBundle {
+            style: style(250.0),
+            z_index: DEMO_Z_INDEX,
+            ..default()
+        },
+        StateScoped(MenuState::Settings),
+        Slider {
+            value: 5.0,
+            min: 1.0,
+            max: 30.0,
+            step: 1.0,
+            ..default()
+        },
+        SliderBinding::TextSpeed,
+    ));
+}
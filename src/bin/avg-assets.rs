@@ -0,0 +1,122 @@
+//! Batch processes the images of a directory into a build directory.
+//!
+//! ```text
+//! avg-assets <input dir> <output dir> [--size 1280x720] [--mode fit|fill|cover]
+//!     [--filter nearest|triangle|catmull-rom|gaussian|lanczos3] [--trim]
+//!     [--format png|webp|jpeg] [--variants] [--force]
+//! ```
+//!
+//! Images are written to the same relative path below the output directory.
+//! With `--variants` each image is written once for every resolution the
+//! game offers, e.g. `bg1@720p.png`, `bg1@1080p.png` and `bg1@1440p.png`,
+//! the size being the one at 720p. Inputs unchanged since the last run are
+//! skipped.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use avg_dsx::pipeline::images::{
+    parse_filter, process_dir, process_variants, ImageOptions, ResizeMode,
+};
+use bevy::math::UVec2;
+use image::ImageFormat;
+
+const USAGE: &str = "usage: avg-assets <input dir> <output dir> [--size WxH] [--mode fit|fill|cover] \
+[--filter nearest|triangle|catmull-rom|gaussian|lanczos3] [--trim] [--format png|webp|jpeg] [--variants] [--force]";
+
+struct Args {
+    input: PathBuf,
+    output: PathBuf,
+    options: ImageOptions,
+    variants: bool,
+    force: bool,
+}
+
+fn parse_size(value: &str) -> Option<UVec2> {
+    let (width, height) = value.split_once('x')?;
+    Some(UVec2::new(width.parse().ok()?, height.parse().ok()?))
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.into_iter();
+    let mut paths = Vec::new();
+    let mut options = ImageOptions::default();
+    let mut variants = false;
+    let mut force = false;
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--size" => {
+                let value = value()?;
+                options.size = Some(parse_size(&value).ok_or(format!("bad size {value}"))?);
+            }
+            "--mode" => {
+                let value = value()?;
+                options.mode = ResizeMode::parse(&value).ok_or(format!("bad mode {value}"))?;
+            }
+            "--filter" => {
+                let value = value()?;
+                options.filter = parse_filter(&value).ok_or(format!("bad filter {value}"))?;
+            }
+            "--format" => {
+                let value = value()?;
+                options.format =
+                    Some(ImageFormat::from_extension(&value).ok_or(format!("bad format {value}"))?);
+            }
+            "--trim" => options.trim = true,
+            "--variants" => variants = true,
+            "--force" => force = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    let [input, output] = <[PathBuf; 2]>::try_from(paths)
+        .map_err(|_| "expected an input and an output directory".to_string())?;
+    Ok(Args {
+        input,
+        output,
+        options,
+        variants,
+        force,
+    })
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let process = if args.variants {
+        process_variants
+    } else {
+        process_dir
+    };
+    let report = match process(&args.input, &args.output, &args.options, args.force) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("can not process {}: {}", args.input.display(), err);
+            return ExitCode::FAILURE;
+        }
+    };
+    for path in report.processed.iter() {
+        println!("processed {}", path.display());
+    }
+    for (path, err) in report.failed.iter() {
+        eprintln!("{}: {}", args.input.join(path).display(), err);
+    }
+    println!(
+        "{} processed, {} unchanged, {} failed",
+        report.processed.len(),
+        report.skipped.len(),
+        report.failed.len()
+    );
+    if report.failed.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
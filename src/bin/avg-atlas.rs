@@ -0,0 +1,84 @@
+//! Packs the expression layers of characters into texture atlases.
+//!
+//! ```text
+//! avg-atlas <input dir> <output dir> [--max-size 2048] [--padding 2]
+//! ```
+//!
+//! Every folder directly below the input directory is a character and every
+//! image below it one of its layers, drawn on a canvas of the same size.
+//! Layers are trimmed of their transparent borders and packed into
+//! `<character>_<n>.png`, indexed in `<character>.atlas.ron`.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use avg_dsx::pipeline::atlas::{pack_dir, AtlasOptions};
+
+const USAGE: &str = "usage: avg-atlas <input dir> <output dir> [--max-size N] [--padding N]";
+
+struct Args {
+    input: PathBuf,
+    output: PathBuf,
+    options: AtlasOptions,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.into_iter();
+    let mut paths = Vec::new();
+    let mut options = AtlasOptions::default();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            let value = args.next().ok_or(format!("{arg} needs a value"))?;
+            value
+                .parse::<u32>()
+                .map_err(|_| format!("bad {arg} {value}"))
+        };
+        match arg.as_str() {
+            "--max-size" => options.max_size = value()?,
+            "--padding" => options.padding = value()?,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    let [input, output] = <[PathBuf; 2]>::try_from(paths)
+        .map_err(|_| "expected an input and an output directory".to_string())?;
+    Ok(Args {
+        input,
+        output,
+        options,
+    })
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let report = match pack_dir(&args.input, &args.output, &args.options) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("can not pack {}: {}", args.input.display(), err);
+            return ExitCode::FAILURE;
+        }
+    };
+    for path in report.packed.iter() {
+        println!("packed {}", path.display());
+    }
+    for (path, err) in report.failed.iter() {
+        eprintln!("{}: {}", path.display(), err);
+    }
+    println!(
+        "{} packed, {} failed",
+        report.packed.len(),
+        report.failed.len()
+    );
+    if report.failed.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,93 @@
+//! Draws the branching structure of the game's scripts.
+//!
+//! ```text
+//! avg-flowchart <asset dir> <output dir> [--words-per-minute 250]
+//! ```
+//!
+//! Every `.avg` script below the asset directory is split into blocks at its
+//! labels, jumps and choices. The graph is written to `story.dot` for
+//! Graphviz and to `story.html`, a page drawing it without other files.
+//! Blocks show their word count and read time; endings are highlighted.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use avg_dsx::pipeline::flowchart::{flowchart_dir, FlowchartOptions};
+
+const USAGE: &str = "usage: avg-flowchart <asset dir> <output dir> [--words-per-minute N]";
+
+struct Args {
+    input: PathBuf,
+    output: PathBuf,
+    options: FlowchartOptions,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.into_iter();
+    let mut paths = Vec::new();
+    let mut options = FlowchartOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--words-per-minute" => {
+                let value = args.next().ok_or(format!("{arg} needs a value"))?;
+                options.words_per_minute = value
+                    .parse()
+                    .ok()
+                    .filter(|words: &f32| *words > 0.0)
+                    .ok_or(format!("bad {arg} {value}"))?;
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    let [input, output] = <[PathBuf; 2]>::try_from(paths)
+        .map_err(|_| "expected an asset and an output directory".to_string())?;
+    Ok(Args {
+        input,
+        output,
+        options,
+    })
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let flowchart = match flowchart_dir(&args.input, &args.options) {
+        Ok(flowchart) => flowchart,
+        Err(err) => {
+            eprintln!(
+                "can not read the scripts of {}: {}",
+                args.input.display(),
+                err
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    let outputs = [
+        ("story.dot", flowchart.to_dot()),
+        ("story.html", flowchart.to_html()),
+    ];
+    for (name, text) in outputs {
+        let path = args.output.join(name);
+        let written = fs::create_dir_all(&args.output).and_then(|_| fs::write(&path, text));
+        if let Err(err) = written {
+            eprintln!("can not write {}: {}", path.display(), err);
+            return ExitCode::FAILURE;
+        }
+        println!("wrote {}", path.display());
+    }
+    let blocks: usize = flowchart
+        .scripts
+        .iter()
+        .map(|script| script.nodes.len())
+        .sum();
+    println!("{} scripts, {} blocks", flowchart.scripts.len(), blocks);
+    ExitCode::SUCCESS
+}
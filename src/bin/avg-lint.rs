@@ -0,0 +1,60 @@
+//! Checks the scripts of the game.
+//!
+//! ```text
+//! avg-lint [asset dir]
+//! ```
+//!
+//! Every `.avg` script below the asset directory (`assets` by default) is
+//! parsed and checked: jump targets, the assets and atlas layers it shows or
+//! plays, variables read before they are set, choices without an option to
+//! show and unreachable statements. Problems are printed as
+//! `file:line: message`; the exit code is non-zero when there are any.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use avg_dsx::pipeline::lint::lint_dir;
+
+const USAGE: &str = "usage: avg-lint [asset dir]";
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<PathBuf, String> {
+    let mut paths = Vec::new();
+    for arg in args {
+        if arg.starts_with("--") {
+            return Err(format!("unknown option {arg}"));
+        }
+        paths.push(PathBuf::from(arg));
+    }
+    match paths.len() {
+        0 => Ok(PathBuf::from("assets")),
+        1 => Ok(paths.remove(0)),
+        _ => Err("expected a single asset directory".to_string()),
+    }
+}
+
+fn main() -> ExitCode {
+    let root = match parse_args(std::env::args().skip(1)) {
+        Ok(root) => root,
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let diagnostics = match lint_dir(&root) {
+        Ok(diagnostics) => diagnostics,
+        Err(err) => {
+            eprintln!("can not check {}: {}", root.display(), err);
+            return ExitCode::FAILURE;
+        }
+    };
+    for diagnostic in diagnostics.iter() {
+        println!("{diagnostic}");
+    }
+    if diagnostics.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        eprintln!("{} problems", diagnostics.len());
+        ExitCode::FAILURE
+    }
+}
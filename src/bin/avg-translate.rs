@@ -0,0 +1,187 @@
+//! Extracts the texts of the game for translators and updates the
+//! translations.
+//!
+//! ```text
+//! avg-translate <asset dir> [--sources DIR]... [--init LANG]... [--xliff]
+//! ```
+//!
+//! The dialogue lines, choice options and speaker names of every `.avg`
+//! script below the asset directory, the `LocalizedText` UI texts of the Rust
+//! sources (`src` and `examples` by default) and the main menu items of
+//! `title.toml` are written to the template `locales/messages.pot` with their
+//! speaker, file, line and the line before. Every `locales/<language>.po` is then merged with it: new
+//! texts are added, translations of changed texts are marked fuzzy and texts
+//! no longer used are dropped. `--init` starts the translation into a new
+//! language; `--xliff` also writes `.xlf` files for XLIFF tools. The
+//! completion of every language is printed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use avg_dsx::core::locale::LOCALE_DIR;
+use avg_dsx::core::po::{parse_po, write_po};
+use avg_dsx::pipeline::translate::{
+    completion, extract_scripts, extract_sources, extract_title_screen, header, merge, template,
+    to_xliff, TEMPLATE_FILE,
+};
+
+const USAGE: &str = "usage: avg-translate <asset dir> [--sources DIR]... [--init LANG]... [--xliff] [--source-language LANG]";
+
+struct Args {
+    root: PathBuf,
+    sources: Vec<PathBuf>,
+    init: Vec<String>,
+    xliff: bool,
+    source_language: String,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.into_iter();
+    let mut paths = Vec::new();
+    let mut sources = Vec::new();
+    let mut init = Vec::new();
+    let mut xliff = false;
+    let mut source_language = "zh".to_string();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sources" => {
+                sources.push(PathBuf::from(
+                    args.next().ok_or(format!("{arg} needs a value"))?,
+                ));
+            }
+            "--init" => init.push(args.next().ok_or(format!("{arg} needs a value"))?),
+            "--source-language" => {
+                source_language = args.next().ok_or(format!("{arg} needs a value"))?;
+            }
+            "--xliff" => xliff = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    let [root] = <[PathBuf; 1]>::try_from(paths)
+        .map_err(|_| "expected a single asset directory".to_string())?;
+    if sources.is_empty() {
+        sources = ["src", "examples"]
+            .into_iter()
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_dir())
+            .collect();
+    }
+    Ok(Args {
+        root,
+        sources,
+        init,
+        xliff,
+        source_language,
+    })
+}
+
+fn write(path: &Path, text: String) -> Result<(), String> {
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(path, text));
+    written.map_err(|err| format!("can not write {}: {}", path.display(), err))
+}
+
+/// The translations in the locale folder, by language.
+fn translations(dir: &Path) -> Result<Vec<(String, PathBuf)>, String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    let mut languages = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|err| err.to_string())?.path();
+        if path.extension().is_some_and(|ext| ext == "po") {
+            if let Some(stem) = path.file_stem() {
+                languages.push((stem.to_string_lossy().into_owned(), path));
+            }
+        }
+    }
+    languages.sort();
+    Ok(languages)
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let mut messages = extract_scripts(&args.root).map_err(|err| {
+        format!(
+            "can not read the scripts of {}: {}",
+            args.root.display(),
+            err
+        )
+    })?;
+    for dir in args.sources.iter() {
+        let found = extract_sources(dir)
+            .map_err(|err| format!("can not read the sources in {}: {}", dir.display(), err))?;
+        messages.extend(found);
+    }
+    let items = extract_title_screen(&args.root).map_err(|err| {
+        format!(
+            "can not read the title screen of {}: {}",
+            args.root.display(),
+            err
+        )
+    })?;
+    messages.extend(items);
+    let template = template(&messages);
+    let dir = args.root.join(LOCALE_DIR);
+    let path = dir.join(TEMPLATE_FILE);
+    write(&path, write_po(&template))?;
+    println!("wrote {} ({} texts)", path.display(), template.len() - 1);
+    if args.xliff {
+        let path = path.with_extension("xlf");
+        write(&path, to_xliff(&template, &args.source_language, None))?;
+        println!("wrote {}", path.display());
+    }
+
+    for language in args.init.iter() {
+        let path = dir.join(format!("{language}.po"));
+        if path.exists() {
+            println!("{} exists, merging it", path.display());
+            continue;
+        }
+        write(&path, write_po(&[header(Some(language))]))?;
+    }
+
+    for (language, path) in translations(&dir)? {
+        let existing = fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| parse_po(&text))
+            .map_err(|err| format!("can not read {}: {}", path.display(), err))?;
+        let (merged, report) = merge(&template, &existing);
+        write(&path, write_po(&merged))?;
+        if args.xliff {
+            let xliff = to_xliff(&merged, &args.source_language, Some(&language));
+            write(&path.with_extension("xlf"), xliff)?;
+        }
+        let (translated, total) = completion(&merged);
+        let percent = if total == 0 {
+            100.0
+        } else {
+            translated as f32 * 100.0 / total as f32
+        };
+        println!(
+            "{language}: {translated}/{total} ({percent:.1}%), {} fuzzy, {} new, {} obsolete",
+            report.fuzzy, report.new, report.obsolete
+        );
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
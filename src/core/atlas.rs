@@ -0,0 +1,49 @@
+use bevy::math::{URect, UVec2};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The extension of the index of a character's layer atlases.
+pub const ATLAS_INDEX_EXTENSION: &str = "atlas.ron";
+
+/// The index of a character's layer atlases, as written by `avg-atlas`.
+///
+/// Every layer was drawn on a canvas of the same size and is stored trimmed
+/// of its transparent borders, so it is drawn at `offset` on the canvas.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AtlasIndex {
+    /// The size of the canvas the layers were drawn on.
+    pub canvas: UVec2,
+    pub pages: Vec<AtlasPage>,
+    /// Every layer by name, its path below the character's folder without
+    /// the extension, e.g. `face/smile`.
+    pub layers: BTreeMap<String, AtlasLayer>,
+}
+
+/// One atlas image.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AtlasPage {
+    /// The path of the image, relative to the index.
+    pub path: String,
+    pub size: UVec2,
+}
+
+/// Where a layer is in the atlas and on the canvas.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AtlasLayer {
+    /// The index of the page in [`AtlasIndex::pages`].
+    pub page: usize,
+    /// The layer's pixels on the page.
+    pub rect: URect,
+    /// The top left corner of the layer on the canvas.
+    pub offset: UVec2,
+}
+
+impl AtlasIndex {
+    pub fn from_ron(text: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+}
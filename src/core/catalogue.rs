@@ -0,0 +1,463 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::event_bus::VnCommand;
+use crate::global_def::global_define::{RESOLUTION_720P, RESOLUTION_VARIANTS};
+use crate::utils::image_utils::is_supported_image_format;
+
+/// The manifest in the asset folder giving assets their ids and tags.
+pub const CATALOGUE_MANIFEST: &str = "catalogue.toml";
+
+/// A Bevy Plugin indexing the asset folder into an [`AssetCatalogue`] when
+/// the app is built.
+///
+/// The folder is found the way Bevy's `AssetPlugin` finds it, so
+/// `file_path` must match the one given to the `AssetPlugin`. Every image,
+/// audio, font and script file gets an id from its path, e.g.
+/// `images/bg1.png` becomes `images.bg1`; [`CATALOGUE_MANIFEST`] can give it
+/// further ids and tags:
+///
+/// ```toml
+/// [assets."bg.classroom"]
+/// path = "images/bg1.png"
+/// tags = ["school"]
+/// ```
+///
+/// Scripts can then use `bg.classroom` wherever a [`VnCommand`] takes a path.
+///
+/// Images named after one of [`RESOLUTION_VARIANTS`], e.g. `images/bg1@1080p.png`
+/// as written by `avg-assets --variants`, are variants of `images/bg1.png`
+/// rather than assets of their own, see [`AssetCatalogue::variant`].
+pub struct AssetCataloguePlugin {
+    pub file_path: String,
+}
+
+impl Default for AssetCataloguePlugin {
+    fn default() -> Self {
+        Self {
+            file_path: "assets".to_string(),
+        }
+    }
+}
+
+/// The kinds of assets the catalogue indexes, by file extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AssetKind {
+    Image,
+    Audio,
+    Font,
+    Script,
+}
+
+impl AssetKind {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?;
+        if is_supported_image_format(ext) {
+            return Some(AssetKind::Image);
+        }
+        match ext.to_str()? {
+            "ogg" | "mp3" | "wav" | "flac" => Some(AssetKind::Audio),
+            "ttf" | "otf" => Some(AssetKind::Font),
+            "avg" | "toml" | "ron" | "txt" => Some(AssetKind::Script),
+            _ => None,
+        }
+    }
+}
+
+/// One indexed asset file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AssetEntry {
+    /// The path relative to the asset folder, as given to the `AssetServer`.
+    pub path: String,
+    pub kind: AssetKind,
+    pub tags: Vec<String>,
+    /// The size of an image, in pixels.
+    pub dimensions: Option<UVec2>,
+    /// The length of an Ogg Vorbis file, in seconds.
+    pub duration: Option<f32>,
+    /// The paths of the resolution variants of an image, by variant name.
+    #[serde(default)]
+    pub variants: BTreeMap<String, String>,
+}
+
+/// An entry of [`CATALOGUE_MANIFEST`].
+#[derive(Deserialize)]
+struct ManifestEntry {
+    path: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    assets: BTreeMap<String, ManifestEntry>,
+}
+
+/// Every asset of the asset folder by id, see [`AssetCataloguePlugin`].
+#[derive(Resource, Clone, Debug, Default)]
+pub struct AssetCatalogue {
+    root: PathBuf,
+    entries: BTreeMap<String, AssetEntry>,
+}
+
+impl AssetCatalogue {
+    /// Indexes the asset folder at `root` and reads its manifest, if any.
+    pub fn scan(root: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut catalogue = Self {
+            root: root.to_path_buf(),
+            ..default()
+        };
+        let mut variants: Vec<(String, AssetEntry)> = Vec::new();
+        let mut dirs = vec![root.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let Ok(relative) = path.strip_prefix(root) else {
+                    continue;
+                };
+                if relative == Path::new(CATALOGUE_MANIFEST) {
+                    continue;
+                }
+                let Some(kind) = AssetKind::from_path(&path) else {
+                    continue;
+                };
+                let relative = relative
+                    .components()
+                    .map(|part| part.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let entry = AssetEntry {
+                    kind,
+                    tags: Vec::new(),
+                    dimensions: match kind {
+                        AssetKind::Image => image::image_dimensions(&path)
+                            .ok()
+                            .map(|(width, height)| UVec2::new(width, height)),
+                        _ => None,
+                    },
+                    duration: match kind {
+                        AssetKind::Audio => ogg_duration(&path),
+                        _ => None,
+                    },
+                    variants: BTreeMap::new(),
+                    path: relative,
+                };
+                if kind == AssetKind::Image && split_variant(&entry.path).is_some() {
+                    variants.push((entry.path.clone(), entry));
+                    continue;
+                }
+                catalogue.entries.insert(path_id(&entry.path), entry);
+            }
+        }
+
+        // 按分辨率顺序归入原图；只有变体时以最小的变体作为原图
+        variants.sort_by_key(|(path, _)| split_variant(path).map(|(_, index)| index));
+        for (path, entry) in variants {
+            let Some((base, index)) = split_variant(&path) else {
+                continue;
+            };
+            let name = RESOLUTION_VARIANTS[index].0.to_string();
+            let base_entry = catalogue
+                .entries
+                .entry(path_id(&base))
+                .or_insert_with(|| {
+                    // 尺寸记为设计分辨率下的尺寸
+                    let scale = variant_scale(&name).unwrap_or(1.0);
+                    AssetEntry {
+                        dimensions: entry
+                            .dimensions
+                            .map(|size| (size.as_vec2() / scale).round().as_uvec2()),
+                        ..entry.clone()
+                    }
+                });
+            base_entry.variants.insert(name, path);
+        }
+
+        let manifest_path = root.join(CATALOGUE_MANIFEST);
+        if manifest_path.is_file() {
+            let manifest: Manifest = toml::from_str(&fs::read_to_string(&manifest_path)?)?;
+            for (id, item) in manifest.assets {
+                let Some(mut entry) = catalogue.by_path(&item.path).cloned() else {
+                    return Err(
+                        format!("{CATALOGUE_MANIFEST} `{id}`: no asset {}", item.path).into(),
+                    );
+                };
+                entry.tags = item.tags;
+                catalogue.entries.insert(id, entry);
+            }
+        }
+        Ok(catalogue)
+    }
+
+    /// The asset folder the catalogue indexes.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn get(&self, id: &str) -> Option<&AssetEntry> {
+        self.entries.get(id)
+    }
+
+    /// Every id with its asset, sorted by id. An asset with several ids is
+    /// listed once for each.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &AssetEntry)> {
+        self.entries.iter().map(|(id, entry)| (id.as_str(), entry))
+    }
+
+    pub fn with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = (&'a str, &'a AssetEntry)> {
+        self.iter()
+            .filter(move |(_, entry)| entry.tags.iter().any(|entry_tag| entry_tag == tag))
+    }
+
+    /// The asset at `path`, as given to the `AssetServer`.
+    pub fn by_path(&self, path: &str) -> Option<&AssetEntry> {
+        self.entries.values().find(|entry| entry.path == path)
+    }
+
+    /// The path of the asset `id_or_path` names; anything that is not an id
+    /// is taken to be a path already.
+    pub fn resolve<'a>(&'a self, id_or_path: &'a str) -> &'a str {
+        self.get(id_or_path)
+            .map_or(id_or_path, |entry| entry.path.as_str())
+    }
+
+    /// The path of the variant of the image at `path` nearest to `variant`:
+    /// the largest one up to it, else the smallest one above it. An image
+    /// that is not a variant itself counts as the design resolution one.
+    pub fn variant<'a>(&'a self, path: &'a str, variant: Option<&str>) -> &'a str {
+        let wanted = variant.and_then(variant_index);
+        let (Some(wanted), Some(entry)) = (wanted, self.by_path(path)) else {
+            return path;
+        };
+        let base = split_variant(path).is_none().then_some((0, path));
+        let available = base.into_iter().chain(
+            entry
+                .variants
+                .iter()
+                .filter_map(|(name, path)| Some((variant_index(name)?, path.as_str()))),
+        );
+        // 优先用不超过所需分辨率的最大变体，同一分辨率时用变体而不是原图
+        let (below, above): (Vec<_>, Vec<_>) = available.partition(|(index, _)| *index <= wanted);
+        below
+            .into_iter()
+            .max_by_key(|(index, _)| *index)
+            .or_else(|| above.into_iter().min_by_key(|(index, _)| *index))
+            .map_or(path, |(_, path)| path)
+    }
+
+    /// Replaces the asset id `command` refers to by its path.
+    pub fn resolve_command(&self, mut command: VnCommand) -> VnCommand {
+        match &mut command {
+            VnCommand::ShowBackground { path }
+            | VnCommand::ShowCharacter { path, .. }
+            | VnCommand::PlayMusic { path, .. }
+            | VnCommand::PlayVoice { path } => {
+                if let Some(entry) = self.get(path) {
+                    *path = entry.path.clone();
+                }
+            }
+            _ => {}
+        }
+        command
+    }
+}
+
+/// The id of an asset without one in the manifest: its path without the
+/// extension, with `.` separating the folders.
+fn path_id(path: &str) -> String {
+    let stem = path.rsplit_once('.').map_or(path, |(stem, _)| stem);
+    stem.replace('/', ".")
+}
+
+/// The path of the variant `variant` of the asset at `path`: the variant
+/// name after an `@` before the extension.
+pub fn variant_path(path: &str, variant: &str) -> String {
+    match path.rsplit_once('.') {
+        Some((stem, ext)) if !ext.contains('/') => format!("{stem}@{variant}.{ext}"),
+        _ => format!("{path}@{variant}"),
+    }
+}
+
+/// The path of the asset `path` is a variant of, and the index of the
+/// variant in [`RESOLUTION_VARIANTS`].
+fn split_variant(path: &str) -> Option<(String, usize)> {
+    let (stem, ext) = path.rsplit_once('.')?;
+    let (base, variant) = stem.rsplit_once('@')?;
+    Some((format!("{base}.{ext}"), variant_index(variant)?))
+}
+
+/// The position of `variant` in [`RESOLUTION_VARIANTS`].
+fn variant_index(variant: &str) -> Option<usize> {
+    RESOLUTION_VARIANTS
+        .iter()
+        .position(|(name, _)| *name == variant)
+}
+
+/// The variant of [`RESOLUTION_VARIANTS`] best suited to a window
+/// `height` physical pixels high: the smallest one at least as high, or the
+/// largest.
+pub fn best_variant(height: f32) -> &'static str {
+    RESOLUTION_VARIANTS
+        .iter()
+        .find(|(_, resolution)| resolution.1 >= height)
+        .unwrap_or(&RESOLUTION_VARIANTS[RESOLUTION_VARIANTS.len() - 1])
+        .0
+}
+
+/// How much larger than at the design resolution the images of `variant`
+/// are, `None` for an unknown variant.
+pub fn variant_scale(variant: &str) -> Option<f32> {
+    RESOLUTION_VARIANTS
+        .iter()
+        .find(|(name, _)| *name == variant)
+        .map(|(_, resolution)| resolution.1 / RESOLUTION_720P.1)
+}
+
+/// Reads the length of an Ogg Vorbis file from the sample rate in its first
+/// page and the sample position of its last page.
+fn ogg_duration(path: &Path) -> Option<f32> {
+    let data = fs::read(path).ok()?;
+    // 标识头：页头 27 字节 + 段表，之后是 0x01 "vorbis" 版本(4) 声道(1) 采样率(4)
+    let segments = *data.get(26)? as usize;
+    let header = data.get(27 + segments..)?;
+    if header.get(..7)? != b"\x01vorbis" {
+        return None;
+    }
+    let sample_rate = u32::from_le_bytes(header.get(12..16)?.try_into().ok()?);
+    let last_page = data.windows(4).rposition(|window| window == b"OggS")?;
+    let granule = u64::from_le_bytes(data.get(last_page + 6..last_page + 14)?.try_into().ok()?);
+    (sample_rate > 0).then(|| granule as f32 / sample_rate as f32)
+}
+
+impl Plugin for AssetCataloguePlugin {
+    fn build(&self, app: &mut App) {
+        let root: PathBuf = FileAssetReader::new(&self.file_path).root_path().clone();
+        let catalogue = match AssetCatalogue::scan(&root) {
+            Ok(catalogue) => {
+                info!(
+                    "{} assets indexed in {}",
+                    catalogue.entries.len(),
+                    root.display()
+                );
+                catalogue
+            }
+            Err(err) => {
+                error!("can not index assets in {}: {}", root.display(), err);
+                AssetCatalogue::default()
+            }
+        };
+        app.insert_resource(catalogue);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalogue() -> AssetCatalogue {
+        AssetCatalogue::scan(&Path::new(env!("CARGO_MANIFEST_DIR")).join("assets")).unwrap()
+    }
+
+    #[test]
+    fn indexes_assets_by_path_and_manifest_id() {
+        let catalogue = catalogue();
+        let by_path = catalogue.get("images.bg1_resized").unwrap();
+        let by_id = catalogue.get("bg.prologue").unwrap();
+        assert_eq!(by_path.path, "images/bg1_resized.png");
+        assert_eq!(by_id.path, by_path.path);
+        assert_eq!(by_id.tags, ["background"]);
+        assert!(catalogue
+            .with_tag("background")
+            .any(|(id, _)| id == "bg.title"));
+        assert_eq!(
+            catalogue.get("fonts.FiraSans-Bold").unwrap().kind,
+            AssetKind::Font
+        );
+        assert!(catalogue.get("catalogue").is_none());
+    }
+
+    #[test]
+    fn reads_image_size_and_music_length() {
+        let catalogue = catalogue();
+        let image = catalogue.get("bg.prologue").unwrap();
+        assert_eq!(image.kind, AssetKind::Image);
+        assert!(image
+            .dimensions
+            .is_some_and(|size| size.x > 0 && size.y > 0));
+        let music = catalogue.get("bgm.main").unwrap();
+        assert_eq!(music.kind, AssetKind::Audio);
+        assert!(music.duration.is_some_and(|seconds| seconds > 1.0));
+    }
+
+    #[test]
+    fn resolves_ids_in_commands() {
+        let catalogue = catalogue();
+        assert_eq!(catalogue.resolve("bgm.main"), "music/bgmusic1.ogg");
+        assert_eq!(catalogue.resolve("images/other.png"), "images/other.png");
+        let command = catalogue.resolve_command(VnCommand::ShowCharacter {
+            path: "ch.ch4".to_string(),
+            layers: Vec::new(),
+        });
+        assert_eq!(
+            command,
+            VnCommand::ShowCharacter {
+                path: "images/ch4.png".to_string(),
+                layers: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn groups_resolution_variants() {
+        let root = std::env::temp_dir().join(format!("avg-catalogue-test-{}", std::process::id()));
+        fs::create_dir_all(root.join("images")).unwrap();
+        for name in [
+            "bg@1080p.png",
+            "bg.png",
+            "ch@1440p.png",
+            "ch@720p.png",
+            "cg@1080p.png",
+            "cg@1440p.png",
+            "other@4k.png",
+        ] {
+            image::RgbaImage::new(2, 2).save(root.join("images").join(name)).unwrap();
+        }
+        let catalogue = AssetCatalogue::scan(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let bg = catalogue.get("images.bg").unwrap();
+        assert_eq!(bg.path, "images/bg.png");
+        assert_eq!(catalogue.variant("images/bg.png", Some("1080p")), "images/bg@1080p.png");
+        assert_eq!(catalogue.variant("images/bg.png", Some("1440p")), "images/bg@1080p.png");
+        assert_eq!(catalogue.variant("images/bg.png", Some("720p")), "images/bg.png");
+        assert_eq!(catalogue.variant("images/bg.png", None), "images/bg.png");
+        let ch = catalogue.get("images.ch").unwrap();
+        assert_eq!(ch.path, "images/ch@720p.png");
+        assert_eq!(catalogue.variant(&ch.path, Some("1440p")), "images/ch@1440p.png");
+        assert_eq!(catalogue.variant(&ch.path, Some("1080p")), "images/ch@720p.png");
+        // 没有更小的变体时用更大的
+        let cg = catalogue.get("images.cg").unwrap();
+        assert_eq!(catalogue.variant(&cg.path, Some("720p")), "images/cg@1080p.png");
+        assert!(catalogue.get("images.other@4k").is_some());
+        assert!(catalogue.get("images.bg@1080p").is_none());
+    }
+
+    #[test]
+    fn picks_the_smallest_variant_covering_the_window() {
+        assert_eq!(best_variant(600.0), "720p");
+        assert_eq!(best_variant(720.0), "720p");
+        assert_eq!(best_variant(900.0), "1080p");
+        assert_eq!(best_variant(2160.0), "1440p");
+        assert_eq!(variant_path("images/bg1.png", "1080p"), "images/bg1@1080p.png");
+        assert_eq!(variant_scale("1440p"), Some(2.0));
+    }
+}
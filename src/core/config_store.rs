@@ -0,0 +1,271 @@
+use crate::global_def::global_define::GameConfig;
+use bevy::prelude::Resource;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+/// Environment variable that overrides the config file location.
+pub const CONFIG_ENV_VAR: &str = "AVG_CONFIG";
+
+/// Command line flag that overrides the config file location, e.g. `--config ./my.toml`.
+/// Takes precedence over [`CONFIG_ENV_VAR`].
+pub const CONFIG_CLI_FLAG: &str = "--config";
+
+const APP_DIR_NAME: &str = "AVG_DSX";
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+// 允许的取值范围
+const RESOLUTION_MIN: (f32, f32) = (640.0, 360.0);
+const RESOLUTION_MAX: (f32, f32) = (7680.0, 4320.0);
+const MSAA_SAMPLES: [u32; 4] = [1, 2, 4, 8];
+const TARGET_FPS_RANGE: (u32, u32) = (15, 360);
+const TEXT_SPEED_RANGE: (f32, f32) = (1.0, 100.0);
+
+/// A problem found while loading the config file.
+///
+/// Issues never abort loading: the offending value is replaced by a clamped
+/// or default value and the issue is kept so it can be reported to the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    /// The key the issue refers to, empty when the whole file is affected.
+    pub key: String,
+    /// A human readable description of what was wrong and what was used instead.
+    pub message: String,
+}
+
+impl ConfigIssue {
+    fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Where the [`GameConfig`] lives on disk and everything read from the file
+/// that is not part of it.
+///
+/// Keys unknown to this version of the game are kept untouched and written
+/// back on save, so newer or hand edited files survive a round trip.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ConfigStore {
+    /// The file the config is read from and saved to.
+    pub path: PathBuf,
+    /// Problems found during the last load.
+    pub issues: Vec<ConfigIssue>,
+    unknown: toml::Table,
+}
+
+impl ConfigStore {
+    /// Resolves the config path (CLI flag, then environment variable, then the
+    /// platform user data directory) and loads the config from it.
+    pub fn load_from_env() -> (GameConfig, Self) {
+        Self::load(resolve_config_path(env::args()))
+    }
+
+    /// Loads the config from `path`.
+    ///
+    /// A missing file yields the defaults without issues. An unreadable file,
+    /// a value of the wrong type or a value out of range is replaced by the
+    /// default or clamped, and recorded in [`ConfigStore::issues`].
+    pub fn load(path: impl Into<PathBuf>) -> (GameConfig, Self) {
+        let mut store = Self {
+            path: path.into(),
+            ..Default::default()
+        };
+
+        let config = match fs::read_to_string(&store.path) {
+            Ok(content) => store.parse(&content),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => GameConfig::default(),
+            Err(err) => {
+                store.issues.push(ConfigIssue::new(
+                    "",
+                    format!("can not read {}: {}, using defaults", store.path.display(), err),
+                ));
+                GameConfig::default()
+            }
+        };
+
+        (config, store)
+    }
+
+    /// Writes `config` to [`ConfigStore::path`], creating the parent directory if needed.
+    pub fn save(&self, config: &GameConfig) -> Result<(), Box<dyn std::error::Error>> {
+        let mut table = toml::Table::try_from(config)?;
+        for (key, value) in self.unknown.iter() {
+            table.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, toml::to_string_pretty(&table)?)?;
+
+        Ok(())
+    }
+
+    /// Restores `config` to its defaults and clears the recorded issues.
+    ///
+    /// Unknown keys are kept, they are not ours to discard.
+    pub fn reset(&mut self, config: &mut GameConfig) {
+        *config = GameConfig::default();
+        self.issues.clear();
+    }
+
+    fn parse(&mut self, content: &str) -> GameConfig {
+        let file_table = match content.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(err) => {
+                self.issues.push(ConfigIssue::new(
+                    "",
+                    format!("{} is not valid TOML: {}, using defaults", self.path.display(), err),
+                ));
+                return GameConfig::default();
+            }
+        };
+
+        let mut merged =
+            toml::Table::try_from(GameConfig::default()).expect("GameConfig must serialize to TOML");
+
+        // 逐个字段覆盖默认值，类型错误的字段保留默认值
+        for (key, value) in file_table {
+            if !merged.contains_key(&key) {
+                self.unknown.insert(key, value);
+                continue;
+            }
+
+            let mut candidate = merged.clone();
+            candidate.insert(key.clone(), value);
+            match candidate.clone().try_into::<GameConfig>() {
+                Ok(_) => merged = candidate,
+                Err(err) => self.issues.push(ConfigIssue::new(
+                    key,
+                    format!("invalid value ({}), using default", err.message()),
+                )),
+            }
+        }
+
+        let mut config = merged
+            .try_into::<GameConfig>()
+            .expect("merged table only holds validated values");
+        self.issues.extend(sanitize(&mut config));
+        config
+    }
+}
+
+/// Clamps every value of `config` into its valid range, returning one issue per changed value.
+pub fn sanitize(config: &mut GameConfig) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+    let defaults = GameConfig::default();
+
+    let (width, height) = config.resolution;
+    let clamped = (
+        clamp_or(width, RESOLUTION_MIN.0, RESOLUTION_MAX.0, defaults.resolution.0),
+        clamp_or(height, RESOLUTION_MIN.1, RESOLUTION_MAX.1, defaults.resolution.1),
+    );
+    if clamped != config.resolution {
+        issues.push(ConfigIssue::new(
+            "resolution",
+            format!(
+                "{:?} is out of range, using {:?}",
+                config.resolution, clamped
+            ),
+        ));
+        config.resolution = clamped;
+    }
+
+    if config.title.trim().is_empty() {
+        issues.push(ConfigIssue::new("title", "empty title, using default"));
+        config.title = defaults.title.clone();
+    }
+
+    if !MSAA_SAMPLES.contains(&config.msaa_samples) {
+        // 取不超过给定值的最大合法采样数
+        let samples = MSAA_SAMPLES
+            .iter()
+            .rev()
+            .find(|samples| **samples <= config.msaa_samples)
+            .copied()
+            .unwrap_or(1);
+        issues.push(ConfigIssue::new(
+            "msaa_samples",
+            format!(
+                "{} is not one of {:?}, using {}",
+                config.msaa_samples, MSAA_SAMPLES, samples
+            ),
+        ));
+        config.msaa_samples = samples;
+    }
+
+    let fps = config
+        .target_fps
+        .clamp(TARGET_FPS_RANGE.0, TARGET_FPS_RANGE.1);
+    if fps != config.target_fps {
+        issues.push(ConfigIssue::new(
+            "target_fps",
+            format!("{} is out of range, using {}", config.target_fps, fps),
+        ));
+        config.target_fps = fps;
+    }
+
+    let volume = clamp_or(config.master_volume, 0.0, 1.0, defaults.master_volume);
+    if volume != config.master_volume {
+        issues.push(ConfigIssue::new(
+            "master_volume",
+            format!("{} is out of range, using {}", config.master_volume, volume),
+        ));
+        config.master_volume = volume;
+    }
+
+    let text_speed = clamp_or(
+        config.text_speed,
+        TEXT_SPEED_RANGE.0,
+        TEXT_SPEED_RANGE.1,
+        defaults.text_speed,
+    );
+    if text_speed != config.text_speed {
+        issues.push(ConfigIssue::new(
+            "text_speed",
+            format!("{} is out of range, using {}", config.text_speed, text_speed),
+        ));
+        config.text_speed = text_speed;
+    }
+
+    issues
+}
+
+fn clamp_or(value: f32, min: f32, max: f32, fallback: f32) -> f32 {
+    if value.is_finite() {
+        value.clamp(min, max)
+    } else {
+        fallback
+    }
+}
+
+/// Picks the config file path from the command line, the environment or the platform default.
+pub fn resolve_config_path(args: impl IntoIterator<Item = String>) -> PathBuf {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == CONFIG_CLI_FLAG {
+            if let Some(path) = args.next() {
+                return PathBuf::from(path);
+            }
+        } else if let Some(path) = arg.strip_prefix(CONFIG_CLI_FLAG).and_then(|p| p.strip_prefix('=')) {
+            return PathBuf::from(path);
+        }
+    }
+
+    if let Some(path) = env::var_os(CONFIG_ENV_VAR).filter(|path| !path.is_empty()) {
+        return PathBuf::from(path);
+    }
+
+    default_config_path()
+}
+
+/// The config file inside the platform user data directory,
+/// falling back to the working directory when there is none.
+pub fn default_config_path() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_DIR_NAME))
+        .unwrap_or_else(|| Path::new(".").to_path_buf())
+        .join(CONFIG_FILE_NAME)
+}
@@ -1,8 +1,537 @@
+use crate::core::catalogue::AssetCatalogue;
+use crate::core::script::Script;
+use crate::core::story::{track_story, StoryHistory, StoryState};
+use bevy::core::FrameCount;
 use bevy::prelude::*;
-/// A generic event structure that can hold any type of data.
-/// This event can be used within the Bevy framework's event system.
-#[derive(Clone, Event)]
-pub(crate) struct GenericEvent<T> {
-    /// The data associated with the event.
-    pub(crate) data: T,
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// Environment variable naming a file the command stream of the session is
+/// recorded to, written when the app exits.
+pub const COMMAND_LOG_ENV_VAR: &str = "AVG_COMMAND_LOG";
+
+/// How many jumps a script may take in one frame before it is taken to loop
+/// forever.
+const MAX_JUMPS_PER_FRAME: usize = 1000;
+
+/// A Bevy Plugin running [`VnCommand`]s pushed to the [`CommandBus`].
+///
+/// Commands run one after another in the order they were pushed. Every
+/// command is announced with a [`RunCommand`] event; the bus then moves on
+/// immediately, unless the command [blocks](VnCommand::is_blocking), in which
+/// case it waits until the system handling it calls [`CommandBus::complete`].
+/// The bus handles [`VnCommand::Wait`] and the jumps of the [`Script`] it
+/// [started](CommandBus::start) itself, and keeps the [`StoryState`]
+/// up to date with every command it runs. Asset ids in commands are replaced
+/// by their paths when an [`AssetCatalogue`] exists.
+pub struct CommandBusPlugin;
+
+/// A single step of the visual novel runtime.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum VnCommand {
+    ShowBackground { path: String },
+    /// Shows the character image at `path`, or the `layers` of the
+    /// character atlas at `path`, bottom first.
+    ShowCharacter {
+        path: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        layers: Vec<String>,
+    },
+    HideCharacter,
+    PlayMusic { path: String, looped: bool },
+    StopMusic,
+    /// Plays a voice line once, over the music.
+    PlayVoice { path: String },
+    /// Shows a dialogue line; completes when the player advances past it.
+    ///
+    /// `id` names the translations of `text`.
+    Say {
+        speaker: Option<String>,
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+    },
+    /// Offers the `options` whose conditions hold; completes when one is
+    /// picked, storing its index in `variable`.
+    ///
+    /// `ids` names the translations of the options, when given.
+    Choice {
+        options: Vec<String>,
+        variable: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        conditions: Vec<OptionCondition>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        ids: Vec<String>,
+    },
+    /// Does nothing for `seconds`.
+    Wait { seconds: f32 },
+    SetVar { name: String, value: VnValue },
+    /// Continues the script at `label`.
+    Jump { label: String },
+    /// Continues the script at `label` when `condition` holds.
+    JumpIf { condition: Condition, label: String },
+    /// Ends the story.
+    End,
+}
+
+impl VnCommand {
+    /// Whether the bus waits for the command to complete before running the next one.
+    pub fn is_blocking(&self) -> bool {
+        matches!(
+            self,
+            VnCommand::Say { .. } | VnCommand::Choice { .. } | VnCommand::Wait { .. }
+        )
+    }
+
+    /// The asset the command loads, if any.
+    pub fn asset_path(&self) -> Option<&str> {
+        match self {
+            VnCommand::ShowBackground { path }
+            | VnCommand::ShowCharacter { path, .. }
+            | VnCommand::PlayMusic { path, .. }
+            | VnCommand::PlayVoice { path } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Whether the command only decides which command runs next.
+    pub fn is_control_flow(&self) -> bool {
+        matches!(
+            self,
+            VnCommand::Jump { .. } | VnCommand::JumpIf { .. } | VnCommand::End
+        )
+    }
+}
+
+/// A test of a script variable: whether it is set to `value`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Condition {
+    pub variable: String,
+    pub value: VnValue,
+}
+
+impl Condition {
+    pub fn holds(&self, variables: &BTreeMap<String, VnValue>) -> bool {
+        variables.get(&self.variable) == Some(&self.value)
+    }
+}
+
+/// Written as in a script, e.g. `route == 1`.
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} == {}", self.variable, self.value)
+    }
+}
+
+/// Offers option `option` of a [`VnCommand::Choice`] only when `condition` holds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OptionCondition {
+    pub option: usize,
+    pub condition: Condition,
+}
+
+/// Whether option `option` of a choice with `conditions` is offered.
+pub fn is_option_shown(
+    conditions: &[OptionCondition],
+    option: usize,
+    variables: &BTreeMap<String, VnValue>,
+) -> bool {
+    conditions
+        .iter()
+        .filter(|condition| condition.option == option)
+        .all(|condition| condition.condition.holds(variables))
+}
+
+/// The value of a script variable.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum VnValue {
+    Bool(bool),
+    Int(i64),
+    Text(String),
+}
+
+/// Written as in a script: `true`, `1` or `"text"`.
+impl fmt::Display for VnValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VnValue::Bool(value) => write!(f, "{value}"),
+            VnValue::Int(value) => write!(f, "{value}"),
+            VnValue::Text(text) => write!(f, "{text:?}"),
+        }
+    }
+}
+
+/// Identifies one run of a command, in the order commands were run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct CommandId(pub u64);
+
+/// Sent for every command the [`CommandBus`] runs, in order.
+#[derive(Event, Clone, Debug)]
+pub struct RunCommand {
+    pub id: CommandId,
+    pub command: VnCommand,
+}
+
+/// Sent when a blocking command has completed.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommandCompleted {
+    pub id: CommandId,
+}
+
+/// The ordered queue of [`VnCommand`]s waiting to run.
+#[derive(Resource, Default)]
+pub struct CommandBus {
+    queue: VecDeque<VnCommand>,
+    /// The blocking command being waited on.
+    active: Option<(CommandId, VnCommand)>,
+    completed: Vec<CommandId>,
+    next_id: u64,
+    wait: Option<Timer>,
+    /// The commands of the started script, and where each label starts.
+    script: Vec<VnCommand>,
+    labels: BTreeMap<String, usize>,
+}
+
+impl CommandBus {
+    /// Queues `command` behind every command pushed before it.
+    pub fn push(&mut self, command: VnCommand) {
+        self.queue.push_back(command);
+    }
+
+    pub fn extend(&mut self, commands: impl IntoIterator<Item = VnCommand>) {
+        self.queue.extend(commands);
+    }
+
+    /// Drops everything queued and runs `script` from the start, following
+    /// its jumps.
+    pub fn start(&mut self, script: &Script) {
+        self.script = script.commands().cloned().collect();
+        self.labels = script
+            .labels
+            .iter()
+            .map(|(name, label)| (name.clone(), label.statement))
+            .collect();
+        self.clear();
+        self.extend(self.script.clone());
+    }
+
+    /// Queues the commands of a recorded session, see [`CommandLog`].
+    ///
+    /// The jumps were already taken when the session was recorded and are
+    /// left out.
+    pub fn replay(&mut self, entries: &[RecordedCommand]) {
+        self.extend(
+            entries
+                .iter()
+                .filter(|entry| !entry.command.is_control_flow())
+                .map(|entry| entry.command.clone()),
+        );
+    }
+
+    /// Replaces the queue by the started script from `label` on.
+    fn jump(&mut self, label: &str) {
+        self.queue.clear();
+        match self.labels.get(label) {
+            Some(&start) => self.queue.extend(self.script[start..].iter().cloned()),
+            None => error!("script jumps to unknown label {label}"),
+        }
+    }
+
+    /// Drops the queued commands and stops waiting for the active one.
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.active = None;
+        self.wait = None;
+    }
+
+    /// The blocking command being waited on.
+    pub fn active(&self) -> Option<(CommandId, &VnCommand)> {
+        self.active.as_ref().map(|(id, command)| (*id, command))
+    }
+
+    /// Whether the active command waits for the player to read a line or pick an option.
+    pub fn is_waiting_for_player(&self) -> bool {
+        matches!(
+            self.active(),
+            Some((_, VnCommand::Say { .. } | VnCommand::Choice { .. }))
+        )
+    }
+
+    /// The active command followed by the queued ones, i.e. everything left to run.
+    pub fn pending(&self) -> Vec<VnCommand> {
+        self.active
+            .iter()
+            .map(|(_, command)| command.clone())
+            .chain(self.queue.iter().cloned())
+            .collect()
+    }
+
+    /// Whether no command is running or queued.
+    pub fn is_idle(&self) -> bool {
+        self.active.is_none() && self.queue.is_empty()
+    }
+
+    /// Marks the blocking command `id` as completed, letting the next one run.
+    ///
+    /// Returns `false` when `id` is not the active command, e.g. because it
+    /// already completed.
+    pub fn complete(&mut self, id: CommandId) -> bool {
+        if self.active.as_ref().map(|(active, _)| *active) != Some(id) {
+            return false;
+        }
+        self.active = None;
+        self.wait = None;
+        self.completed.push(id);
+        true
+    }
+}
+
+/// One command run by the bus, as recorded by [`CommandLog`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedCommand {
+    /// The frame the command ran in.
+    pub frame: u32,
+    pub id: CommandId,
+    pub command: VnCommand,
+}
+
+/// The on-disk layout of a command log.
+#[derive(Serialize, Deserialize)]
+struct CommandLogFile {
+    commands: Vec<RecordedCommand>,
+}
+
+/// Records the commands run by the bus, for logging and replaying a session.
+///
+/// Hooks see every command as it runs, whether recording is enabled or not.
+#[derive(Resource, Default)]
+pub struct CommandLog {
+    /// Whether run commands are kept in `entries`.
+    pub enabled: bool,
+    pub entries: Vec<RecordedCommand>,
+    hooks: Vec<CommandHook>,
+}
+
+/// A callback of [`CommandLog::add_hook`].
+type CommandHook = Box<dyn Fn(&RecordedCommand) + Send + Sync>;
+
+impl CommandLog {
+    /// Calls `hook` with every command the bus runs from now on.
+    pub fn add_hook(&mut self, hook: impl Fn(&RecordedCommand) + Send + Sync + 'static) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Writes the recorded entries to `path` as TOML.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let file = CommandLogFile {
+            commands: self.entries.clone(),
+        };
+        fs::write(path, toml::to_string_pretty(&file)?)?;
+        Ok(())
+    }
+
+    /// Reads entries written by [`CommandLog::save`], for [`CommandBus::replay`].
+    pub fn load(path: &Path) -> Result<Vec<RecordedCommand>, Box<dyn std::error::Error>> {
+        let file: CommandLogFile = toml::from_str(&fs::read_to_string(path)?)?;
+        Ok(file.commands)
+    }
+
+    fn record(&mut self, entry: RecordedCommand) {
+        for hook in self.hooks.iter() {
+            hook(&entry);
+        }
+        if self.enabled {
+            self.entries.push(entry);
+        }
+    }
+}
+
+impl Plugin for CommandBusPlugin {
+    fn build(&self, app: &mut App) {
+        let log = CommandLog {
+            enabled: std::env::var_os(COMMAND_LOG_ENV_VAR).is_some(),
+            ..default()
+        };
+        app.add_event::<RunCommand>()
+            .add_event::<CommandCompleted>()
+            .init_resource::<CommandBus>()
+            .init_resource::<StoryState>()
+            .init_resource::<StoryHistory>()
+            .insert_resource(log)
+            .add_systems(PreUpdate, (tick_wait, run_commands, track_story).chain())
+            .add_systems(Last, save_command_log);
+    }
+}
+
+fn tick_wait(time: Res<Time>, mut bus: ResMut<CommandBus>) {
+    let Some(timer) = bus.wait.as_mut() else {
+        return;
+    };
+    if !timer.tick(time.delta()).finished() {
+        return;
+    }
+    if let Some((id, _)) = bus.active() {
+        bus.complete(id);
+    }
+}
+
+/// Runs queued commands until one blocks, replacing asset ids by their paths.
+fn run_commands(
+    mut bus: ResMut<CommandBus>,
+    state: Res<StoryState>,
+    catalogue: Option<Res<AssetCatalogue>>,
+    mut log: ResMut<CommandLog>,
+    frame: Option<Res<FrameCount>>,
+    mut run_events: EventWriter<RunCommand>,
+    mut completed_events: EventWriter<CommandCompleted>,
+) {
+    let bus = &mut *bus;
+    for id in bus.completed.drain(..) {
+        completed_events.send(CommandCompleted { id });
+    }
+
+    // 本帧设置的变量要到 track_story 才写入 StoryState，条件跳转需要先看到它们
+    let mut variables = state.variables.clone();
+    let mut jumps = 0;
+    while bus.active.is_none() {
+        let Some(mut command) = bus.queue.pop_front() else {
+            break;
+        };
+        if let Some(catalogue) = &catalogue {
+            command = catalogue.resolve_command(command);
+        }
+        let id = CommandId(bus.next_id);
+        bus.next_id += 1;
+        log.record(RecordedCommand {
+            frame: frame.as_ref().map_or(0, |frame| frame.0),
+            id,
+            command: command.clone(),
+        });
+
+        if let VnCommand::Wait { seconds } = &command {
+            bus.wait = Some(Timer::new(
+                Duration::from_secs_f32(seconds.max(0.0)),
+                TimerMode::Once,
+            ));
+        }
+        match &command {
+            VnCommand::SetVar { name, value } => {
+                variables.insert(name.clone(), value.clone());
+            }
+            VnCommand::Jump { label } => bus.jump(label),
+            VnCommand::JumpIf { condition, label } if condition.holds(&variables) => {
+                bus.jump(label)
+            }
+            VnCommand::End => bus.queue.clear(),
+            _ => {}
+        }
+        if matches!(command, VnCommand::Jump { .. } | VnCommand::JumpIf { .. }) {
+            jumps += 1;
+            if jumps > MAX_JUMPS_PER_FRAME {
+                error!("script keeps jumping without waiting for the player, stopping it");
+                bus.queue.clear();
+            }
+        }
+        if command.is_blocking() {
+            bus.active = Some((id, command.clone()));
+        }
+        run_events.send(RunCommand { id, command });
+    }
+}
+
+fn save_command_log(mut exit_events: EventReader<AppExit>, log: Res<CommandLog>) {
+    if exit_events.read().last().is_none() || !log.enabled {
+        return;
+    }
+    let Some(path) = std::env::var_os(COMMAND_LOG_ENV_VAR) else {
+        return;
+    };
+    let path = Path::new(&path);
+    match log.save(path) {
+        Ok(()) => info!("command log saved to {}", path.display()),
+        Err(err) => error!("can not save command log to {}: {}", path.display(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::script::text_id;
+    use crate::utils::test_harness::TestApp;
+
+    #[test]
+    fn follows_jumps_and_conditions() {
+        let script = Script::parse(
+            r#"set x = 1
+if x == 2 jump a
+if x == 1 jump b
+label a
+"a"
+label b
+"b"
+jump a
+"#,
+        )
+        .unwrap();
+        let mut app = TestApp::new(CommandBusPlugin);
+        app.world_mut().resource_mut::<CommandBus>().start(&script);
+        app.update();
+
+        let say = |text: &str| VnCommand::Say {
+            speaker: None,
+            text: text.to_string(),
+            id: Some(text_id(text, None, text)),
+        };
+        let active = |app: &TestApp| {
+            let bus = app.world().resource::<CommandBus>();
+            bus.active().map(|(_, command)| command.clone())
+        };
+        assert_eq!(active(&app), Some(say("b")));
+        assert_eq!(
+            app.world().resource::<StoryState>().variables["x"],
+            VnValue::Int(1)
+        );
+
+        let id = app.world().resource::<CommandBus>().active().unwrap().0;
+        app.world_mut().resource_mut::<CommandBus>().complete(id);
+        app.update();
+        assert_eq!(active(&app), Some(say("a")));
+    }
+
+    #[test]
+    fn keeps_the_lines_read_for_the_backlog() {
+        let script = Script::parse(
+            r#""a"
+Alice: "b"
+choice c
+    "go" -> end
+label end
+"c"
+"#,
+        )
+        .unwrap();
+        let mut app = TestApp::new(CommandBusPlugin);
+        app.world_mut().resource_mut::<CommandBus>().start(&script);
+        app.update();
+        for _ in 0..3 {
+            let mut bus = app.world_mut().resource_mut::<CommandBus>();
+            let id = bus.active().unwrap().0;
+            bus.complete(id);
+            app.update();
+        }
+
+        let world = app.world();
+        let lines = world
+            .resource::<StoryHistory>()
+            .lines(world.resource::<StoryState>());
+        let lines: Vec<(Option<&str>, &str)> = lines
+            .iter()
+            .map(|line| (line.speaker.as_deref(), line.text.as_str()))
+            .collect();
+        assert_eq!(lines, [(None, "a"), (Some("Alice"), "b"), (None, "c")]);
+    }
 }
@@ -0,0 +1,316 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::catalogue::AssetCatalogue;
+use crate::core::po::parse_po;
+use crate::global_def::global_define::GameConfig;
+
+/// The folder below the asset folder holding the translations, one
+/// `<language>.po` file per language.
+pub const LOCALE_DIR: &str = "locales";
+
+/// A Bevy Plugin showing the text of the game in the language of
+/// [`GameConfig::language`].
+///
+/// Translations are read at startup from the PO files in [`LOCALE_DIR`],
+/// e.g. `assets/locales/en.po`, each message naming the id of the text it
+/// translates in its `msgctxt`. Give UI text a [`LocalizedText`] and it is
+/// replaced by its translation, also when the language changes. Texts
+/// without a translation show the source text, with a warning.
+pub struct LocalePlugin {
+    /// `file_path` of the `AssetPlugin`.
+    pub file_path: String,
+    /// The language the scripts and UI are written in.
+    pub source_language: String,
+}
+
+impl Default for LocalePlugin {
+    fn default() -> Self {
+        Self {
+            file_path: "assets".to_string(),
+            source_language: "zh".to_string(),
+        }
+    }
+}
+
+/// The translations of a language, by text id.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StringTable {
+    entries: BTreeMap<String, String>,
+}
+
+impl StringTable {
+    /// Reads the messages of a PO file with a context and a translation.
+    /// Fuzzy translations are left out.
+    pub fn from_po(text: &str) -> Result<Self, String> {
+        let mut table = StringTable::default();
+        for entry in parse_po(text)? {
+            let Some(id) = entry.context.as_ref() else {
+                continue;
+            };
+            if entry.translation.is_empty() || entry.is_fuzzy() {
+                continue;
+            }
+            table.insert(id.clone(), entry.translation);
+        }
+        Ok(table)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.entries.get(id).map(String::as_str)
+    }
+
+    pub fn insert(&mut self, id: impl Into<String>, translation: impl Into<String>) {
+        self.entries.insert(id.into(), translation.into());
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// The language shown and the translations into every language.
+#[derive(Resource, Clone, Debug)]
+pub struct Locale {
+    source_language: String,
+    language: String,
+    tables: BTreeMap<String, StringTable>,
+}
+
+impl Locale {
+    /// A locale showing the source text, without translations.
+    pub fn new(source_language: impl Into<String>) -> Self {
+        let source_language = source_language.into();
+        Self {
+            language: source_language.clone(),
+            source_language,
+            tables: BTreeMap::new(),
+        }
+    }
+
+    /// Reads every `<language>.po` file in `dir`, returning the files that
+    /// can not be read.
+    pub fn load_dir(&mut self, dir: &Path) -> Vec<(PathBuf, String)> {
+        let mut failed = Vec::new();
+        let Ok(entries) = fs::read_dir(dir) else {
+            return failed;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "po"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let Some(language) = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+            else {
+                continue;
+            };
+            let table = fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|text| StringTable::from_po(&text));
+            match table {
+                Ok(table) => self.insert(language, table),
+                Err(err) => failed.push((path, err)),
+            }
+        }
+        failed
+    }
+
+    pub fn insert(&mut self, language: impl Into<String>, table: StringTable) {
+        self.tables.insert(language.into(), table);
+    }
+
+    pub fn source_language(&self) -> &str {
+        &self.source_language
+    }
+
+    /// The language shown.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// The source language and every language with translations.
+    pub fn languages(&self) -> Vec<&str> {
+        let mut languages = vec![self.source_language.as_str()];
+        languages.extend(
+            self.tables
+                .keys()
+                .map(String::as_str)
+                .filter(|language| *language != self.source_language),
+        );
+        languages
+    }
+
+    /// Shows `language`; returns `false` and keeps the language shown when
+    /// there are no translations into it.
+    pub fn set_language(&mut self, language: &str) -> bool {
+        if language != self.source_language && !self.tables.contains_key(language) {
+            return false;
+        }
+        self.language = language.to_string();
+        true
+    }
+
+    /// The translation of the text `id` into the language shown, or its
+    /// `source` text when there is none.
+    pub fn text<'a>(&'a self, id: &str, source: &'a str) -> &'a str {
+        if self.language == self.source_language {
+            return source;
+        }
+        match self
+            .tables
+            .get(&self.language)
+            .and_then(|table| table.get(id))
+        {
+            Some(translation) => translation,
+            None => {
+                warn!(
+                    "no {} translation of {}, showing the source text",
+                    self.language, id
+                );
+                source
+            }
+        }
+    }
+}
+
+/// The id of a speaker's name in the string tables.
+pub fn speaker_id(speaker: &str) -> String {
+    format!("speaker.{speaker}")
+}
+
+/// Shows the translation of a text in the first section of the entity's
+/// `Text`.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct LocalizedText {
+    pub id: String,
+    /// What is shown in the source language, and when there is no
+    /// translation.
+    pub source: String,
+}
+
+impl LocalizedText {
+    pub fn new(id: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            source: source.into(),
+        }
+    }
+}
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut App) {
+        // 翻译文件与资源目录放在一起
+        let root = match app.world().get_resource::<AssetCatalogue>() {
+            Some(catalogue) => catalogue.root().to_path_buf(),
+            None => FileAssetReader::new(&self.file_path).root_path().clone(),
+        };
+        let mut locale = Locale::new(&self.source_language);
+        let dir = root.join(LOCALE_DIR);
+        for (path, err) in locale.load_dir(&dir) {
+            error!("can not read translations {}: {}", path.display(), err);
+        }
+        info!("languages: {}", locale.languages().join(", "));
+
+        app.insert_resource(locale)
+            .add_systems(PreUpdate, follow_config_language)
+            .add_systems(PostUpdate, update_localized_texts);
+    }
+}
+
+fn follow_config_language(config: Option<Res<GameConfig>>, mut locale: ResMut<Locale>) {
+    let Some(config) = config.filter(|config| config.is_changed()) else {
+        return;
+    };
+    if config.language == locale.language() {
+        return;
+    }
+    if !locale.set_language(&config.language) {
+        warn!(
+            "no translations into {}, showing {}",
+            config.language,
+            locale.language()
+        );
+    }
+}
+
+fn update_localized_texts(locale: Res<Locale>, mut query: Query<(Ref<LocalizedText>, &mut Text)>) {
+    for (localized, mut text) in query.iter_mut() {
+        if !(locale.is_changed() || localized.is_changed()) {
+            continue;
+        }
+        let value = locale.text(&localized.id, &localized.source);
+        if let Some(section) = text.sections.first_mut() {
+            if section.value != value {
+                section.value = value.to_string();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_harness::TestApp;
+
+    const EN: &str = r#"msgctxt "ui.audio_play"
+msgid "AudioPlay"
+msgstr "Play music"
+
+#, fuzzy
+msgctxt "ui.stop_play"
+msgid "StopPlay"
+msgstr "Stop musik"
+"#;
+
+    fn locale() -> Locale {
+        let mut locale = Locale::new("zh");
+        locale.insert("en", StringTable::from_po(EN).unwrap());
+        locale
+    }
+
+    #[test]
+    fn falls_back_to_the_source_text() {
+        let mut locale = locale();
+        assert_eq!(locale.languages(), ["zh", "en"]);
+        assert_eq!(locale.text("ui.audio_play", "AudioPlay"), "AudioPlay");
+        assert!(!locale.set_language("fr"));
+        assert!(locale.set_language("en"));
+        assert_eq!(locale.text("ui.audio_play", "AudioPlay"), "Play music");
+        // 模糊翻译不使用
+        assert_eq!(locale.text("ui.stop_play", "StopPlay"), "StopPlay");
+    }
+
+    #[test]
+    fn switches_texts_with_the_config_language() {
+        let mut app = TestApp::new(|app: &mut App| {
+            app.insert_resource(locale())
+                .init_resource::<GameConfig>()
+                .add_systems(PreUpdate, follow_config_language)
+                .add_systems(PostUpdate, update_localized_texts);
+        });
+        let label = app
+            .world_mut()
+            .spawn((
+                TextBundle::from_section("", TextStyle::default()),
+                LocalizedText::new("ui.audio_play", "AudioPlay"),
+            ))
+            .id();
+        app.update();
+        assert_eq!(app.get::<Text>(label).sections[0].value, "AudioPlay");
+
+        app.world_mut().resource_mut::<GameConfig>().language = "en".to_string();
+        app.update();
+        assert_eq!(app.get::<Text>(label).sections[0].value, "Play music");
+        assert_eq!(app.world().resource::<Locale>().language(), "en");
+    }
+}
@@ -1,2 +1,9 @@
+pub mod atlas;
+pub mod catalogue;
+pub mod config_store;
 pub mod event_bus;
-mod factory;
+pub mod locale;
+pub mod po;
+pub mod save_store;
+pub mod script;
+pub mod story;
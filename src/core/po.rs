@@ -0,0 +1,268 @@
+use std::fmt::Write;
+
+/// One message of a gettext PO file.
+///
+/// The context (`msgctxt`) holds the id of the text, the source text is the
+/// `msgid` and its translation the `msgstr`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoEntry {
+    /// Translator comments, `# ...`.
+    pub comments: Vec<String>,
+    /// Comments for translators written by the extraction tool, `#. ...`.
+    pub extracted: Vec<String>,
+    /// Where the text is used, `#: file:line`.
+    pub references: Vec<String>,
+    /// Flags such as `fuzzy`, `#, ...`.
+    pub flags: Vec<String>,
+    /// The source text the translation was made for, when it changed since,
+    /// `#| msgid ...`.
+    pub previous: Option<String>,
+    pub context: Option<String>,
+    pub source: String,
+    pub translation: String,
+}
+
+impl PoEntry {
+    /// The entry with an empty source text holding the file's metadata.
+    pub fn is_header(&self) -> bool {
+        self.source.is_empty() && self.context.is_none()
+    }
+
+    /// Whether the translation needs review before it is used.
+    pub fn is_fuzzy(&self) -> bool {
+        self.flags.iter().any(|flag| flag == "fuzzy")
+    }
+
+    pub fn set_fuzzy(&mut self, fuzzy: bool) {
+        self.flags.retain(|flag| flag != "fuzzy");
+        if fuzzy {
+            self.flags.push("fuzzy".to_string());
+        }
+    }
+}
+
+/// The field the continuation lines of a PO entry belong to.
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Context,
+    Source,
+    Translation,
+    Previous,
+}
+
+/// Parses the entries of a PO file, in file order. Obsolete entries
+/// (`#~`) and plural forms are skipped.
+pub fn parse_po(text: &str) -> Result<Vec<PoEntry>, String> {
+    let mut entries = Vec::new();
+    let mut entry = PoEntry::default();
+    let mut started = false;
+    let mut field: Option<Field> = None;
+
+    for (index, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        let error = |message: String| format!("{}: {}", index + 1, message);
+        // 注释或 msgctxt/msgid 出现在 msgstr 之后表示新条目开始
+        let starts_entry =
+            line.starts_with('#') || line.starts_with("msgctxt") || line.starts_with("msgid ");
+        if starts_entry && field == Some(Field::Translation) {
+            entries.push(std::mem::take(&mut entry));
+            started = false;
+            field = None;
+        }
+        if line.is_empty() || line.starts_with("#~") {
+            continue;
+        }
+
+        if let Some(comment) = line.strip_prefix("#.") {
+            entry.extracted.push(comment.trim().to_string());
+        } else if let Some(reference) = line.strip_prefix("#:") {
+            entry
+                .references
+                .extend(reference.split_whitespace().map(str::to_string));
+        } else if let Some(flags) = line.strip_prefix("#,") {
+            entry.flags.extend(
+                flags
+                    .split(',')
+                    .map(|flag| flag.trim().to_string())
+                    .filter(|flag| !flag.is_empty()),
+            );
+        } else if let Some(previous) = line.strip_prefix("#|") {
+            let previous = previous.trim();
+            if let Some(value) = previous.strip_prefix("msgid ") {
+                entry.previous = Some(unquote(value).map_err(error)?);
+                field = Some(Field::Previous);
+            } else if previous.starts_with('"') && field == Some(Field::Previous) {
+                let value = unquote(previous).map_err(error)?;
+                entry
+                    .previous
+                    .get_or_insert_with(String::new)
+                    .push_str(&value);
+            }
+        } else if let Some(comment) = line.strip_prefix('#') {
+            entry.comments.push(comment.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("msgctxt ") {
+            entry.context = Some(unquote(value).map_err(error)?);
+            field = Some(Field::Context);
+            started = true;
+        } else if let Some(value) = line.strip_prefix("msgid ") {
+            entry.source = unquote(value).map_err(error)?;
+            field = Some(Field::Source);
+            started = true;
+        } else if let Some(value) = line.strip_prefix("msgstr ") {
+            if !started {
+                return Err(error("msgstr without msgid".to_string()));
+            }
+            entry.translation = unquote(value).map_err(error)?;
+            field = Some(Field::Translation);
+        } else if line.starts_with('"') {
+            let value = unquote(line).map_err(error)?;
+            match field {
+                Some(Field::Context) => entry
+                    .context
+                    .get_or_insert_with(String::new)
+                    .push_str(&value),
+                Some(Field::Source) => entry.source.push_str(&value),
+                Some(Field::Translation) => entry.translation.push_str(&value),
+                _ => return Err(error("string outside of an entry".to_string())),
+            }
+        } else if line.starts_with("msgid_plural") || line.starts_with("msgstr[") {
+            // 不支持复数形式
+            continue;
+        } else {
+            return Err(error(format!("unexpected `{line}`")));
+        }
+    }
+    if field == Some(Field::Translation) {
+        entries.push(entry);
+    } else if started {
+        return Err("the last entry has no msgstr".to_string());
+    }
+    Ok(entries)
+}
+
+/// Writes `entries` as a PO file.
+pub fn write_po(entries: &[PoEntry]) -> String {
+    let mut po = String::new();
+    for (index, entry) in entries.iter().enumerate() {
+        if index > 0 {
+            po.push('\n');
+        }
+        for comment in entry.comments.iter() {
+            let _ = writeln!(po, "# {comment}");
+        }
+        for comment in entry.extracted.iter() {
+            let _ = writeln!(po, "#. {comment}");
+        }
+        if !entry.references.is_empty() {
+            let _ = writeln!(po, "#: {}", entry.references.join(" "));
+        }
+        if !entry.flags.is_empty() {
+            let _ = writeln!(po, "#, {}", entry.flags.join(", "));
+        }
+        if let Some(previous) = &entry.previous {
+            let _ = writeln!(po, "#| msgid {}", quote(previous));
+        }
+        if let Some(context) = &entry.context {
+            let _ = writeln!(po, "msgctxt {}", quote(context));
+        }
+        write_field(&mut po, "msgid", &entry.source);
+        write_field(&mut po, "msgstr", &entry.translation);
+    }
+    po
+}
+
+/// Writes a field, one quoted string per line of a text with several.
+fn write_field(po: &mut String, name: &str, value: &str) {
+    let lines: Vec<&str> = value.split_inclusive('\n').collect();
+    if lines.len() <= 1 {
+        let _ = writeln!(po, "{name} {}", quote(value));
+        return;
+    }
+    let _ = writeln!(po, "{name} \"\"");
+    for line in lines {
+        let _ = writeln!(po, "{}", quote(line));
+    }
+}
+
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn unquote(text: &str) -> Result<String, String> {
+    let inner = text
+        .trim()
+        .strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .ok_or_else(|| format!("expected a quoted string, got `{text}`"))?;
+    let mut value = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some(escaped @ ('"' | '\\')) => value.push(escaped),
+            other => return Err(format!("unknown escape `\\{}`", other.unwrap_or(' '))),
+        }
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_what_it_writes() {
+        let entries = vec![
+            PoEntry {
+                source: String::new(),
+                translation: "Language: en\n".to_string(),
+                ..Default::default()
+            },
+            PoEntry {
+                comments: vec!["checked".to_string()],
+                extracted: vec!["Alice".to_string()],
+                references: vec!["scripts/prologue.avg:5".to_string()],
+                flags: vec!["fuzzy".to_string()],
+                previous: Some("旧的\"台词\"".to_string()),
+                context: Some("start.3f2a9c1e".to_string()),
+                source: "欢迎游玩DS \n开始游戏".to_string(),
+                translation: "Welcome to DS\nStart the game".to_string(),
+            },
+        ];
+        let po = write_po(&entries);
+        assert!(po.contains("msgid \"\"\n\"欢迎游玩DS \\n\"\n\"开始游戏\"\n"));
+        let parsed = parse_po(&po).unwrap();
+        assert_eq!(parsed, entries);
+        assert!(parsed[0].is_header());
+        assert!(parsed[1].is_fuzzy());
+    }
+
+    #[test]
+    fn reports_the_line_of_an_error() {
+        assert_eq!(
+            parse_po("msgid \"a\"\nmsgstr \"b\"\n\nmsgid \"c\nmsgstr \"\"").unwrap_err(),
+            "4: expected a quoted string, got `\"c`"
+        );
+        assert_eq!(
+            parse_po("msgstr \"b\"").unwrap_err(),
+            "1: msgstr without msgid"
+        );
+    }
+}
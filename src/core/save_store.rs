@@ -0,0 +1,112 @@
+use crate::core::story::StoryHistory;
+use bevy::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The file the save slots are written to, next to the config file.
+pub const SAVE_FILE_NAME: &str = "saves.ron";
+
+/// Where the save slots and persistent variables of the [`StoryHistory`]
+/// live on disk.
+///
+/// Insert it with the history it loaded and every save is written back:
+///
+/// ```rust,ignore
+/// let saves = SaveStore::next_to(&config_store.path);
+/// let history = saves.load().unwrap_or_default();
+/// app.insert_resource(history).insert_resource(saves);
+/// ```
+#[derive(Resource, Debug, Clone)]
+pub struct SaveStore {
+    pub path: PathBuf,
+}
+
+impl SaveStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The save file in the folder of the config file at `config_path`.
+    pub fn next_to(config_path: &Path) -> Self {
+        Self::new(config_path.with_file_name(SAVE_FILE_NAME))
+    }
+
+    /// Reads the slots and persistent variables; a missing file yields an
+    /// empty history.
+    pub fn load(&self) -> Result<StoryHistory, Box<dyn std::error::Error>> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => Ok(ron::from_str(&text)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(StoryHistory::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the slots and persistent variables of `history`, creating the
+    /// parent directory if needed.
+    pub fn save(&self, history: &StoryHistory) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let text = ron::ser::to_string_pretty(history, ron::ser::PrettyConfig::default())?;
+        fs::write(&self.path, text)?;
+        Ok(())
+    }
+}
+
+/// Writes the history to the [`SaveStore`] after a save or a change of the
+/// persistent variables. Added by the `GameMenusPlugin`, whose menus save.
+pub(crate) fn write_saves(store: Option<Res<SaveStore>>, mut history: ResMut<StoryHistory>) {
+    if !history.bypass_change_detection().take_unsaved() {
+        return;
+    }
+    let Some(store) = store else {
+        return;
+    };
+    if let Err(err) = store.save(&history) {
+        error!("can not write {}: {}", store.path.display(), err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::event_bus::{CommandBus, CommandBusPlugin, VnValue};
+    use crate::core::script::Script;
+    use crate::utils::test_harness::TestApp;
+
+    #[test]
+    fn keeps_the_slots_and_progress_but_not_the_rollback() {
+        let path = std::env::temp_dir().join(format!("avg_saves_{}.ron", std::process::id()));
+        let store = SaveStore::new(&path);
+        assert!(store.load().unwrap().latest_slot().is_none());
+
+        let script = Script::parse(
+            r#"set persistent.route_a = true
+set route = 1
+"a"
+"#,
+        )
+        .unwrap();
+        let mut app = TestApp::new(|app: &mut App| {
+            app.add_plugins(CommandBusPlugin)
+                .add_systems(Last, write_saves);
+        });
+        app.world_mut().insert_resource(store.clone());
+        app.world_mut().resource_mut::<CommandBus>().start(&script);
+        app.update();
+        app.world_mut().resource_mut::<StoryHistory>().save(3);
+        app.update();
+
+        let loaded = store.load().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.latest_slot(), Some(3));
+        assert_eq!(
+            loaded.progress().get("persistent.route_a"),
+            Some(&VnValue::Bool(true))
+        );
+        assert!(loaded.progress().get("route").is_none());
+        assert!(loaded.current().is_none());
+        let slot = loaded.slot(3).unwrap();
+        assert_eq!(slot.state.variables.get("route"), Some(&VnValue::Int(1)));
+    }
+}
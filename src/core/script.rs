@@ -0,0 +1,604 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::core::event_bus::{Condition, OptionCondition, VnCommand, VnValue};
+
+/// The extension of script files.
+pub const SCRIPT_EXTENSION: &str = "avg";
+
+/// A chapter of the story, parsed from a script file.
+///
+/// A script has one statement per line; `#` starts a comment outside of
+/// quoted text:
+///
+/// ```text
+/// label start
+/// bg bg.title                      # background, by catalogue id or path
+/// show ch.ch5                      # character image
+/// show characters/ch4.atlas.ron body face/smile   # layers of an atlas
+/// hide
+/// music bgm.main loop
+/// stop music
+/// voice voice/ch4_001.ogg
+/// "A line without a speaker.\nA second row."
+/// Alice: "A line of Alice."
+/// Alice: "A line to keep its translations." @alice_greets
+/// wait 0.5
+/// set met_alice = true             # true, false, an integer or "text"
+/// choice route                     # stores the index of the picked option
+///     "Follow her" -> follow       # jumps to a label when picked
+///     "Ask about the key" if has_key == true
+///     "Stay"                       # continues below the choice
+/// if route == 2 jump stay
+/// jump follow
+/// end
+/// ```
+///
+/// Every line and option has an id naming its translations: the one given
+/// after `@`, or one made from the label above it and its text, see
+/// [`text_id`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Script {
+    pub statements: Vec<Statement>,
+    pub labels: BTreeMap<String, Label>,
+}
+
+/// A command of a [`Script`] and the line it was written on.
+///
+/// Statements that expand to several commands, like a choice with jumps,
+/// share the line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Statement {
+    pub line: usize,
+    pub command: VnCommand,
+}
+
+/// Where a label of a [`Script`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Label {
+    /// The index of the first statement after the label.
+    pub statement: usize,
+    pub line: usize,
+}
+
+/// A line of a script that can not be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.line, self.message)
+    }
+}
+
+/// The option lines of a choice being parsed.
+struct PendingChoice {
+    line: usize,
+    /// The label above the choice.
+    label: String,
+    variable: String,
+    options: Vec<String>,
+    ids: Vec<Option<String>>,
+    conditions: Vec<OptionCondition>,
+    targets: Vec<(usize, String)>,
+}
+
+impl Script {
+    /// Parses the text of a script, returning every line that can not be
+    /// parsed on failure.
+    pub fn parse(text: &str) -> Result<Self, Vec<ScriptError>> {
+        let mut script = Script::default();
+        let mut errors = Vec::new();
+        let mut choice: Option<PendingChoice> = None;
+        let mut label = "start".to_string();
+
+        for (index, raw) in text.lines().enumerate() {
+            let line = index + 1;
+            let trimmed = strip_comment(raw).trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            // 选项是紧跟在 choice 之后、缩进的字符串行
+            let indented = raw.starts_with(char::is_whitespace);
+            if let Some(pending) = choice.as_mut() {
+                if indented && trimmed.starts_with('"') {
+                    if let Err(message) = parse_option(trimmed, pending) {
+                        errors.push(ScriptError { line, message });
+                    }
+                    continue;
+                }
+            }
+            if let Some(pending) = choice.take() {
+                script.push_choice(pending, &mut errors);
+            }
+            match parse_statement(trimmed) {
+                Ok(Parsed::Label(name)) => {
+                    if let Some(label) = script.labels.get(&name) {
+                        errors.push(ScriptError {
+                            line,
+                            message: format!("label {name} is already defined on line {}", label.line),
+                        });
+                        continue;
+                    }
+                    let statement = script.statements.len();
+                    script.labels.insert(name.clone(), Label { statement, line });
+                    label = name;
+                }
+                Ok(Parsed::Choice(variable)) => {
+                    choice = Some(PendingChoice {
+                        line,
+                        label: label.clone(),
+                        variable,
+                        options: Vec::new(),
+                        ids: Vec::new(),
+                        conditions: Vec::new(),
+                        targets: Vec::new(),
+                    })
+                }
+                Ok(Parsed::Command(mut command)) => {
+                    if let VnCommand::Say { speaker, text, id: id @ None } = &mut command {
+                        *id = Some(text_id(&label, speaker.as_deref(), text));
+                    }
+                    script.statements.push(Statement { line, command })
+                }
+                Err(message) => errors.push(ScriptError { line, message }),
+            }
+        }
+        if let Some(pending) = choice.take() {
+            script.push_choice(pending, &mut errors);
+        }
+
+        if errors.is_empty() {
+            Ok(script)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Reads and parses the script at `path`.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = fs::read_to_string(path)?;
+        Script::parse(&text).map_err(|errors| {
+            errors
+                .iter()
+                .map(|error| format!("{}:{}", path.display(), error))
+                .collect::<Vec<_>>()
+                .join("\n")
+                .into()
+        })
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = &VnCommand> {
+        self.statements.iter().map(|statement| &statement.command)
+    }
+
+    /// Adds a choice, followed by a jump for every option with a target.
+    fn push_choice(&mut self, choice: PendingChoice, errors: &mut Vec<ScriptError>) {
+        let line = choice.line;
+        if choice.options.is_empty() {
+            errors.push(ScriptError {
+                line,
+                message: "choice has no options".to_string(),
+            });
+            return;
+        }
+        let ids = choice
+            .ids
+            .into_iter()
+            .zip(choice.options.iter())
+            .map(|(id, option)| id.unwrap_or_else(|| text_id(&choice.label, None, option)))
+            .collect();
+        self.statements.push(Statement {
+            line,
+            command: VnCommand::Choice {
+                options: choice.options,
+                variable: choice.variable.clone(),
+                conditions: choice.conditions,
+                ids,
+            },
+        });
+        for (option, label) in choice.targets {
+            self.statements.push(Statement {
+                line,
+                command: VnCommand::JumpIf {
+                    condition: Condition {
+                        variable: choice.variable.clone(),
+                        value: VnValue::Int(option as i64),
+                    },
+                    label,
+                },
+            });
+        }
+    }
+}
+
+/// The id of a line or option without one given in the script: the label
+/// above it and a hash of the speaker and text, e.g. `start.3f2a9c1e`.
+///
+/// It stays the same as long as the text and the label do.
+pub fn text_id(label: &str, speaker: Option<&str>, text: &str) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(speaker.unwrap_or_default().as_bytes());
+    hasher.update(&[0]);
+    hasher.update(text.as_bytes());
+    let hash = hasher.finalize().to_hex();
+    format!("{label}.{}", &hash[..8])
+}
+
+enum Parsed {
+    Label(String),
+    Choice(String),
+    Command(VnCommand),
+}
+
+fn parse_statement(line: &str) -> Result<Parsed, String> {
+    if line.starts_with('"') {
+        let (text, rest) = parse_string(line)?;
+        let (id, rest) = parse_id(rest)?;
+        expect_end(rest)?;
+        return Ok(Parsed::Command(VnCommand::Say {
+            speaker: None,
+            text,
+            id,
+        }));
+    }
+    let (keyword, rest) = split_word(line);
+    let command = match keyword {
+        "label" => return Ok(Parsed::Label(single_word(rest, "label needs a name")?)),
+        "choice" => {
+            return Ok(Parsed::Choice(single_word(
+                rest,
+                "choice needs the variable to store the picked option in",
+            )?))
+        }
+        "jump" => VnCommand::Jump {
+            label: single_word(rest, "jump needs a label")?,
+        },
+        "if" => {
+            let (condition, rest) = parse_condition(rest)?;
+            let (keyword, rest) = split_word(rest);
+            if keyword != "jump" {
+                return Err("expected `if <variable> == <value> jump <label>`".to_string());
+            }
+            VnCommand::JumpIf {
+                condition,
+                label: single_word(rest, "jump needs a label")?,
+            }
+        }
+        "end" => {
+            expect_end(rest)?;
+            VnCommand::End
+        }
+        "bg" => VnCommand::ShowBackground {
+            path: single_word(rest, "bg needs an image")?,
+        },
+        "show" => {
+            let mut words = rest.split_whitespace();
+            let path = words.next().ok_or("show needs an image or a character atlas")?;
+            VnCommand::ShowCharacter {
+                path: path.to_string(),
+                layers: words.map(str::to_string).collect(),
+            }
+        }
+        "hide" => {
+            expect_end(rest)?;
+            VnCommand::HideCharacter
+        }
+        "music" => {
+            let (path, rest) = split_word(rest);
+            if path.is_empty() {
+                return Err("music needs a track".to_string());
+            }
+            let looped = match rest {
+                "" => false,
+                "loop" => true,
+                _ => return Err(format!("unexpected `{rest}`, expected `loop`")),
+            };
+            VnCommand::PlayMusic {
+                path: path.to_string(),
+                looped,
+            }
+        }
+        "stop" => {
+            if rest != "music" {
+                return Err("expected `stop music`".to_string());
+            }
+            VnCommand::StopMusic
+        }
+        "voice" => VnCommand::PlayVoice {
+            path: single_word(rest, "voice needs a sound")?,
+        },
+        "wait" => {
+            let seconds = single_word(rest, "wait needs a number of seconds")?;
+            VnCommand::Wait {
+                seconds: seconds
+                    .parse()
+                    .map_err(|_| format!("`{seconds}` is not a number of seconds"))?,
+            }
+        }
+        "set" => {
+            let (name, rest) = split_word(rest);
+            let value = rest
+                .strip_prefix('=')
+                .filter(|_| !name.is_empty())
+                .ok_or("expected `set <variable> = <value>`")?;
+            VnCommand::SetVar {
+                name: name.to_string(),
+                value: parse_value(value.trim())?,
+            }
+        }
+        _ => {
+            // 说话人: "台词"
+            if let Some((speaker, text)) = line.split_once(':') {
+                if text.trim_start().starts_with('"') && !speaker.contains('"') {
+                    let (text, rest) = parse_string(text.trim_start())?;
+                    let (id, rest) = parse_id(rest)?;
+                    expect_end(rest)?;
+                    return Ok(Parsed::Command(VnCommand::Say {
+                        speaker: Some(speaker.trim().to_string()),
+                        text,
+                        id,
+                    }));
+                }
+            }
+            return Err(format!("unknown statement `{keyword}`"));
+        }
+    };
+    Ok(Parsed::Command(command))
+}
+
+/// Parses `"text" [@id] [if <variable> == <value>] [-> <label>]` into `choice`.
+fn parse_option(line: &str, choice: &mut PendingChoice) -> Result<(), String> {
+    let option = choice.options.len();
+    let (text, rest) = parse_string(line)?;
+    let (id, mut rest) = parse_id(rest)?;
+    if let Some(condition) = rest.strip_prefix("if ") {
+        let (condition, after) = parse_condition(condition)?;
+        choice.conditions.push(OptionCondition { option, condition });
+        rest = after;
+    }
+    if let Some(label) = rest.strip_prefix("->") {
+        choice
+            .targets
+            .push((option, single_word(label.trim(), "-> needs a label")?));
+    } else {
+        expect_end(rest)?;
+    }
+    choice.options.push(text);
+    choice.ids.push(id);
+    Ok(())
+}
+
+/// Parses an optional `@id`, returning what follows it.
+fn parse_id(text: &str) -> Result<(Option<String>, &str), String> {
+    let Some(rest) = text.strip_prefix('@') else {
+        return Ok((None, text));
+    };
+    let (id, rest) = split_word(rest);
+    if id.is_empty() {
+        return Err("@ needs an id".to_string());
+    }
+    Ok((Some(id.to_string()), rest))
+}
+
+/// Parses `<variable> == <value>`, returning what follows it.
+fn parse_condition(text: &str) -> Result<(Condition, &str), String> {
+    let (variable, rest) = split_word(text);
+    let (operator, rest) = split_word(rest);
+    if variable.is_empty() || operator != "==" {
+        return Err("expected a condition `<variable> == <value>`".to_string());
+    }
+    let (value, rest) = if rest.starts_with('"') {
+        let (text, rest) = parse_string(rest)?;
+        (VnValue::Text(text), rest)
+    } else {
+        let (value, rest) = split_word(rest);
+        (parse_value(value)?, rest)
+    };
+    Ok((
+        Condition {
+            variable: variable.to_string(),
+            value,
+        },
+        rest,
+    ))
+}
+
+/// Parses a condition written as in a script, e.g. `route == 1`.
+impl std::str::FromStr for Condition {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (condition, rest) = parse_condition(text.trim())?;
+        expect_end(rest)?;
+        Ok(condition)
+    }
+}
+
+fn parse_value(text: &str) -> Result<VnValue, String> {
+    match text {
+        "true" => Ok(VnValue::Bool(true)),
+        "false" => Ok(VnValue::Bool(false)),
+        _ if text.starts_with('"') => {
+            let (text, rest) = parse_string(text)?;
+            expect_end(rest)?;
+            Ok(VnValue::Text(text))
+        }
+        _ => text
+            .parse()
+            .map(VnValue::Int)
+            .map_err(|_| format!("`{text}` is not a value: use true, false, an integer or \"text\"")),
+    }
+}
+
+/// Parses a quoted string with `\n`, `\"` and `\\` escapes at the start of
+/// `text`, returning it and the trimmed rest.
+fn parse_string(text: &str) -> Result<(String, &str), String> {
+    let mut chars = text.char_indices();
+    if !matches!(chars.next(), Some((_, '"'))) {
+        return Err("expected a quoted string".to_string());
+    }
+    let mut value = String::new();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, text[index + 1..].trim())),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, escaped @ ('"' | '\\'))) => value.push(escaped),
+                Some((_, other)) => return Err(format!("unknown escape `\\{other}`")),
+                None => break,
+            },
+            _ => value.push(c),
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+/// `line` without a `#` comment; a `#` in a quoted string is kept.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Splits off the first word of `text`, returning it and the trimmed rest.
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (text, ""),
+    }
+}
+
+/// The only word of `text`, or `missing` when there is none.
+fn single_word(text: &str, missing: &str) -> Result<String, String> {
+    let (word, rest) = split_word(text);
+    if word.is_empty() {
+        return Err(missing.to_string());
+    }
+    expect_end(rest)?;
+    Ok(word.to_string())
+}
+
+fn expect_end(rest: &str) -> Result<(), String> {
+    if rest.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("unexpected `{rest}`"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_statements_and_labels() {
+        let script = Script::parse(
+            r#"
+# 注释
+label start
+bg bg.title
+show atlas/ch4.atlas.ron body face/smile  # 表情
+music bgm.main loop
+Alice: "Hi, \"you\".\n#Bye" @alice_hi # 注释
+set met = true
+if met == true jump start
+end
+"#,
+        )
+        .unwrap();
+        assert_eq!(script.labels["start"], Label { statement: 0, line: 3 });
+        let commands: Vec<_> = script.commands().cloned().collect();
+        assert_eq!(
+            commands[1],
+            VnCommand::ShowCharacter {
+                path: "atlas/ch4.atlas.ron".to_string(),
+                layers: vec!["body".to_string(), "face/smile".to_string()],
+            }
+        );
+        assert_eq!(
+            commands[3],
+            VnCommand::Say {
+                speaker: Some("Alice".to_string()),
+                text: "Hi, \"you\".\n#Bye".to_string(),
+                id: Some("alice_hi".to_string()),
+            }
+        );
+        assert_eq!(
+            commands[5],
+            VnCommand::JumpIf {
+                condition: Condition {
+                    variable: "met".to_string(),
+                    value: VnValue::Bool(true),
+                },
+                label: "start".to_string(),
+            }
+        );
+        assert_eq!(script.statements[6].line, 10);
+    }
+
+    #[test]
+    fn expands_choice_targets_into_jumps() {
+        let script = Script::parse(
+            r#"choice route
+    "Left" -> left
+    "Secret" @secret if key == 1
+    "Right" -> right
+"After"
+"#,
+        )
+        .unwrap();
+        let commands: Vec<_> = script.commands().cloned().collect();
+        let VnCommand::Choice {
+            options,
+            variable,
+            conditions,
+            ids,
+        } = &commands[0]
+        else {
+            panic!("expected a choice, got {:?}", commands[0]);
+        };
+        assert_eq!(options, &["Left", "Secret", "Right"]);
+        assert_eq!(variable, "route");
+        assert_eq!(conditions[0].option, 1);
+        assert_eq!(ids[0], text_id("start", None, "Left"));
+        assert_eq!(ids[1], "secret");
+        let jump = |option, label: &str| VnCommand::JumpIf {
+            condition: Condition {
+                variable: "route".to_string(),
+                value: VnValue::Int(option),
+            },
+            label: label.to_string(),
+        };
+        assert_eq!(commands[1..3], [jump(0, "left"), jump(2, "right")]);
+        let VnCommand::Say { id: Some(id), .. } = &commands[3] else {
+            panic!("expected a line, got {:?}", commands[3]);
+        };
+        assert!(id.starts_with("start.") && id.len() == "start.".len() + 8);
+        assert!(script.statements.iter().take(3).all(|statement| statement.line == 1));
+    }
+
+    #[test]
+    fn reports_every_bad_line() {
+        let errors = Script::parse("label a\nlabel a\nwait soon\nchoice x\nfly away\n\"open").unwrap_err();
+        let lines: Vec<usize> = errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, [2, 3, 4, 5, 6]);
+        assert_eq!(errors[0].message, "label a is already defined on line 1");
+        assert_eq!(errors[2].message, "choice has no options");
+    }
+}
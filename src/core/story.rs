@@ -0,0 +1,255 @@
+use crate::core::event_bus::{CommandBus, RunCommand, VnCommand, VnValue};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How many lines [`StoryHistory`] can roll back.
+const HISTORY_LEN: usize = 200;
+
+/// Script variables named with this prefix, e.g. `persistent.route_a`, are
+/// kept across play sessions in [`StoryHistory::progress`].
+pub const PERSISTENT_PREFIX: &str = "persistent.";
+
+/// What the story shows at the moment, rebuilt from the commands the
+/// [`CommandBus`] ran, plus the script variables.
+///
+/// It holds everything needed to compare two play sessions and to restore a
+/// point of the story, but nothing about how it is presented.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StoryState {
+    /// The script the story runs, relative to the asset folder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    pub background: Option<String>,
+    pub character: Option<String>,
+    /// The layers shown when `character` is a character atlas.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub character_layers: Vec<String>,
+    /// Music is always restored looped.
+    pub music: Option<String>,
+    pub line: Option<DialogueLine>,
+    /// Written by [`VnCommand::SetVar`] and [`VnCommand::Choice`].
+    pub variables: BTreeMap<String, VnValue>,
+}
+
+/// The dialogue line shown last.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DialogueLine {
+    pub speaker: Option<String>,
+    pub text: String,
+}
+
+impl StoryState {
+    fn apply(&mut self, command: &VnCommand) {
+        match command {
+            VnCommand::ShowBackground { path } => self.background = Some(path.clone()),
+            VnCommand::ShowCharacter { path, layers } => {
+                self.character = Some(path.clone());
+                self.character_layers.clone_from(layers);
+            }
+            VnCommand::HideCharacter => {
+                self.character = None;
+                self.character_layers.clear();
+            }
+            VnCommand::PlayMusic { path, .. } => self.music = Some(path.clone()),
+            VnCommand::StopMusic => self.music = None,
+            VnCommand::Say { speaker, text, .. } => {
+                self.line = Some(DialogueLine {
+                    speaker: speaker.clone(),
+                    text: text.clone(),
+                })
+            }
+            VnCommand::SetVar { name, value } => {
+                self.variables.insert(name.clone(), value.clone());
+            }
+            VnCommand::PlayVoice { .. }
+            | VnCommand::Choice { .. }
+            | VnCommand::Wait { .. }
+            | VnCommand::Jump { .. }
+            | VnCommand::JumpIf { .. }
+            | VnCommand::End => {}
+        }
+    }
+}
+
+/// A point of the story the player stopped at: the state right before a
+/// line or choice, and the commands left to run from there.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StorySnapshot {
+    pub state: StoryState,
+    pub pending: Vec<VnCommand>,
+}
+
+impl StorySnapshot {
+    /// Makes the story continue from the snapshot.
+    ///
+    /// Drops everything queued on `bus`, then queues the commands changing
+    /// what `state` shows into what the snapshot shows, followed by the
+    /// pending commands of the snapshot.
+    pub fn restore(&self, bus: &mut CommandBus, state: &mut StoryState) {
+        let target = &self.state;
+        let mut commands = Vec::new();
+        if state.background != target.background {
+            if let Some(path) = &target.background {
+                commands.push(VnCommand::ShowBackground { path: path.clone() });
+            }
+        }
+        if state.character != target.character
+            || state.character_layers != target.character_layers
+        {
+            commands.push(match &target.character {
+                Some(path) => VnCommand::ShowCharacter {
+                    path: path.clone(),
+                    layers: target.character_layers.clone(),
+                },
+                None => VnCommand::HideCharacter,
+            });
+        }
+        if state.music != target.music {
+            commands.push(match &target.music {
+                Some(path) => VnCommand::PlayMusic {
+                    path: path.clone(),
+                    looped: true,
+                },
+                None => VnCommand::StopMusic,
+            });
+        }
+
+        *state = target.clone();
+        bus.clear();
+        bus.extend(commands);
+        bus.extend(self.pending.iter().cloned());
+    }
+}
+
+/// The snapshots taken at every line and choice, for rollback, the save
+/// slots and the persistent variables.
+///
+/// Slots and persistent variables are written to disk when a
+/// [`SaveStore`](crate::core::save_store::SaveStore) resource exists, and
+/// only live as long as the app otherwise.
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct StoryHistory {
+    #[serde(skip)]
+    snapshots: Vec<StorySnapshot>,
+    slots: BTreeMap<u32, StorySnapshot>,
+    /// The slot saved last.
+    #[serde(default)]
+    latest: Option<u32>,
+    #[serde(default)]
+    progress: BTreeMap<String, VnValue>,
+    #[serde(skip)]
+    unsaved: bool,
+}
+
+impl StoryHistory {
+    /// The snapshot of the line or choice the player is at.
+    pub fn current(&self) -> Option<&StorySnapshot> {
+        self.snapshots.last()
+    }
+
+    /// The lines read so far, oldest first, ending with the one `state`
+    /// shows. Only as many as the rollback history holds.
+    pub fn lines<'a>(&'a self, state: &'a StoryState) -> Vec<&'a DialogueLine> {
+        // 快照记录的是执行前的状态，其中的台词是上一句
+        let mut lines: Vec<&DialogueLine> = Vec::new();
+        let shown = self.snapshots.iter().skip(1).map(|snapshot| &snapshot.state);
+        for line in shown.chain([state]).filter_map(|state| state.line.as_ref()) {
+            if lines.last() != Some(&line) {
+                lines.push(line);
+            }
+        }
+        lines
+    }
+
+    /// The snapshot saved in `slot`.
+    pub fn slot(&self, slot: u32) -> Option<&StorySnapshot> {
+        self.slots.get(&slot)
+    }
+
+    /// The slot saved last, for continuing the story.
+    pub fn latest_slot(&self) -> Option<u32> {
+        self.latest.filter(|slot| self.slots.contains_key(slot))
+    }
+
+    /// The variables named with [`PERSISTENT_PREFIX`] set in any session.
+    pub fn progress(&self) -> &BTreeMap<String, VnValue> {
+        &self.progress
+    }
+
+    /// Whether the slots or the progress changed since the last call.
+    pub(crate) fn take_unsaved(&mut self) -> bool {
+        std::mem::take(&mut self.unsaved)
+    }
+
+    /// Forgets the rollback history, keeping the save slots.
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+
+    /// Goes back to the line or choice before the current one.
+    ///
+    /// Returns `false` when there is nothing to go back to.
+    pub fn rollback(&mut self, bus: &mut CommandBus, state: &mut StoryState) -> bool {
+        if self.snapshots.len() < 2 {
+            return false;
+        }
+        self.snapshots.pop();
+        // 恢复后会重新执行这一句，届时再次记录快照
+        let Some(previous) = self.snapshots.pop() else {
+            return false;
+        };
+        previous.restore(bus, state);
+        true
+    }
+
+    /// Stores the current snapshot in `slot`.
+    pub fn save(&mut self, slot: u32) -> bool {
+        let Some(current) = self.current().cloned() else {
+            return false;
+        };
+        self.slots.insert(slot, current);
+        self.latest = Some(slot);
+        self.unsaved = true;
+        true
+    }
+
+    /// Continues the story from `slot`, forgetting the rollback history.
+    pub fn load(&mut self, slot: u32, bus: &mut CommandBus, state: &mut StoryState) -> bool {
+        let Some(snapshot) = self.slots.get(&slot).cloned() else {
+            return false;
+        };
+        self.snapshots.clear();
+        snapshot.restore(bus, state);
+        true
+    }
+}
+
+/// Applies every command run by the bus to the [`StoryState`], taking a
+/// snapshot before each line and choice.
+pub(crate) fn track_story(
+    mut run_events: EventReader<RunCommand>,
+    bus: Res<CommandBus>,
+    mut state: ResMut<StoryState>,
+    mut history: ResMut<StoryHistory>,
+) {
+    for event in run_events.read() {
+        if matches!(event.command, VnCommand::Say { .. } | VnCommand::Choice { .. }) {
+            // 阻塞命令总是本帧最后执行的命令，此时总线上剩下的就是它之后的内容
+            history.snapshots.push(StorySnapshot {
+                state: state.clone(),
+                pending: bus.pending(),
+            });
+            if history.snapshots.len() > HISTORY_LEN {
+                history.snapshots.remove(0);
+            }
+        }
+        if let VnCommand::SetVar { name, value } = &event.command {
+            if name.starts_with(PERSISTENT_PREFIX) && history.progress.get(name) != Some(value) {
+                history.progress.insert(name.clone(), value.clone());
+                history.unsaved = true;
+            }
+        }
+        state.apply(&event.command);
+    }
+}
@@ -1,5 +1,6 @@
 pub mod global_define {
     use bevy::prelude::Resource;
+    use serde::{Deserialize, Serialize};
 
     // 常用分辨率选项
     pub const RESOLUTION_720P: (f32, f32) = (1280.0, 720.0);
@@ -7,13 +8,23 @@ pub mod global_define {
     pub const RESOLUTION_1440P: (f32, f32) = (2560.0, 1440.0);
     pub const RESOLUTION_2160P: (f32, f32) = (3840.0, 2160.0);
 
-    #[derive(Resource)]
+    // 可选的分辨率及其名称，同时也是图片变体的名称（如 bg1@1080p.png）
+    pub const RESOLUTION_VARIANTS: [(&str, (f32, f32)); 3] = [
+        ("720p", RESOLUTION_720P),
+        ("1080p", RESOLUTION_1080P),
+        ("1440p", RESOLUTION_1440P),
+    ];
+
+    #[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
     // 游戏全局配置
     pub struct GameConfig {
         pub resolution: (f32, f32),  // 屏幕分辨率
         pub title: String,          // 窗口标题
         pub msaa_samples: u32,      // 抗锯齿设置
         pub target_fps: u32,        // 目标帧率
+        pub master_volume: f32,     // 主音量 0.0 ~ 1.0
+        pub text_speed: f32,        // 文字显示速度（字/秒）
+        pub language: String,       // 台词与界面语言，如 zh、en
     }
 
     impl Default for GameConfig {
@@ -23,6 +34,9 @@ pub mod global_define {
                 title: "Nooly's AVG Game".to_string(),
                 msaa_samples: 4,  // 默认 4xMSAA
                 target_fps: 60,   // 默认帧率 60FPS
+                master_volume: 1.0,
+                text_speed: 5.0,
+                language: "zh".to_string(),
             }
         }
     }
@@ -0,0 +1,179 @@
+//! A framework for building text adventure games with Bevy.
+//!
+//! Add [`AvgPlugin`] to an app with `DefaultPlugins` to get the whole
+//! framework, or pick the sub-plugins from [`plugins`] yourself.
+
+pub mod core;
+pub mod global_def;
+pub mod pipeline;
+pub mod plugins;
+pub mod utils;
+
+pub mod prelude
+{
+    pub use crate::core::atlas::*;
+    pub use crate::core::catalogue::*;
+    pub use crate::core::config_store::*;
+    pub use crate::core::event_bus::*;
+    pub use crate::core::locale::*;
+    pub use crate::core::po::*;
+    pub use crate::core::save_store::*;
+    pub use crate::core::script::*;
+    pub use crate::core::story::*;
+    pub use crate::global_def::global_define::*;
+    pub use crate::plugins::character_layers::*;
+    pub use crate::plugins::check_box::*;
+    pub use crate::plugins::config::*;
+    pub use crate::plugins::drop_down::*;
+    pub use crate::plugins::focus::*;
+    pub use crate::plugins::game_menus::*;
+    pub use crate::plugins::game_state::*;
+    pub use crate::plugins::image_variant::*;
+    pub use crate::plugins::layout::*;
+    pub use crate::plugins::loading::*;
+    pub use crate::plugins::processor_bar::*;
+    pub use crate::plugins::replay::*;
+    pub use crate::plugins::scene_play::*;
+    pub use crate::plugins::scroll_view::*;
+    pub use crate::plugins::slider_bar::*;
+    pub use crate::AvgPlugin;
+}
+
+use bevy::app::{PluginGroup, PluginGroupBuilder};
+
+use crate::core::catalogue::AssetCataloguePlugin;
+use crate::core::locale::LocalePlugin;
+use crate::plugins::image_variant::ImageVariantPlugin;
+use crate::plugins::character_layers::CharacterLayersPlugin;
+use crate::plugins::check_box::CheckboxPlugin;
+use crate::plugins::config::ConfigPlugin;
+use crate::plugins::drop_down::DropDownPlugin;
+use crate::plugins::focus::FocusPlugin;
+use crate::plugins::game_menus::GameMenusPlugin;
+use crate::plugins::game_state::GameStatePlugin;
+use crate::plugins::layout::LayoutPlugin;
+use crate::plugins::processor_bar::ProgressBarPlugin;
+use crate::plugins::scene_play::ScenePlayPlugin;
+use crate::plugins::scroll_view::ScrollViewPlugin;
+use crate::plugins::slider_bar::SliderBarPlugin;
+
+/// Every plugin of the framework, as a plugin group.
+///
+/// The [`LayoutPlugin`], the [`GameStatePlugin`], the [`FocusPlugin`], the
+/// [`AssetCataloguePlugin`], the [`ImageVariantPlugin`] and the
+/// [`LocalePlugin`] are always added; the other parts can be turned off:
+///
+/// ```rust,ignore
+/// App::new()
+///     .add_plugins(DefaultPlugins)
+///     .add_plugins(AvgPlugin::new().scroll_view(false).config(false))
+///     .run();
+/// ```
+///
+/// Single plugins can also be turned off with [`PluginGroupBuilder::disable`]
+/// after calling [`PluginGroup::build`].
+#[derive(Clone, Copy, Debug)]
+pub struct AvgPlugin {
+    scroll_view: bool,
+    scene_play: bool,
+    widgets: bool,
+    config: bool,
+    menus: bool,
+    audio: bool,
+}
+
+impl Default for AvgPlugin {
+    fn default() -> Self {
+        Self {
+            scroll_view: true,
+            scene_play: true,
+            widgets: true,
+            config: true,
+            menus: true,
+            audio: true,
+        }
+    }
+}
+
+impl AvgPlugin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The [`ScrollViewPlugin`].
+    pub fn scroll_view(mut self, enabled: bool) -> Self {
+        self.scroll_view = enabled;
+        self
+    }
+
+    /// The [`ScenePlayPlugin`] running the story, with its loading screen,
+    /// and the [`CharacterLayersPlugin`].
+    pub fn scene_play(mut self, enabled: bool) -> Self {
+        self.scene_play = enabled;
+        self
+    }
+
+    /// The checkbox, dropdown, slider and progress bar plugins.
+    ///
+    /// The config menu adds the ones it needs even when they are turned off.
+    pub fn widgets(mut self, enabled: bool) -> Self {
+        self.widgets = enabled;
+        self
+    }
+
+    /// The [`ConfigPlugin`] with the settings menu.
+    pub fn config(mut self, enabled: bool) -> Self {
+        self.config = enabled;
+        self
+    }
+
+    /// The [`GameMenusPlugin`] with the title screen, main menu, pause
+    /// menu, save slots and backlog.
+    pub fn menus(mut self, enabled: bool) -> Self {
+        self.menus = enabled;
+        self
+    }
+
+    /// The `bevy_kira_audio` `AudioPlugin`; turn it off for apps without an
+    /// audio device. The story then runs without music.
+    pub fn audio(mut self, enabled: bool) -> Self {
+        self.audio = enabled;
+        self
+    }
+}
+
+impl PluginGroup for AvgPlugin {
+    fn build(self) -> PluginGroupBuilder {
+        let mut group = PluginGroupBuilder::start::<Self>()
+            .add(LayoutPlugin)
+            .add(GameStatePlugin)
+            .add(FocusPlugin)
+            .add(AssetCataloguePlugin::default())
+            .add(ImageVariantPlugin)
+            .add(LocalePlugin::default());
+        if self.audio {
+            group = group.add(bevy_kira_audio::AudioPlugin);
+        }
+        // 控件插件要在依赖它们的插件之前添加，后者会跳过已添加的插件
+        if self.widgets {
+            group = group
+                .add(ProgressBarPlugin)
+                .add(CheckboxPlugin)
+                .add(DropDownPlugin)
+                .add(SliderBarPlugin);
+        }
+        if self.scroll_view {
+            group = group.add(ScrollViewPlugin);
+        }
+        if self.scene_play {
+            group = group.add(ScenePlayPlugin).add(CharacterLayersPlugin);
+        }
+        if self.config {
+            group = group.add(ConfigPlugin);
+        }
+        if self.menus {
+            group = group.add(GameMenusPlugin);
+        }
+        group
+    }
+}
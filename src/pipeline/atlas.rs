@@ -0,0 +1,272 @@
+use bevy::math::{URect, UVec2};
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::atlas::{AtlasIndex, AtlasLayer, AtlasPage, ATLAS_INDEX_EXTENSION};
+use crate::pipeline::images::{image_files, opaque_bounds};
+
+/// How the layers of a character are packed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AtlasOptions {
+    /// The largest width and height of an atlas page; layers that do not
+    /// fit on one page go on the next.
+    pub max_size: u32,
+    /// Transparent pixels around every layer, so filtering does not bleed
+    /// neighbouring layers in.
+    pub padding: u32,
+}
+
+impl Default for AtlasOptions {
+    fn default() -> Self {
+        Self {
+            max_size: 2048,
+            padding: 2,
+        }
+    }
+}
+
+/// What [`pack_dir`] did.
+#[derive(Debug, Default)]
+pub struct AtlasReport {
+    /// The index of every packed character.
+    pub packed: Vec<PathBuf>,
+    /// The characters that could not be packed.
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+/// Packs the layers of every character folder directly below `input` into
+/// `output`.
+///
+/// `input/ch4/face/smile.png` becomes the layer `face/smile` of `ch4`,
+/// written to `output/ch4_0.png` and indexed in `output/ch4.atlas.ron`.
+pub fn pack_dir(
+    input: &Path,
+    output: &Path,
+    options: &AtlasOptions,
+) -> Result<AtlasReport, Box<dyn std::error::Error>> {
+    let mut characters = Vec::new();
+    for entry in fs::read_dir(input)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            characters.push(path);
+        }
+    }
+    characters.sort();
+
+    let mut report = AtlasReport::default();
+    for dir in characters {
+        let name = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match pack_character(&dir, &name, output, options) {
+            Ok(index_path) => report.packed.push(index_path),
+            Err(err) => report.failed.push((dir, err.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+/// Packs the layers below `dir` into the atlases of the character `name`,
+/// returning the path of its index.
+pub fn pack_character(
+    dir: &Path,
+    name: &str,
+    output: &Path,
+    options: &AtlasOptions,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let mut layers: Vec<(String, DynamicImage, UVec2)> = Vec::new();
+    let mut canvas = UVec2::ZERO;
+    for relative in image_files(dir, output)? {
+        let image = image::open(dir.join(&relative))
+            .map_err(|err| format!("{}: {}", relative.display(), err))?;
+        let (width, height) = image.dimensions();
+        canvas = canvas.max(UVec2::new(width, height));
+        // 去掉透明边框，记录在画布上的位置
+        let bounds =
+            opaque_bounds(&image).unwrap_or_else(|| URect::from_corners(UVec2::ZERO, UVec2::ONE));
+        let size = bounds.size();
+        let trimmed = image.crop_imm(bounds.min.x, bounds.min.y, size.x, size.y);
+        let layer = relative
+            .with_extension("")
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        layers.push((layer, trimmed, bounds.min));
+    }
+    if layers.is_empty() {
+        return Err("no layers".into());
+    }
+
+    let sizes: Vec<UVec2> = layers
+        .iter()
+        .map(|(_, image, _)| UVec2::new(image.width(), image.height()))
+        .collect();
+    let placements = pack_rects(&sizes, options)?;
+    let page_count = placements
+        .iter()
+        .map(|(page, _)| page + 1)
+        .max()
+        .unwrap_or(0);
+
+    let mut page_sizes = vec![UVec2::ZERO; page_count];
+    for ((page, position), size) in placements.iter().zip(sizes.iter()) {
+        page_sizes[*page] =
+            page_sizes[*page].max(*position + *size + UVec2::splat(options.padding));
+    }
+    let mut pages: Vec<RgbaImage> = page_sizes
+        .iter()
+        .map(|size| RgbaImage::new(size.x, size.y))
+        .collect();
+
+    let mut index = AtlasIndex {
+        canvas,
+        pages: Vec::new(),
+        layers: BTreeMap::new(),
+    };
+    for ((layer, image, offset), (page, position)) in layers.into_iter().zip(placements) {
+        image::imageops::replace(
+            &mut pages[page],
+            &image.to_rgba8(),
+            position.x as i64,
+            position.y as i64,
+        );
+        let rect = URect::from_corners(
+            position,
+            position + UVec2::new(image.width(), image.height()),
+        );
+        index
+            .layers
+            .insert(layer, AtlasLayer { page, rect, offset });
+    }
+
+    fs::create_dir_all(output)?;
+    for (number, page) in pages.into_iter().enumerate() {
+        let file_name = format!("{name}_{number}.png");
+        page.save(output.join(&file_name))?;
+        index.pages.push(AtlasPage {
+            path: file_name,
+            size: UVec2::new(page.width(), page.height()),
+        });
+    }
+    let index_path = output.join(format!("{name}.{ATLAS_INDEX_EXTENSION}"));
+    fs::write(&index_path, index.to_ron()?)?;
+    Ok(index_path)
+}
+
+/// Places rectangles of `sizes` on pages of at most `options.max_size`,
+/// tallest first on shelves left to right. Returns the page and top left
+/// corner of each, in the order of `sizes`.
+pub fn pack_rects(sizes: &[UVec2], options: &AtlasOptions) -> Result<Vec<(usize, UVec2)>, String> {
+    let padding = options.padding;
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| (std::cmp::Reverse(sizes[i].y), std::cmp::Reverse(sizes[i].x)));
+
+    let mut placements = vec![(0, UVec2::ZERO); sizes.len()];
+    let mut page = 0;
+    let mut cursor = UVec2::splat(padding);
+    let mut shelf_height = 0;
+    for i in order {
+        let size = sizes[i];
+        if size.max_element() + 2 * padding > options.max_size {
+            return Err(format!(
+                "a {}x{} layer does not fit in a {} atlas",
+                size.x, size.y, options.max_size
+            ));
+        }
+        // 当前行放不下时换行，当前页放不下时换页
+        if cursor.x + size.x + padding > options.max_size {
+            cursor = UVec2::new(padding, cursor.y + shelf_height + padding);
+            shelf_height = 0;
+        }
+        if cursor.y + size.y + padding > options.max_size {
+            page += 1;
+            cursor = UVec2::splat(padding);
+            shelf_height = 0;
+        }
+        placements[i] = (page, cursor);
+        cursor.x += size.x + padding;
+        shelf_height = shelf_height.max(size.y);
+    }
+    Ok(placements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn packs_rects_without_overlap() {
+        let options = AtlasOptions {
+            max_size: 64,
+            padding: 1,
+        };
+        let sizes = [
+            UVec2::new(30, 20),
+            UVec2::new(30, 40),
+            UVec2::new(20, 10),
+            UVec2::new(62, 62),
+        ];
+        let placements = pack_rects(&sizes, &options).unwrap();
+        let rects: Vec<(usize, URect)> = placements
+            .iter()
+            .zip(sizes)
+            .map(|((page, position), size)| {
+                (*page, URect::from_corners(*position, *position + size))
+            })
+            .collect();
+        for (i, (page, rect)) in rects.iter().enumerate() {
+            assert!(rect.max.x < options.max_size && rect.max.y < options.max_size);
+            for (other_page, other) in rects.iter().skip(i + 1) {
+                assert!(page != other_page || rect.intersect(*other).is_empty());
+            }
+        }
+        assert_eq!(placements[3], (0, UVec2::ONE));
+        assert!(placements[..3].iter().all(|(page, _)| *page == 1));
+
+        assert!(pack_rects(&[UVec2::new(64, 1)], &options).is_err());
+    }
+
+    #[test]
+    fn packs_character_layers_with_their_offsets() {
+        let dir = std::env::temp_dir().join(format!("avg-atlas-test-{}", std::process::id()));
+        let input = dir.join("in/ch");
+        let output = dir.join("out");
+        fs::create_dir_all(input.join("face")).unwrap();
+        let mut body = RgbaImage::new(40, 60);
+        body.put_pixel(10, 20, Rgba([1, 2, 3, 255]));
+        body.put_pixel(29, 59, Rgba([1, 2, 3, 255]));
+        body.save(input.join("body.png")).unwrap();
+        let mut smile = RgbaImage::new(40, 60);
+        smile.put_pixel(15, 5, Rgba([200, 0, 0, 255]));
+        smile.save(input.join("face/smile.png")).unwrap();
+
+        let report = pack_dir(&dir.join("in"), &output, &AtlasOptions::default()).unwrap();
+        assert_eq!(report.packed, [output.join("ch.atlas.ron")]);
+        let index = AtlasIndex::from_ron(&fs::read_to_string(&report.packed[0]).unwrap()).unwrap();
+        assert_eq!(index.canvas, UVec2::new(40, 60));
+        assert_eq!(index.pages.len(), 1);
+        let body = &index.layers["body"];
+        assert_eq!(body.offset, UVec2::new(10, 20));
+        assert_eq!(body.rect.size(), UVec2::new(20, 40));
+        let smile = &index.layers["face/smile"];
+        assert_eq!(smile.offset, UVec2::new(15, 5));
+
+        let page = image::open(output.join(&index.pages[0].path))
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(
+            page.dimensions(),
+            (index.pages[0].size.x, index.pages[0].size.y)
+        );
+        assert_eq!(
+            *page.get_pixel(smile.rect.min.x, smile.rect.min.y),
+            Rgba([200, 0, 0, 255])
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,585 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::core::event_bus::{VnCommand, VnValue};
+use crate::core::script::Script;
+use crate::pipeline::lint::script_files;
+
+/// How the read time of the blocks is estimated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlowchartOptions {
+    /// How fast players read; a CJK character counts as a word.
+    pub words_per_minute: f32,
+}
+
+impl Default for FlowchartOptions {
+    fn default() -> Self {
+        Self {
+            words_per_minute: 250.0,
+        }
+    }
+}
+
+/// The branching structure of every script of the game.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Flowchart {
+    pub scripts: Vec<ScriptGraph>,
+}
+
+/// The blocks of a script and the ways the story moves between them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScriptGraph {
+    /// The script's path, relative to the asset folder.
+    pub file: String,
+    /// The blocks in script order; the first is where the script starts.
+    pub nodes: Vec<FlowNode>,
+    pub edges: Vec<FlowEdge>,
+}
+
+/// A run of statements without a branch into or out of its middle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlowNode {
+    /// The labels the block starts at, `start` or the line it starts on.
+    pub name: String,
+    pub line: usize,
+    /// The words of the dialogue lines and choice options of the block.
+    pub words: usize,
+    pub read_seconds: f32,
+    /// Whether the story ends after the block.
+    pub ending: bool,
+}
+
+/// How the story moves from one block to another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Continues with the next block.
+    Next,
+    Jump,
+    /// A conditional jump, or continuing when its condition does not hold.
+    Condition,
+    /// The player picked one of the options the edge is labelled with.
+    Choice,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlowEdge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+    pub label: String,
+}
+
+/// Reads every script below the asset folder `root` into a flowchart.
+pub fn flowchart_dir(
+    root: &Path,
+    options: &FlowchartOptions,
+) -> Result<Flowchart, Box<dyn std::error::Error>> {
+    let mut flowchart = Flowchart::default();
+    for path in script_files(root)? {
+        let script = Script::load(&path)?;
+        let file = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        flowchart.scripts.push(script_graph(file, &script, options));
+    }
+    Ok(flowchart)
+}
+
+/// Splits `script` into blocks, starting at labels and ending after jumps
+/// and choices.
+pub fn script_graph(file: String, script: &Script, options: &FlowchartOptions) -> ScriptGraph {
+    let statements = &script.statements;
+    let count = statements.len();
+    // 一个选项展开成的跳转与选项在同一行，属于同一个块
+    let expands_previous = |index: usize| {
+        index < count
+            && index > 0
+            && statements[index].line == statements[index - 1].line
+            && matches!(statements[index].command, VnCommand::JumpIf { .. })
+    };
+    let mut starts = vec![false; count + 1];
+    if count > 0 {
+        starts[0] = true;
+    }
+    for label in script.labels.values() {
+        starts[label.statement] = true;
+    }
+    for (index, statement) in statements.iter().enumerate() {
+        let ends = statement.command.is_control_flow()
+            || matches!(statement.command, VnCommand::Choice { .. });
+        if ends && !expands_previous(index + 1) {
+            starts[index + 1] = true;
+        }
+    }
+    starts[count] = false;
+    let block_starts: Vec<usize> = (0..count).filter(|&index| starts[index]).collect();
+    let block_of = |statement: usize| block_starts.iter().rposition(|&start| start <= statement);
+
+    let mut labels: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+    for (name, label) in script.labels.iter() {
+        labels.entry(label.statement).or_default().push(name);
+    }
+
+    let mut graph = ScriptGraph {
+        file,
+        ..Default::default()
+    };
+    for (block, &start) in block_starts.iter().enumerate() {
+        let end = block_starts.get(block + 1).copied().unwrap_or(count);
+        let name = match labels.get(&start) {
+            Some(names) => names.join(", "),
+            None if start == 0 => "start".to_string(),
+            None => format!("line {}", statements[start].line),
+        };
+        let mut words = 0;
+        let mut choice: Option<(&str, &[String])> = None;
+        let mut targeted = Vec::new();
+        let mut ending = false;
+        let mut falls_through = true;
+        for statement in &statements[start..end] {
+            match &statement.command {
+                VnCommand::Say { text, .. } => words += word_count(text),
+                VnCommand::Choice {
+                    options, variable, ..
+                } => {
+                    words += options
+                        .iter()
+                        .map(|option| word_count(option))
+                        .sum::<usize>();
+                    choice = Some((variable, options));
+                }
+                VnCommand::JumpIf { condition, label } => {
+                    // 选项展开的跳转以选项文字标注
+                    let option = choice.and_then(|(variable, options)| {
+                        let VnValue::Int(index) = condition.value else {
+                            return None;
+                        };
+                        let text = options.get(usize::try_from(index).ok()?)?;
+                        (condition.variable == variable).then_some((index as usize, text))
+                    });
+                    let (kind, text) = match option {
+                        Some((index, text)) => {
+                            targeted.push(index);
+                            (EdgeKind::Choice, text.clone())
+                        }
+                        None => (EdgeKind::Condition, condition.to_string()),
+                    };
+                    match script.labels.get(label) {
+                        Some(target) if target.statement < count => graph.edges.push(FlowEdge {
+                            from: block,
+                            to: block_of(target.statement).unwrap_or(0),
+                            kind,
+                            label: text,
+                        }),
+                        Some(_) => ending = true,
+                        None => {}
+                    }
+                }
+                VnCommand::Jump { label } => {
+                    falls_through = false;
+                    match script.labels.get(label) {
+                        Some(target) if target.statement < count => graph.edges.push(FlowEdge {
+                            from: block,
+                            to: block_of(target.statement).unwrap_or(0),
+                            kind: EdgeKind::Jump,
+                            label: String::new(),
+                        }),
+                        Some(_) => ending = true,
+                        None => {}
+                    }
+                }
+                VnCommand::End => {
+                    falls_through = false;
+                    ending = true;
+                }
+                _ => {}
+            }
+        }
+
+        if falls_through {
+            let last = &statements[end - 1].command;
+            let (kind, label) = match choice {
+                Some((_, options)) => {
+                    let rest: Vec<&str> = (0..options.len())
+                        .filter(|index| !targeted.contains(index))
+                        .map(|index| options[index].as_str())
+                        .collect();
+                    falls_through = !rest.is_empty();
+                    (EdgeKind::Choice, rest.join(" / "))
+                }
+                None if matches!(last, VnCommand::JumpIf { .. }) => {
+                    (EdgeKind::Condition, "else".to_string())
+                }
+                None => (EdgeKind::Next, String::new()),
+            };
+            if falls_through && end < count {
+                graph.edges.push(FlowEdge {
+                    from: block,
+                    to: block + 1,
+                    kind,
+                    label,
+                });
+            } else if falls_through {
+                ending = true;
+            }
+        }
+
+        graph.nodes.push(FlowNode {
+            name,
+            line: statements[start].line,
+            words,
+            read_seconds: words as f32 * 60.0 / options.words_per_minute.max(1.0),
+            ending,
+        });
+    }
+    graph
+}
+
+/// Counts the words of `text`, counting every CJK character as a word.
+pub fn word_count(text: &str) -> usize {
+    let mut words = 0;
+    let mut in_word = false;
+    for c in text.chars() {
+        if is_cjk(c) {
+            words += 1;
+            in_word = false;
+        } else if c.is_alphanumeric() {
+            if !in_word {
+                words += 1;
+            }
+            in_word = true;
+        } else if c != '\'' {
+            in_word = false;
+        }
+    }
+    words
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3040}'..='\u{30ff}'
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{4e00}'..='\u{9fff}'
+            | '\u{ac00}'..='\u{d7af}'
+            | '\u{f900}'..='\u{faff}'
+    )
+}
+
+/// `12 words, ~3 s` or `~2 min`.
+fn node_summary(node: &FlowNode) -> String {
+    let time = if node.read_seconds < 60.0 {
+        format!("~{} s", node.read_seconds.ceil() as u32)
+    } else {
+        format!("~{} min", (node.read_seconds / 60.0).round() as u32)
+    };
+    format!("{} words, {}", node.words, time)
+}
+
+impl Flowchart {
+    /// The flowchart as a Graphviz graph, one cluster per script.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        dot.push_str("digraph story {\n");
+        dot.push_str("    node [shape=box, style=\"rounded,filled\", fillcolor=\"#ffffff\", fontname=\"sans-serif\"];\n");
+        dot.push_str("    edge [fontname=\"sans-serif\", fontsize=10];\n");
+        for (number, script) in self.scripts.iter().enumerate() {
+            let id = |node: usize| format!("s{number}_{node}");
+            let _ = writeln!(dot, "    subgraph cluster_{number} {{");
+            let _ = writeln!(dot, "        label={};", dot_string(&script.file));
+            for (index, node) in script.nodes.iter().enumerate() {
+                let label = format!("{}\n{}", node.name, node_summary(node));
+                let ending = if node.ending {
+                    ", fillcolor=\"#f4cccc\", peripheries=2"
+                } else {
+                    ""
+                };
+                let _ = writeln!(
+                    dot,
+                    "        {} [label={}, tooltip={}{}];",
+                    id(index),
+                    dot_string(&label),
+                    dot_string(&format!("{}:{}", script.file, node.line)),
+                    ending
+                );
+            }
+            for edge in script.edges.iter() {
+                let style = match edge.kind {
+                    EdgeKind::Next => "",
+                    EdgeKind::Jump => ", style=bold",
+                    EdgeKind::Condition => ", style=dashed",
+                    EdgeKind::Choice => ", color=\"#3465a4\"",
+                };
+                let _ = writeln!(
+                    dot,
+                    "        {} -> {} [label={}{}];",
+                    id(edge.from),
+                    id(edge.to),
+                    dot_string(&edge.label),
+                    style
+                );
+            }
+            dot.push_str("    }\n");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// The flowchart as a standalone HTML page drawing each script as an
+    /// SVG graph, blocks placed in rows by how far they are from the start.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        html.push_str(HTML_HEAD);
+        for script in self.scripts.iter() {
+            let _ = writeln!(html, "<section>\n<h2>{}</h2>", html_escape(&script.file));
+            let words: usize = script.nodes.iter().map(|node| node.words).sum();
+            let endings = script.nodes.iter().filter(|node| node.ending).count();
+            let _ = writeln!(
+                html,
+                "<p>{} blocks, {} words, {} endings</p>",
+                script.nodes.len(),
+                words,
+                endings
+            );
+            html.push_str(&svg_graph(script));
+            html.push_str("</section>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+const NODE_WIDTH: f32 = 200.0;
+const NODE_HEIGHT: f32 = 48.0;
+const COLUMN_GAP: f32 = 60.0;
+const ROW_GAP: f32 = 70.0;
+
+const HTML_HEAD: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Story flowchart</title>
+<style>
+body { font-family: sans-serif; margin: 2em; color: #222; }
+svg { display: block; overflow: visible; }
+.node rect { fill: #fff; stroke: #555; rx: 8; }
+.node.ending rect { fill: #f4cccc; stroke: #a40000; stroke-width: 2.5; }
+.node text { font-size: 13px; text-anchor: middle; }
+.node .summary { font-size: 11px; fill: #666; }
+.edge path { fill: none; stroke: #555; }
+.edge.jump path { stroke-width: 2; }
+.edge.condition path { stroke-dasharray: 5 4; }
+.edge.choice path { stroke: #3465a4; }
+.edge text { font-size: 11px; fill: #333; paint-order: stroke; stroke: #fff; stroke-width: 3; }
+</style>
+</head>
+<body>
+<h1>Story flowchart</h1>
+<svg width="0" height="0"><defs><marker id="arrow" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="8" markerHeight="8" orient="auto-start-reverse"><path d="M0,0 L10,5 L0,10 z" fill="#555"/></marker></defs></svg>
+"##;
+
+/// The graph of `script` as SVG.
+fn svg_graph(script: &ScriptGraph) -> String {
+    // 按离开头的距离分行，开头到不了的块放在最后一行
+    let mut rows = vec![usize::MAX; script.nodes.len()];
+    let mut queue = std::collections::VecDeque::new();
+    if !script.nodes.is_empty() {
+        rows[0] = 0;
+        queue.push_back(0);
+    }
+    while let Some(node) = queue.pop_front() {
+        for edge in script.edges.iter().filter(|edge| edge.from == node) {
+            if rows[edge.to] == usize::MAX {
+                rows[edge.to] = rows[node] + 1;
+                queue.push_back(edge.to);
+            }
+        }
+    }
+    let unreachable_row = rows
+        .iter()
+        .filter(|&&row| row != usize::MAX)
+        .max()
+        .map_or(0, |row| row + 1);
+    for row in rows.iter_mut().filter(|row| **row == usize::MAX) {
+        *row = unreachable_row;
+    }
+    let mut columns = vec![0; script.nodes.len()];
+    let mut row_sizes: BTreeMap<usize, usize> = BTreeMap::new();
+    for (node, row) in rows.iter().enumerate() {
+        let size = row_sizes.entry(*row).or_default();
+        columns[node] = *size;
+        *size += 1;
+    }
+    let position = |node: usize| {
+        (
+            columns[node] as f32 * (NODE_WIDTH + COLUMN_GAP),
+            rows[node] as f32 * (NODE_HEIGHT + ROW_GAP),
+        )
+    };
+    let width = row_sizes.values().max().copied().unwrap_or(0) as f32 * (NODE_WIDTH + COLUMN_GAP);
+    let height = (row_sizes.len() as f32) * (NODE_HEIGHT + ROW_GAP);
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg width=\"{width}\" height=\"{height}\" viewBox=\"-20 -10 {} {}\">",
+        width + 40.0,
+        height + 20.0
+    );
+    for edge in script.edges.iter() {
+        let (from_x, from_y) = position(edge.from);
+        let (to_x, to_y) = position(edge.to);
+        let start = (from_x + NODE_WIDTH / 2.0, from_y + NODE_HEIGHT);
+        let path = if rows[edge.to] > rows[edge.from] {
+            let end = (to_x + NODE_WIDTH / 2.0, to_y);
+            let middle = (start.1 + end.1) / 2.0;
+            format!(
+                "M{},{} C{},{} {},{} {},{}",
+                start.0, start.1, start.0, middle, end.0, middle, end.0, end.1
+            )
+        } else {
+            // 回到前面的块时从右侧绕过去
+            let start = (from_x + NODE_WIDTH, from_y + NODE_HEIGHT / 2.0);
+            let end = (to_x + NODE_WIDTH, to_y + NODE_HEIGHT / 2.0);
+            let side = start.0.max(end.0) + COLUMN_GAP / 2.0 + 10.0;
+            format!(
+                "M{},{} C{},{} {},{} {},{}",
+                start.0, start.1, side, start.1, side, end.1, end.0, end.1
+            )
+        };
+        let class = match edge.kind {
+            EdgeKind::Next => "edge",
+            EdgeKind::Jump => "edge jump",
+            EdgeKind::Condition => "edge condition",
+            EdgeKind::Choice => "edge choice",
+        };
+        let label_x = (start.0 + to_x + NODE_WIDTH / 2.0) / 2.0;
+        let label_y = (start.1 + to_y) / 2.0;
+        let _ = writeln!(
+            svg,
+            "<g class=\"{class}\"><path d=\"{path}\" marker-end=\"url(#arrow)\"/><text x=\"{label_x}\" y=\"{label_y}\">{}</text></g>",
+            html_escape(&edge.label)
+        );
+    }
+    for (index, node) in script.nodes.iter().enumerate() {
+        let (x, y) = position(index);
+        let class = if node.ending { "node ending" } else { "node" };
+        let _ = writeln!(
+            svg,
+            "<g class=\"{class}\"><title>{}:{}</title><rect x=\"{x}\" y=\"{y}\" width=\"{NODE_WIDTH}\" height=\"{NODE_HEIGHT}\"/><text x=\"{}\" y=\"{}\">{}</text><text class=\"summary\" x=\"{}\" y=\"{}\">{}</text></g>",
+            html_escape(&script.file),
+            node.line,
+            x + NODE_WIDTH / 2.0,
+            y + 20.0,
+            html_escape(&node.name),
+            x + NODE_WIDTH / 2.0,
+            y + 38.0,
+            node_summary(node)
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// `text` as a quoted DOT string.
+fn dot_string(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = r#"bg bg.title
+"Hello there, traveller."
+choice route
+    "Left" -> left
+    "Right"
+if route == 1 jump right
+label left
+"左边"
+end
+label right
+"Right it is."
+jump left
+"#;
+
+    fn graph() -> ScriptGraph {
+        let script = Script::parse(SCRIPT).unwrap();
+        script_graph("a.avg".to_string(), &script, &FlowchartOptions::default())
+    }
+
+    #[test]
+    fn splits_scripts_into_blocks() {
+        let graph = graph();
+        let nodes: Vec<(&str, usize, usize, bool)> = graph
+            .nodes
+            .iter()
+            .map(|node| (node.name.as_str(), node.line, node.words, node.ending))
+            .collect();
+        assert_eq!(
+            nodes,
+            [
+                ("start", 1, 5, false),
+                ("line 6", 6, 0, false),
+                ("left", 8, 2, true),
+                ("right", 11, 3, false),
+            ]
+        );
+        let edges: Vec<(usize, usize, EdgeKind, &str)> = graph
+            .edges
+            .iter()
+            .map(|edge| (edge.from, edge.to, edge.kind, edge.label.as_str()))
+            .collect();
+        assert_eq!(
+            edges,
+            [
+                (0, 2, EdgeKind::Choice, "Left"),
+                (0, 1, EdgeKind::Choice, "Right"),
+                (1, 3, EdgeKind::Condition, "route == 1"),
+                (1, 2, EdgeKind::Condition, "else"),
+                (3, 2, EdgeKind::Jump, ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_words_and_cjk_characters() {
+        assert_eq!(word_count("Don't panic, it's fine."), 4);
+        assert_eq!(word_count("欢迎游玩DS \n开始游戏"), 9);
+        assert_eq!(word_count("……"), 0);
+    }
+
+    #[test]
+    fn exports_dot_and_html() {
+        let flowchart = Flowchart {
+            scripts: vec![graph()],
+        };
+        let dot = flowchart.to_dot();
+        assert!(dot.starts_with("digraph story {"));
+        assert!(dot.contains("s0_2 [label=\"left\\n2 words, ~1 s\", tooltip=\"a.avg:8\", fillcolor=\"#f4cccc\", peripheries=2];"));
+        assert!(dot.contains("s0_1 -> s0_3 [label=\"route == 1\", style=dashed];"));
+
+        let html = flowchart.to_html();
+        assert!(html.contains("<h2>a.avg</h2>"));
+        assert!(html.contains("4 blocks, 10 words, 1 endings"));
+        assert_eq!(html.matches("class=\"node ending\"").count(), 1);
+        assert_eq!(html.matches("marker-end").count(), 5);
+    }
+}
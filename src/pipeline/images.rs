@@ -0,0 +1,411 @@
+use bevy::math::{URect, UVec2, Vec2};
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::catalogue::{variant_path, variant_scale};
+use crate::global_def::global_define::RESOLUTION_VARIANTS;
+use crate::utils::image_utils::is_supported_image_format;
+
+/// The file in an output directory remembering which inputs produced it.
+pub const BUILD_CACHE_FILE: &str = ".avg-assets.toml";
+
+/// How an image is brought to a target size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResizeMode {
+    /// Scales to fit inside the size, keeping the aspect ratio.
+    #[default]
+    Fit,
+    /// Stretches to exactly the size.
+    Fill,
+    /// Scales to cover the size, keeping the aspect ratio, and crops the
+    /// overflow around the centre.
+    Cover,
+}
+
+impl ResizeMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "fit" => Some(ResizeMode::Fit),
+            "fill" => Some(ResizeMode::Fill),
+            "cover" => Some(ResizeMode::Cover),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the name of a resampling filter, e.g. `lanczos3`.
+pub fn parse_filter(name: &str) -> Option<FilterType> {
+    match name {
+        "nearest" => Some(FilterType::Nearest),
+        "triangle" => Some(FilterType::Triangle),
+        "catmull-rom" => Some(FilterType::CatmullRom),
+        "gaussian" => Some(FilterType::Gaussian),
+        "lanczos3" => Some(FilterType::Lanczos3),
+        _ => None,
+    }
+}
+
+/// What to do with every image of a batch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageOptions {
+    /// The target size, `None` keeps the size.
+    pub size: Option<UVec2>,
+    pub mode: ResizeMode,
+    pub filter: FilterType,
+    /// Crops fully transparent rows and columns off the edges first, for
+    /// character sprites.
+    pub trim: bool,
+    /// The output format, `None` keeps the format of the input.
+    pub format: Option<ImageFormat>,
+    /// The resolution variant to write, one of [`RESOLUTION_VARIANTS`]. The
+    /// size, or the size of the input, is taken to be the size at the design
+    /// resolution and scaled up to the variant.
+    pub variant: Option<&'static str>,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            size: None,
+            mode: ResizeMode::Fit,
+            filter: FilterType::Lanczos3,
+            trim: false,
+            format: None,
+            variant: None,
+        }
+    }
+}
+
+impl ImageOptions {
+    /// A stable description of the options, part of the content hash.
+    fn fingerprint(&self) -> String {
+        format!(
+            "{:?} {:?} {:?} {} {:?} {:?}",
+            self.size, self.mode, self.filter, self.trim, self.format, self.variant
+        )
+    }
+
+    /// The output path of `relative` below `output`, with the extension of
+    /// the output format and the name of the variant.
+    pub fn output_path(&self, output: &Path, relative: &Path) -> PathBuf {
+        let mut path = output.join(relative);
+        if let Some(format) = self.format {
+            path = path.with_extension(format.extensions_str()[0]);
+        }
+        match self.variant {
+            Some(variant) => PathBuf::from(variant_path(&path.to_string_lossy(), variant)),
+            None => path,
+        }
+    }
+}
+
+/// Applies `options` to a single image, except the format.
+pub fn process_image(image: DynamicImage, options: &ImageOptions) -> DynamicImage {
+    let image = if options.trim {
+        trim_transparent(&image)
+    } else {
+        image
+    };
+    let scale = options.variant.and_then(variant_scale).unwrap_or(1.0);
+    let size = match options.size {
+        Some(size) if scale == 1.0 => size,
+        Some(size) => (size.as_vec2() * scale).round().as_uvec2(),
+        None if scale == 1.0 => return image,
+        None => {
+            let (width, height) = image.dimensions();
+            (Vec2::new(width as f32, height as f32) * scale).round().as_uvec2()
+        }
+    };
+    match options.mode {
+        ResizeMode::Fit => image.resize(size.x, size.y, options.filter),
+        ResizeMode::Fill => image.resize_exact(size.x, size.y, options.filter),
+        ResizeMode::Cover => image.resize_to_fill(size.x, size.y, options.filter),
+    }
+}
+
+/// Crops the fully transparent rows and columns around the edges. A fully
+/// transparent image is returned unchanged.
+pub fn trim_transparent(image: &DynamicImage) -> DynamicImage {
+    match opaque_bounds(image) {
+        Some(bounds) => {
+            let size = bounds.size();
+            image.crop_imm(bounds.min.x, bounds.min.y, size.x, size.y)
+        }
+        None => image.clone(),
+    }
+}
+
+/// The smallest rectangle holding every pixel that is not fully
+/// transparent, `None` for a fully transparent image.
+pub fn opaque_bounds(image: &DynamicImage) -> Option<URect> {
+    let (width, height) = image.dimensions();
+    let mut min = UVec2::new(width, height);
+    let mut max = UVec2::ZERO;
+    for (x, y, pixel) in image.pixels() {
+        if pixel.0[3] == 0 {
+            continue;
+        }
+        min = min.min(UVec2::new(x, y));
+        max = max.max(UVec2::new(x, y));
+    }
+    (min.x <= max.x && min.y <= max.y).then(|| URect::from_corners(min, max + UVec2::ONE))
+}
+
+/// What [`process_dir`] did.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    /// The inputs written to the output directory.
+    pub processed: Vec<PathBuf>,
+    /// The inputs whose output was up to date.
+    pub skipped: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+/// The content hash of every input, by path relative to the input directory.
+#[derive(Default, Serialize, Deserialize)]
+struct BuildCache {
+    hashes: BTreeMap<String, String>,
+}
+
+/// Processes every image below `input` into the same place below `output`.
+///
+/// An input is skipped when its content and the options hash the same as
+/// in the last run and its output still exists, unless `force` is set.
+/// Failing images are reported and do not stop the batch.
+pub fn process_dir(
+    input: &Path,
+    output: &Path,
+    options: &ImageOptions,
+    force: bool,
+) -> Result<BatchReport, Box<dyn std::error::Error>> {
+    let cache_path = output.join(BUILD_CACHE_FILE);
+    let mut cache: BuildCache = match fs::read_to_string(&cache_path) {
+        Ok(text) => toml::from_str(&text).unwrap_or_default(),
+        Err(_) => BuildCache::default(),
+    };
+    let mut report = BatchReport::default();
+
+    for relative in image_files(input, output)? {
+        let key = match options.variant {
+            Some(variant) => variant_path(&relative.to_string_lossy(), variant),
+            None => relative.to_string_lossy().into_owned(),
+        }
+        .replace('\\', "/");
+        let output_path = options.output_path(output, &relative);
+        let result = fs::read(input.join(&relative))
+            .map_err(|err| err.to_string())
+            .and_then(|data| {
+                let mut hasher = blake3::Hasher::new();
+                hasher.update(&data);
+                hasher.update(options.fingerprint().as_bytes());
+                let hash = hasher.finalize().to_hex().to_string();
+                if !force && output_path.exists() && cache.hashes.get(&key) == Some(&hash) {
+                    return Ok(None);
+                }
+                write_image(&data, &relative, &output_path, options)?;
+                Ok(Some(hash))
+            });
+        match result {
+            Ok(Some(hash)) => {
+                cache.hashes.insert(key, hash);
+                report.processed.push(relative);
+            }
+            Ok(None) => report.skipped.push(relative),
+            Err(err) => {
+                cache.hashes.remove(&key);
+                report.failed.push((relative, err));
+            }
+        }
+    }
+
+    fs::create_dir_all(output)?;
+    fs::write(&cache_path, toml::to_string_pretty(&cache)?)?;
+    Ok(report)
+}
+
+/// Runs [`process_dir`] once for every variant of [`RESOLUTION_VARIANTS`],
+/// writing e.g. `bg1@720p.png`, `bg1@1080p.png` and `bg1@1440p.png`.
+pub fn process_variants(
+    input: &Path,
+    output: &Path,
+    options: &ImageOptions,
+    force: bool,
+) -> Result<BatchReport, Box<dyn std::error::Error>> {
+    let mut report = BatchReport::default();
+    for (variant, _) in RESOLUTION_VARIANTS {
+        let options = ImageOptions {
+            variant: Some(variant),
+            ..options.clone()
+        };
+        let variant_report = process_dir(input, output, &options, force)?;
+        let named = |path: PathBuf| PathBuf::from(variant_path(&path.to_string_lossy(), variant));
+        report
+            .processed
+            .extend(variant_report.processed.into_iter().map(named));
+        report
+            .skipped
+            .extend(variant_report.skipped.into_iter().map(named));
+        report.failed.extend(
+            variant_report
+                .failed
+                .into_iter()
+                .map(|(path, err)| (named(path), err)),
+        );
+    }
+    Ok(report)
+}
+
+fn write_image(
+    data: &[u8],
+    relative: &Path,
+    output_path: &Path,
+    options: &ImageOptions,
+) -> Result<(), String> {
+    let format = match options.format {
+        Some(format) => format,
+        None => ImageFormat::from_path(relative).map_err(|err| err.to_string())?,
+    };
+    let image = image::load_from_memory(data).map_err(|err| err.to_string())?;
+    let mut image = process_image(image, options);
+    // JPEG 不支持透明通道
+    if format == ImageFormat::Jpeg {
+        image = DynamicImage::ImageRgb8(image.to_rgb8());
+    }
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    image
+        .save_with_format(output_path, format)
+        .map_err(|err| err.to_string())
+}
+
+/// The image files below `dir`, relative to it and sorted, leaving out the
+/// folder `skip`, e.g. an output folder inside `dir`.
+pub fn image_files(dir: &Path, skip: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let skip = fs::canonicalize(skip).ok();
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(current) = dirs.pop() {
+        for entry in fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                // 输出目录在输入目录里时不再处理上次的输出
+                if skip.is_none() || fs::canonicalize(&path).ok() != skip {
+                    dirs.push(path);
+                }
+            } else if path.extension().is_some_and(is_supported_image_format) {
+                files.push(path.strip_prefix(dir)?.to_path_buf());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba([255, 0, 0, 255])))
+    }
+
+    fn resized(mode: ResizeMode) -> (u32, u32) {
+        let options = ImageOptions {
+            size: Some(UVec2::new(100, 100)),
+            mode,
+            ..Default::default()
+        };
+        process_image(image(400, 200), &options).dimensions()
+    }
+
+    #[test]
+    fn resize_modes() {
+        assert_eq!(resized(ResizeMode::Fit), (100, 50));
+        assert_eq!(resized(ResizeMode::Fill), (100, 100));
+        assert_eq!(resized(ResizeMode::Cover), (100, 100));
+    }
+
+    #[test]
+    fn trims_transparent_borders() {
+        let mut sprite = RgbaImage::new(10, 8);
+        sprite.put_pixel(2, 3, Rgba([0, 0, 0, 255]));
+        sprite.put_pixel(6, 4, Rgba([0, 0, 0, 10]));
+        let trimmed = trim_transparent(&DynamicImage::ImageRgba8(sprite));
+        assert_eq!(trimmed.dimensions(), (5, 2));
+
+        let empty = DynamicImage::ImageRgba8(RgbaImage::new(4, 4));
+        assert_eq!(trim_transparent(&empty).dimensions(), (4, 4));
+    }
+
+    #[test]
+    fn skips_unchanged_inputs() {
+        let dir = std::env::temp_dir().join(format!("avg-assets-test-{}", std::process::id()));
+        let input = dir.join("in");
+        let output = dir.join("out");
+        fs::create_dir_all(input.join("bg")).unwrap();
+        image(40, 20).save(input.join("bg/a.png")).unwrap();
+        let options = ImageOptions {
+            size: Some(UVec2::new(20, 20)),
+            format: Some(ImageFormat::WebP),
+            ..Default::default()
+        };
+
+        let report = process_dir(&input, &output, &options, false).unwrap();
+        assert_eq!(report.processed, [PathBuf::from("bg/a.png")]);
+        let written = image::open(output.join("bg/a.webp")).unwrap();
+        assert_eq!(written.dimensions(), (20, 10));
+
+        let report = process_dir(&input, &output, &options, false).unwrap();
+        assert_eq!(report.skipped.len(), 1);
+
+        image(40, 40).save(input.join("bg/a.png")).unwrap();
+        let report = process_dir(&input, &output, &options, false).unwrap();
+        assert_eq!(report.processed.len(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn leaves_out_an_output_folder_inside_the_input() {
+        let input = std::env::temp_dir().join(format!("avg-assets-nested-{}", std::process::id()));
+        let output = input.join("out");
+        fs::create_dir_all(&input).unwrap();
+        image(40, 20).save(input.join("a.png")).unwrap();
+        let options = ImageOptions::default();
+
+        let report = process_dir(&input, &output, &options, false).unwrap();
+        assert_eq!(report.processed, [PathBuf::from("a.png")]);
+        let report = process_dir(&input, &output, &options, false).unwrap();
+        assert!(report.processed.is_empty());
+        assert_eq!(report.skipped, [PathBuf::from("a.png")]);
+        fs::remove_dir_all(input).unwrap();
+    }
+
+    #[test]
+    fn writes_resolution_variants() {
+        let dir = std::env::temp_dir().join(format!("avg-assets-variants-{}", std::process::id()));
+        let input = dir.join("in");
+        let output = dir.join("out");
+        fs::create_dir_all(&input).unwrap();
+        image(80, 40).save(input.join("bg.png")).unwrap();
+        let options = ImageOptions {
+            size: Some(UVec2::new(32, 18)),
+            mode: ResizeMode::Cover,
+            ..Default::default()
+        };
+
+        let report = process_variants(&input, &output, &options, false).unwrap();
+        assert_eq!(report.processed.len(), 3);
+        for (name, size) in [("bg@720p.png", (32, 18)), ("bg@1080p.png", (48, 27)), ("bg@1440p.png", (64, 36))] {
+            assert_eq!(image::open(output.join(name)).unwrap().dimensions(), size);
+        }
+        let report = process_variants(&input, &output, &options, false).unwrap();
+        assert_eq!(report.skipped.len(), 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,519 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::atlas::AtlasIndex;
+use crate::core::catalogue::{AssetCatalogue, AssetKind};
+use crate::core::event_bus::{Condition, VnCommand, VnValue};
+use crate::core::script::{Script, SCRIPT_EXTENSION};
+use crate::core::story::PERSISTENT_PREFIX;
+
+/// A problem found in a script.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file.display(), self.line, self.message)
+    }
+}
+
+/// The values the scripts may give each variable.
+///
+/// The variables of a chapter carry over into the next one, so a variable
+/// read in one script may have been set by another. Persistent variables,
+/// named with [`PERSISTENT_PREFIX`], keep what any earlier playthrough set,
+/// so they count as set and may hold any value.
+#[derive(Clone, Debug, Default)]
+pub struct VariableValues(BTreeMap<String, Vec<VnValue>>);
+
+impl VariableValues {
+    /// The values `script` may give each variable.
+    pub fn of(script: &Script) -> Self {
+        let mut values = Self::default();
+        for command in script.commands() {
+            match command {
+                VnCommand::SetVar { name, value } => {
+                    values.0.entry(name.clone()).or_default().push(value.clone())
+                }
+                VnCommand::Choice {
+                    options, variable, ..
+                } => values
+                    .0
+                    .entry(variable.clone())
+                    .or_default()
+                    .extend((0..options.len()).map(|index| VnValue::Int(index as i64))),
+                _ => {}
+            }
+        }
+        values
+    }
+
+    pub fn extend(&mut self, other: &VariableValues) {
+        for (name, values) in other.0.iter() {
+            self.0.entry(name.clone()).or_default().extend(values.iter().cloned());
+        }
+    }
+
+    fn is_set(&self, name: &str) -> bool {
+        name.starts_with(PERSISTENT_PREFIX) || self.0.contains_key(name)
+    }
+
+    fn may_hold(&self, condition: &Condition) -> bool {
+        condition.variable.starts_with(PERSISTENT_PREFIX)
+            || self
+                .0
+                .get(condition.variable.as_str())
+                .is_some_and(|values| values.contains(&condition.value))
+    }
+}
+
+/// Checks every script below the asset folder `root`, returning the
+/// problems found, sorted by file and line.
+///
+/// A variable set by any other script counts as set, see [`VariableValues`].
+pub fn lint_dir(root: &Path) -> Result<Vec<Diagnostic>, Box<dyn std::error::Error>> {
+    let catalogue = AssetCatalogue::scan(root)?;
+    let mut scripts = Vec::new();
+    for file in script_files(root)? {
+        let text =
+            fs::read_to_string(&file).map_err(|err| format!("{}: {}", file.display(), err))?;
+        let values = Script::parse(&text)
+            .map(|script| VariableValues::of(&script))
+            .unwrap_or_default();
+        scripts.push((file, text, values));
+    }
+
+    let mut diagnostics = Vec::new();
+    for (index, (file, text, _)) in scripts.iter().enumerate() {
+        let mut outside = VariableValues::default();
+        for (_, _, values) in scripts
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != index)
+            .map(|(_, script)| script)
+        {
+            outside.extend(values);
+        }
+        diagnostics.extend(lint_script(file, text, &catalogue, &outside));
+    }
+    Ok(diagnostics)
+}
+
+/// Checks the script `text`, read from `file`, against the assets of
+/// `catalogue`; `outside` are the variables the other scripts set.
+///
+/// Reports lines that can not be parsed, jumps to missing labels, missing
+/// assets and atlas layers, variables that may be read before they are set,
+/// choices without an option that can be shown, and statements no path of
+/// the script reaches.
+pub fn lint_script(
+    file: &Path,
+    text: &str,
+    catalogue: &AssetCatalogue,
+    outside: &VariableValues,
+) -> Vec<Diagnostic> {
+    let diagnostic = |line: usize, message: String| Diagnostic {
+        file: file.to_path_buf(),
+        line,
+        message,
+    };
+    let script = match Script::parse(text) {
+        Ok(script) => script,
+        Err(errors) => {
+            return errors
+                .into_iter()
+                .map(|error| diagnostic(error.line, error.message))
+                .collect()
+        }
+    };
+
+    let mut problems: Vec<(usize, String)> = Vec::new();
+    check_labels(&script, &mut problems);
+    check_assets(&script, catalogue, &mut problems);
+    let defined = defined_variables(&script);
+    check_variables(&script, &defined, outside, &mut problems);
+    check_choices(&script, outside, &mut problems);
+    check_reachable(&script, &defined, &mut problems);
+
+    problems.sort();
+    problems.dedup();
+    problems
+        .into_iter()
+        .map(|(line, message)| diagnostic(line, message))
+        .collect()
+}
+
+/// Every script file below `root`, sorted.
+pub(crate) fn script_files(root: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext == SCRIPT_EXTENSION) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn check_labels(script: &Script, problems: &mut Vec<(usize, String)>) {
+    for statement in script.statements.iter() {
+        if let VnCommand::Jump { label } | VnCommand::JumpIf { label, .. } = &statement.command {
+            if !script.labels.contains_key(label) {
+                problems.push((statement.line, format!("unknown label {label}")));
+            }
+        }
+    }
+}
+
+fn check_assets(script: &Script, catalogue: &AssetCatalogue, problems: &mut Vec<(usize, String)>) {
+    // 同一个图集只读取一次
+    let mut atlases: BTreeMap<String, Result<AtlasIndex, String>> = BTreeMap::new();
+    for statement in script.statements.iter() {
+        let command = &statement.command;
+        let Some(id_or_path) = command.asset_path() else {
+            continue;
+        };
+        let path = catalogue.resolve(id_or_path);
+        let exists = catalogue.by_path(path).is_some() || catalogue.root().join(path).is_file();
+        if !exists {
+            problems.push((statement.line, format!("missing asset {id_or_path}")));
+            continue;
+        }
+
+        let layers = match command {
+            VnCommand::ShowCharacter { layers, .. } => layers.as_slice(),
+            _ => &[],
+        };
+        let expected = match command {
+            VnCommand::PlayMusic { .. } | VnCommand::PlayVoice { .. } => Some(AssetKind::Audio),
+            _ if layers.is_empty() => Some(AssetKind::Image),
+            _ => None,
+        };
+        let kind = catalogue.by_path(path).map(|entry| entry.kind);
+        if let (Some(expected), Some(kind)) = (expected, kind) {
+            if expected != kind {
+                problems.push((
+                    statement.line,
+                    format!("{id_or_path} is not {}", kind_name(expected)),
+                ));
+            }
+        }
+        if layers.is_empty() {
+            continue;
+        }
+
+        let index = atlases.entry(path.to_string()).or_insert_with(|| {
+            fs::read_to_string(catalogue.root().join(path))
+                .map_err(|err| err.to_string())
+                .and_then(|text| AtlasIndex::from_ron(&text).map_err(|err| err.to_string()))
+        });
+        match index {
+            Ok(index) => {
+                for layer in layers.iter() {
+                    if !index.layers.contains_key(layer) {
+                        problems
+                            .push((statement.line, format!("{id_or_path} has no layer {layer}")));
+                    }
+                }
+            }
+            Err(err) => problems.push((
+                statement.line,
+                format!("{id_or_path} is not a character atlas: {err}"),
+            )),
+        }
+    }
+}
+
+fn kind_name(kind: AssetKind) -> &'static str {
+    match kind {
+        AssetKind::Image => "an image",
+        AssetKind::Audio => "a sound",
+        AssetKind::Font => "a font",
+        AssetKind::Script => "a script",
+    }
+}
+
+/// The statements each statement can be followed by.
+fn successors(script: &Script, index: usize) -> Vec<usize> {
+    let next = index + 1;
+    let target = |label: &str| script.labels.get(label).map(|label| label.statement);
+    let mut successors: Vec<usize> = match &script.statements[index].command {
+        VnCommand::End => Vec::new(),
+        VnCommand::Jump { label } => target(label).into_iter().collect(),
+        VnCommand::JumpIf { label, .. } => {
+            [Some(next), target(label)].into_iter().flatten().collect()
+        }
+        _ => vec![next],
+    };
+    // 跳到脚本末尾的标签等同于结束
+    successors.retain(|&successor| successor < script.statements.len());
+    successors
+}
+
+/// The variables set on every path to each statement, `None` for
+/// statements no path reaches.
+fn defined_variables(script: &Script) -> Vec<Option<BTreeSet<String>>> {
+    let mut defined: Vec<Option<BTreeSet<String>>> = vec![None; script.statements.len()];
+    if script.statements.is_empty() {
+        return defined;
+    }
+    defined[0] = Some(BTreeSet::new());
+    let mut pending = vec![0];
+    while let Some(index) = pending.pop() {
+        let mut after = defined[index].clone().unwrap_or_default();
+        match &script.statements[index].command {
+            VnCommand::SetVar { name, .. } => {
+                after.insert(name.clone());
+            }
+            VnCommand::Choice { variable, .. } => {
+                after.insert(variable.clone());
+            }
+            _ => {}
+        }
+        // 汇合处只保留所有路径上都设置过的变量
+        for successor in successors(script, index) {
+            let merged = match &defined[successor] {
+                Some(before) => before.intersection(&after).cloned().collect(),
+                None => after.clone(),
+            };
+            if defined[successor].as_ref() != Some(&merged) {
+                defined[successor] = Some(merged);
+                pending.push(successor);
+            }
+        }
+    }
+    defined
+}
+
+fn check_variables(
+    script: &Script,
+    defined: &[Option<BTreeSet<String>>],
+    outside: &VariableValues,
+    problems: &mut Vec<(usize, String)>,
+) {
+    for (statement, defined) in script.statements.iter().zip(defined) {
+        let Some(defined) = defined else {
+            continue;
+        };
+        let read: Vec<&Condition> = match &statement.command {
+            VnCommand::JumpIf { condition, .. } => vec![condition],
+            VnCommand::Choice { conditions, .. } => conditions
+                .iter()
+                .map(|condition| &condition.condition)
+                .collect(),
+            _ => Vec::new(),
+        };
+        for condition in read {
+            if !defined.contains(&condition.variable) && !outside.is_set(&condition.variable) {
+                problems.push((
+                    statement.line,
+                    format!("{} may be read before it is set", condition.variable),
+                ));
+            }
+        }
+    }
+}
+
+fn check_choices(script: &Script, outside: &VariableValues, problems: &mut Vec<(usize, String)>) {
+    // 脚本中可能赋给每个变量的值，加上其他脚本赋的值
+    let mut values = VariableValues::of(script);
+    values.extend(outside);
+
+    for statement in script.statements.iter() {
+        let VnCommand::Choice {
+            options,
+            conditions,
+            ..
+        } = &statement.command
+        else {
+            continue;
+        };
+        let reachable = (0..options.len()).any(|option| {
+            conditions
+                .iter()
+                .filter(|condition| condition.option == option)
+                .all(|condition| values.may_hold(&condition.condition))
+        });
+        if !reachable {
+            problems.push((
+                statement.line,
+                "choice has no option that can be shown".to_string(),
+            ));
+        }
+    }
+}
+
+fn check_reachable(
+    script: &Script,
+    defined: &[Option<BTreeSet<String>>],
+    problems: &mut Vec<(usize, String)>,
+) {
+    // 每段不可达的语句只报告第一行
+    for (index, statement) in script.statements.iter().enumerate() {
+        if defined[index].is_none() && (index == 0 || defined[index - 1].is_some()) {
+            problems.push((statement.line, "unreachable statement".to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::atlas::AtlasLayer;
+    use bevy::math::{URect, UVec2};
+
+    fn messages(text: &str) -> Vec<String> {
+        lint_script(
+            Path::new("test.avg"),
+            text,
+            &AssetCatalogue::default(),
+            &VariableValues::default(),
+        )
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn reports_parse_errors_and_missing_labels() {
+        assert_eq!(
+            messages("fly away"),
+            ["test.avg:1: unknown statement `fly`"]
+        );
+        assert_eq!(
+            messages("jump nowhere\nlabel here\n\"Hi\""),
+            [
+                "test.avg:1: unknown label nowhere",
+                "test.avg:3: unreachable statement"
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_variables_read_before_they_are_set() {
+        let text = r#"choice route
+    "A" -> a
+    "B"
+set key = 1
+label a
+if key == 1 jump b
+"Locked"
+label b
+if route == 0 jump a
+"#;
+        assert_eq!(
+            messages(text),
+            ["test.avg:6: key may be read before it is set"]
+        );
+    }
+
+    #[test]
+    fn reports_choices_without_an_option_to_show() {
+        let text = r#"set key = 1
+choice door
+    "Open" if key == 2
+    "Unlock" if lock == true
+end
+"Never"
+"#;
+        assert_eq!(
+            messages(text),
+            [
+                "test.avg:2: choice has no option that can be shown",
+                "test.avg:2: lock may be read before it is set",
+                "test.avg:6: unreachable statement",
+            ]
+        );
+    }
+
+    #[test]
+    fn variables_carry_over_from_the_other_scripts() {
+        let root = std::env::temp_dir().join(format!("avg-lint-chapters-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("a.avg"), "set met = true\n").unwrap();
+        fs::write(
+            root.join("b.avg"),
+            "if met == true jump end\nchoice door\n    \"Open\" if met == true\nlabel end\nif gone == 1 jump end\n",
+        )
+        .unwrap();
+
+        let diagnostics = lint_dir(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        let messages: Vec<&str> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(messages, ["gone may be read before it is set"]);
+    }
+
+    #[test]
+    fn persistent_variables_count_as_set() {
+        assert!(messages(
+            "if persistent.route_a == true jump end\nchoice extra\n    \"Epilogue\" if persistent.cleared == 2\nlabel end",
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn checks_assets_and_atlas_layers() {
+        let root = std::env::temp_dir().join(format!("avg-lint-test-{}", std::process::id()));
+        fs::create_dir_all(root.join("scripts")).unwrap();
+        image::RgbaImage::new(2, 2)
+            .save(root.join("bg.png"))
+            .unwrap();
+        let index = AtlasIndex {
+            canvas: UVec2::new(2, 2),
+            pages: Vec::new(),
+            layers: BTreeMap::from([(
+                "body".to_string(),
+                AtlasLayer {
+                    page: 0,
+                    rect: URect::new(0, 0, 1, 1),
+                    offset: UVec2::ZERO,
+                },
+            )]),
+        };
+        fs::write(root.join("ch.atlas.ron"), index.to_ron().unwrap()).unwrap();
+        fs::write(
+            root.join("scripts/a.avg"),
+            "bg bg.png\nshow ch.atlas.ron body smile\nmusic bg.png\nvoice missing.ogg\n",
+        )
+        .unwrap();
+
+        let diagnostics = lint_dir(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        let lines: Vec<(usize, &str)> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (2, "ch.atlas.ron has no layer smile"),
+                (3, "bg.png is not a sound"),
+                (4, "missing asset missing.ogg"),
+            ]
+        );
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.file == root.join("scripts/a.avg")));
+    }
+
+    #[test]
+    fn the_game_scripts_are_clean() {
+        let diagnostics = lint_dir(Path::new("assets")).unwrap();
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    }
+}
@@ -0,0 +1,9 @@
+//! Offline processing of the game's assets and scripts, used by the
+//! `avg-assets`, `avg-atlas`, `avg-lint`, `avg-flowchart` and
+//! `avg-translate` binaries.
+
+pub mod atlas;
+pub mod flowchart;
+pub mod images;
+pub mod lint;
+pub mod translate;
@@ -0,0 +1,636 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::core::event_bus::VnCommand;
+use crate::core::locale::speaker_id;
+use crate::core::po::PoEntry;
+use crate::core::script::Script;
+use crate::pipeline::lint::script_files;
+use crate::plugins::game_menus::{TitleScreen, TITLE_SCREEN_FILE};
+
+/// The template the translations are made from, in the locale folder.
+pub const TEMPLATE_FILE: &str = "messages.pot";
+
+/// How similar a changed source text must be to a translated one for its
+/// translation to be reused, marked fuzzy.
+const FUZZY_SIMILARITY: f32 = 0.6;
+
+/// A text to translate and where it was found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub id: String,
+    pub source: String,
+    /// `file:line` of every use.
+    pub references: Vec<String>,
+    /// Context for translators: the speaker, the line before, ...
+    pub notes: Vec<String>,
+}
+
+/// Collects the texts of every script below the asset folder `root`: the
+/// dialogue lines, choice options and speaker names.
+pub fn extract_scripts(root: &Path) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
+    let mut messages = Vec::new();
+    for path in script_files(root)? {
+        let script = Script::load(&path)?;
+        messages.extend(script_messages(&relative_path(root, &path), &script));
+    }
+    Ok(messages)
+}
+
+/// The texts of `script`, read from `file`.
+pub fn script_messages(file: &str, script: &Script) -> Vec<Message> {
+    let mut messages = Vec::new();
+    let mut previous: Option<&str> = None;
+    let after = |previous: Option<&str>| previous.map(|line| format!("After: {}", one_line(line)));
+    for statement in script.statements.iter() {
+        let reference = format!("{file}:{}", statement.line);
+        match &statement.command {
+            VnCommand::Say { speaker, text, id } => {
+                let Some(id) = id else {
+                    continue;
+                };
+                let mut notes = Vec::new();
+                if let Some(speaker) = speaker {
+                    notes.push(format!("Speaker: {speaker}"));
+                    messages.push(Message {
+                        id: speaker_id(speaker),
+                        source: speaker.clone(),
+                        references: vec![reference.clone()],
+                        notes: vec!["The name of a speaker".to_string()],
+                    });
+                }
+                notes.extend(after(previous));
+                messages.push(Message {
+                    id: id.clone(),
+                    source: text.clone(),
+                    references: vec![reference],
+                    notes,
+                });
+                previous = Some(text);
+            }
+            VnCommand::Choice {
+                options,
+                variable,
+                ids,
+                ..
+            } => {
+                for (option, id) in options.iter().zip(ids.iter()) {
+                    let mut notes = vec![format!("An option of the choice {variable}")];
+                    notes.extend(after(previous));
+                    messages.push(Message {
+                        id: id.clone(),
+                        source: option.clone(),
+                        references: vec![reference.clone()],
+                        notes,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    messages
+}
+
+/// Collects the main menu items of [`TITLE_SCREEN_FILE`] in the asset
+/// folder `root`: their `label`s and the names of the scripts they start.
+pub fn extract_title_screen(root: &Path) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
+    let path = root.join(TITLE_SCREEN_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let title: TitleScreen = toml::from_str(&text)?;
+    Ok(title_messages(&relative_path(root, &path), &title, &text))
+}
+
+/// The texts of the items of `title`, read from `file` with the content `text`.
+pub fn title_messages(file: &str, title: &TitleScreen, text: &str) -> Vec<Message> {
+    // 每个 [[items]] 表头所在的行
+    let lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.trim() == "[[items]]")
+        .map(|(index, _)| index + 1);
+    title
+        .items
+        .iter()
+        .zip(lines)
+        .map(|(item, line)| {
+            let label = item.label();
+            Message {
+                id: label.id,
+                source: label.source,
+                references: vec![format!("{file}:{line}")],
+                notes: vec!["A main menu item".to_string()],
+            }
+        })
+        .collect()
+}
+
+/// Collects the UI texts of the Rust sources below `dir`: the literal
+/// arguments of every `LocalizedText::new("id", "source")`.
+pub fn extract_sources(dir: &Path) -> Result<Vec<Message>, Box<dyn std::error::Error>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "rs") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+
+    let mut messages = Vec::new();
+    for path in files {
+        let text = fs::read_to_string(&path)?;
+        let file = path
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        messages.extend(source_messages(&file, &text));
+    }
+    Ok(messages)
+}
+
+/// The UI texts of the Rust source `text`, read from `file`. Comments and
+/// the code after `#[cfg(test)]` are left out.
+pub fn source_messages(file: &str, text: &str) -> Vec<Message> {
+    const CALL: &str = "LocalizedText::new(";
+    let code = text.split("#[cfg(test)]").next().unwrap_or_default();
+    let mut messages = Vec::new();
+    for (start, _) in code.match_indices(CALL) {
+        let line_start = code[..start].rfind('\n').map_or(0, |index| index + 1);
+        if code[line_start..start].trim_start().starts_with("//") {
+            continue;
+        }
+        let rest = &code[start + CALL.len()..];
+        let Some((id, rest)) = rust_string(rest) else {
+            continue;
+        };
+        let Some(rest) = rest.trim_start().strip_prefix(',') else {
+            continue;
+        };
+        let Some((source, _)) = rust_string(rest) else {
+            continue;
+        };
+        let line = text[..start].matches('\n').count() + 1;
+        messages.push(Message {
+            id,
+            source,
+            references: vec![format!("{file}:{line}")],
+            notes: vec!["UI".to_string()],
+        });
+    }
+    messages
+}
+
+/// Reads a string literal at the start of `text`, after whitespace.
+fn rust_string(text: &str) -> Option<(String, &str)> {
+    let text = text.trim_start().strip_prefix('"')?;
+    let mut value = String::new();
+    let mut chars = text.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &text[index + 1..])),
+            '\\' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                other => value.push(other),
+            },
+            _ => value.push(c),
+        }
+    }
+    None
+}
+
+/// The template of `messages`: one entry per id, without translations.
+///
+/// Texts used in several places get the references of all of them.
+pub fn template(messages: &[Message]) -> Vec<PoEntry> {
+    let mut entries = vec![header(None)];
+    let mut by_id: BTreeMap<&str, usize> = BTreeMap::new();
+    for message in messages {
+        if let Some(&index) = by_id.get(message.id.as_str()) {
+            let entry: &mut PoEntry = &mut entries[index];
+            entry.references.extend(message.references.iter().cloned());
+            continue;
+        }
+        by_id.insert(&message.id, entries.len());
+        entries.push(PoEntry {
+            extracted: message.notes.clone(),
+            references: message.references.clone(),
+            context: Some(message.id.clone()),
+            source: message.source.clone(),
+            ..Default::default()
+        });
+    }
+    entries
+}
+
+/// The header of a PO file, for `language` or the template.
+pub fn header(language: Option<&str>) -> PoEntry {
+    let mut translation = String::new();
+    if let Some(language) = language {
+        let _ = writeln!(translation, "Language: {language}");
+    }
+    translation.push_str("MIME-Version: 1.0\n");
+    translation.push_str("Content-Type: text/plain; charset=UTF-8\n");
+    translation.push_str("Content-Transfer-Encoding: 8bit\n");
+    PoEntry {
+        translation,
+        ..Default::default()
+    }
+}
+
+/// What [`merge`] did.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    /// Entries whose translation was kept but needs review.
+    pub fuzzy: usize,
+    pub new: usize,
+    /// Entries of the translation no longer in the template.
+    pub obsolete: usize,
+}
+
+/// Brings the translation `existing` up to date with `template`.
+///
+/// Translations are kept by id. When the source text of an id changed, or a
+/// new text is close to one translated text that is gone, its translation is
+/// kept marked fuzzy, with the source it was made for. Translator comments
+/// and the header of `existing` are kept.
+pub fn merge(template: &[PoEntry], existing: &[PoEntry]) -> (Vec<PoEntry>, MergeReport) {
+    let mut report = MergeReport::default();
+    let by_id: BTreeMap<&str, &PoEntry> = existing
+        .iter()
+        .filter_map(|entry| Some((entry.context.as_deref()?, entry)))
+        .collect();
+    let template_ids: Vec<&str> = template
+        .iter()
+        .filter_map(|entry| entry.context.as_deref())
+        .collect();
+    // 模板中已不存在的翻译，可以作为相近新文本的模糊翻译
+    let mut unused: Vec<&PoEntry> = existing
+        .iter()
+        .filter(|entry| !entry.is_header() && !entry.translation.is_empty())
+        .filter(|entry| {
+            entry
+                .context
+                .as_deref()
+                .is_none_or(|id| !template_ids.contains(&id))
+        })
+        .collect();
+    report.obsolete = existing
+        .iter()
+        .filter(|entry| !entry.is_header())
+        .filter(|entry| {
+            entry
+                .context
+                .as_deref()
+                .is_none_or(|id| !template_ids.contains(&id))
+        })
+        .count();
+
+    let mut merged = Vec::new();
+    for entry in template {
+        if entry.is_header() {
+            let header = existing.iter().find(|entry| entry.is_header());
+            merged.push(header.unwrap_or(entry).clone());
+            continue;
+        }
+        let mut entry = entry.clone();
+        let previous = entry.context.as_deref().and_then(|id| by_id.get(id));
+        match previous {
+            Some(previous) => {
+                entry.comments.clone_from(&previous.comments);
+                entry.translation.clone_from(&previous.translation);
+                if previous.source == entry.source {
+                    entry.flags.clone_from(&previous.flags);
+                    entry.previous.clone_from(&previous.previous);
+                } else if !previous.translation.is_empty() {
+                    entry.set_fuzzy(true);
+                    entry.previous = Some(previous.source.clone());
+                    report.fuzzy += 1;
+                }
+            }
+            None => {
+                let closest = unused
+                    .iter()
+                    .enumerate()
+                    .map(|(index, candidate)| (index, similarity(&candidate.source, &entry.source)))
+                    .filter(|(_, score)| *score >= FUZZY_SIMILARITY)
+                    .max_by(|a, b| a.1.total_cmp(&b.1));
+                match closest {
+                    Some((index, _)) => {
+                        let candidate = unused.remove(index);
+                        entry.translation.clone_from(&candidate.translation);
+                        entry.set_fuzzy(true);
+                        entry.previous = Some(candidate.source.clone());
+                        report.fuzzy += 1;
+                    }
+                    None => report.new += 1,
+                }
+            }
+        }
+        merged.push(entry);
+    }
+    (merged, report)
+}
+
+/// How many entries are translated, not counting fuzzy ones, of how many.
+pub fn completion(entries: &[PoEntry]) -> (usize, usize) {
+    let messages = entries.iter().filter(|entry| !entry.is_header());
+    let total = messages.clone().count();
+    let translated = messages
+        .filter(|entry| !entry.translation.is_empty() && !entry.is_fuzzy())
+        .count();
+    (translated, total)
+}
+
+/// How alike two texts are, from 0 to 1, by the edit distance of their
+/// characters.
+pub fn similarity(a: &str, b: &str) -> f32 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    1.0 - row[b.len()] as f32 / longest as f32
+}
+
+/// The entries as an XLIFF 1.2 document from `source_language` into
+/// `target_language`, or a template without targets.
+pub fn to_xliff(
+    entries: &[PoEntry],
+    source_language: &str,
+    target_language: Option<&str>,
+) -> String {
+    let mut xliff = String::new();
+    xliff.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xliff.push_str("<xliff version=\"1.2\" xmlns=\"urn:oasis:names:tc:xliff:document:1.2\">\n");
+    let target = target_language
+        .map(|language| format!(" target-language=\"{}\"", xml_escape(language)))
+        .unwrap_or_default();
+    let _ = writeln!(
+        xliff,
+        "  <file original=\"messages\" datatype=\"plaintext\" source-language=\"{}\"{}>",
+        xml_escape(source_language),
+        target
+    );
+    xliff.push_str("    <body>\n");
+    for entry in entries.iter().filter(|entry| !entry.is_header()) {
+        let Some(id) = &entry.context else {
+            continue;
+        };
+        let _ = writeln!(xliff, "      <trans-unit id=\"{}\">", xml_escape(id));
+        let _ = writeln!(
+            xliff,
+            "        <source>{}</source>",
+            xml_escape(&entry.source)
+        );
+        if target_language.is_some() && !entry.translation.is_empty() {
+            let state = if entry.is_fuzzy() {
+                "needs-review-translation"
+            } else {
+                "translated"
+            };
+            let _ = writeln!(
+                xliff,
+                "        <target state=\"{state}\">{}</target>",
+                xml_escape(&entry.translation)
+            );
+        }
+        for note in entry.extracted.iter() {
+            let _ = writeln!(
+                xliff,
+                "        <note from=\"developer\">{}</note>",
+                xml_escape(note)
+            );
+        }
+        for reference in entry.references.iter() {
+            let (file, line) = reference.rsplit_once(':').unwrap_or((reference, ""));
+            let _ = writeln!(
+                xliff,
+                "        <context-group purpose=\"location\"><context context-type=\"sourcefile\">{}</context><context context-type=\"linenumber\">{}</context></context-group>",
+                xml_escape(file),
+                xml_escape(line)
+            );
+        }
+        xliff.push_str("      </trans-unit>\n");
+    }
+    xliff.push_str("    </body>\n  </file>\n</xliff>\n");
+    xliff
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A line of dialogue on one line, for a note.
+fn one_line(text: &str) -> String {
+    text.replace('\n', " ")
+}
+
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_lines_with_their_context() {
+        let script = Script::parse(
+            r#""Hello." @hello
+Alice: "Hi, I am Alice."
+choice route
+    "Follow her" @follow
+"#,
+        )
+        .unwrap();
+        let messages = script_messages("scripts/a.avg", &script);
+        let summary: Vec<(&str, &str, Vec<&str>)> = messages
+            .iter()
+            .map(|message| {
+                (
+                    message.id.as_str(),
+                    message.references[0].as_str(),
+                    message.notes.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("hello", "scripts/a.avg:1", vec![]),
+                (
+                    "speaker.Alice",
+                    "scripts/a.avg:2",
+                    vec!["The name of a speaker"]
+                ),
+                (
+                    messages[2].id.as_str(),
+                    "scripts/a.avg:2",
+                    vec!["Speaker: Alice", "After: Hello."]
+                ),
+                (
+                    "follow",
+                    "scripts/a.avg:3",
+                    vec!["An option of the choice route", "After: Hi, I am Alice."]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn extracts_ui_texts_from_sources() {
+        let source = r#"
+button.spawn((
+    TextBundle::default(),
+    LocalizedText::new(
+        "ui.play",
+        "Play \"now\"",
+    ),
+));
+let dynamic = LocalizedText::new(id, source);
+// LocalizedText::new("ui.comment", "Comment")
+#[cfg(test)]
+let test = LocalizedText::new("ui.test", "Test");
+"#;
+        let messages = source_messages("src/menu.rs", source);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id, "ui.play");
+        assert_eq!(messages[0].source, "Play \"now\"");
+        assert_eq!(messages[0].references, ["src/menu.rs:4"]);
+    }
+
+    #[test]
+    fn extracts_main_menu_items() {
+        let text = r#"
+[[items]]
+action = "new_game"
+
+[[items]]
+action = "start"
+script = "scripts/epilogue.avg"
+if = "persistent.route_a == true"
+
+[[items]]
+action = "start"
+script = "scripts/extra.avg"
+label = "Bonus"
+id = "ui.bonus"
+"#;
+        let title: TitleScreen = toml::from_str(text).unwrap();
+        let summary: Vec<(String, String, String)> = title_messages("title.toml", &title, text)
+            .into_iter()
+            .map(|message| (message.id, message.source, message.references[0].clone()))
+            .collect();
+        let expected = [
+            ("ui.new_game", "New Game", "title.toml:2"),
+            ("ui.epilogue", "epilogue", "title.toml:5"),
+            ("ui.bonus", "Bonus", "title.toml:10"),
+        ]
+        .map(|(id, source, reference)| (id.to_string(), source.to_string(), reference.to_string()));
+        assert_eq!(summary, expected);
+    }
+
+    #[test]
+    fn merges_marking_changed_texts_fuzzy() {
+        let message = |id: &str, source: &str| Message {
+            id: id.to_string(),
+            source: source.to_string(),
+            references: Vec::new(),
+            notes: Vec::new(),
+        };
+        let translated = |id: &str, source: &str, translation: &str| PoEntry {
+            context: Some(id.to_string()),
+            source: source.to_string(),
+            translation: translation.to_string(),
+            ..Default::default()
+        };
+        let template = template(&[
+            message("same", "不变"),
+            message("edited", "改过的台词"),
+            message("start.1234", "四下无人了。"),
+            message("new", "新台词"),
+            message("same", "不变"),
+        ]);
+        let existing = vec![
+            header(Some("en")),
+            translated("same", "不变", "Unchanged"),
+            translated("edited", "原来的台词", "The old line"),
+            translated("start.abcd", "四下无人。", "Nobody around."),
+            translated("gone", "删掉的", "Removed"),
+        ];
+        let (merged, report) = merge(&template, &existing);
+        assert_eq!(
+            report,
+            MergeReport {
+                fuzzy: 2,
+                new: 1,
+                obsolete: 2,
+            }
+        );
+        assert_eq!(merged.len(), 5);
+        assert!(merged[0].translation.contains("Language: en"));
+        assert_eq!(merged[1].translation, "Unchanged");
+        assert!(!merged[1].is_fuzzy());
+        assert!(merged[2].is_fuzzy());
+        assert_eq!(merged[2].previous.as_deref(), Some("原来的台词"));
+        assert_eq!(merged[3].translation, "Nobody around.");
+        assert!(merged[3].is_fuzzy());
+        assert_eq!(merged[4].translation, "");
+        assert_eq!(completion(&merged), (1, 4));
+    }
+
+    #[test]
+    fn writes_xliff() {
+        let mut entry = PoEntry {
+            extracted: vec!["Speaker: Alice".to_string()],
+            references: vec!["scripts/a.avg:2".to_string()],
+            context: Some("start.1234".to_string()),
+            source: "<你好>".to_string(),
+            translation: "<Hello>".to_string(),
+            ..Default::default()
+        };
+        entry.set_fuzzy(true);
+        let xliff = to_xliff(&[header(Some("en")), entry], "zh", Some("en"));
+        assert!(xliff.contains("source-language=\"zh\" target-language=\"en\""));
+        assert!(xliff.contains("<trans-unit id=\"start.1234\">"));
+        assert!(xliff.contains("<source>&lt;你好&gt;</source>"));
+        assert!(xliff.contains("<target state=\"needs-review-translation\">&lt;Hello&gt;</target>"));
+        assert!(xliff.contains("<context context-type=\"linenumber\">2</context>"));
+    }
+}
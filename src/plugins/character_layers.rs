@@ -0,0 +1,279 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+
+use crate::core::atlas::{AtlasIndex, ATLAS_INDEX_EXTENSION};
+
+/// A Bevy Plugin drawing layered characters from the atlases `avg-atlas`
+/// packs.
+///
+/// Load a `.atlas.ron` index as a [`CharacterAtlas`] and give an entity a
+/// [`CharacterLayers`] naming the layers to show, bottom first. Every layer
+/// becomes a child sprite showing its section of the atlas, placed where it
+/// was on the character's canvas, which is centred on the entity. Changing
+/// the layers, e.g. to switch the expression, respawns the children.
+pub struct CharacterLayersPlugin;
+
+/// The layers of a character, each a section of one of its atlas pages.
+#[derive(Asset, TypePath, Debug)]
+pub struct CharacterAtlas {
+    /// The size of the canvas the layers were drawn on.
+    pub canvas: UVec2,
+    pub pages: Vec<Handle<Image>>,
+    /// The layout of each page, with a section for each of its layers.
+    pub layouts: Vec<Handle<TextureAtlasLayout>>,
+    pub layers: BTreeMap<String, AtlasSection>,
+}
+
+/// Where a layer of a [`CharacterAtlas`] is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasSection {
+    pub page: usize,
+    /// The index of the section in the page's layout.
+    pub index: usize,
+    pub size: UVec2,
+    /// The top left corner of the layer on the canvas.
+    pub offset: UVec2,
+}
+
+impl CharacterAtlas {
+    /// The centre of `section` relative to the centre of the canvas, in
+    /// world units (y up).
+    pub fn layer_translation(&self, section: &AtlasSection) -> Vec2 {
+        let centre = section.offset.as_vec2() + section.size.as_vec2() / 2.0;
+        let canvas = self.canvas.as_vec2() / 2.0;
+        Vec2::new(centre.x - canvas.x, canvas.y - centre.y)
+    }
+}
+
+/// The layers shown on an entity, bottom first.
+#[derive(Component, Clone, Debug, Default)]
+pub struct CharacterLayers {
+    pub atlas: Handle<CharacterAtlas>,
+    pub layers: Vec<String>,
+}
+
+impl CharacterLayers {
+    pub fn new(
+        atlas: Handle<CharacterAtlas>,
+        layers: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self {
+            atlas,
+            layers: layers.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// A child sprite drawing one of the [`CharacterLayers`].
+#[derive(Component)]
+pub struct LayerSprite;
+
+#[derive(Default)]
+struct CharacterAtlasLoader;
+
+impl AssetLoader for CharacterAtlasLoader {
+    type Asset = CharacterAtlas;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<CharacterAtlas, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let index = AtlasIndex::from_ron(std::str::from_utf8(&bytes)?)?;
+
+        // 图集页的路径相对于索引文件
+        let dir = load_context
+            .path()
+            .parent()
+            .map(|dir| dir.to_path_buf())
+            .unwrap_or_default();
+        let pages = index
+            .pages
+            .iter()
+            .map(|page| load_context.load(dir.join(&page.path)))
+            .collect();
+        let mut layouts: Vec<TextureAtlasLayout> = index
+            .pages
+            .iter()
+            .map(|page| TextureAtlasLayout::new_empty(page.size))
+            .collect();
+        let mut layers = BTreeMap::new();
+        for (name, layer) in index.layers {
+            let layout = layouts
+                .get_mut(layer.page)
+                .ok_or(format!("layer {name} is on a missing page {}", layer.page))?;
+            let section = AtlasSection {
+                page: layer.page,
+                index: layout.add_texture(layer.rect),
+                size: layer.rect.size(),
+                offset: layer.offset,
+            };
+            layers.insert(name, section);
+        }
+        let layouts = layouts
+            .into_iter()
+            .enumerate()
+            .map(|(page, layout)| load_context.add_labeled_asset(format!("page{page}"), layout))
+            .collect();
+
+        Ok(CharacterAtlas {
+            canvas: index.canvas,
+            pages,
+            layouts,
+            layers,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[ATLAS_INDEX_EXTENSION]
+    }
+}
+
+impl Plugin for CharacterLayersPlugin {
+    fn build(&self, app: &mut App) {
+        // 无窗口时没有 SpritePlugin
+        if !app
+            .world()
+            .contains_resource::<Assets<TextureAtlasLayout>>()
+        {
+            app.init_asset::<TextureAtlasLayout>();
+        }
+        app.init_asset::<CharacterAtlas>()
+            .init_asset_loader::<CharacterAtlasLoader>()
+            .add_systems(PostUpdate, spawn_layer_sprites);
+    }
+}
+
+fn spawn_layer_sprites(
+    mut commands: Commands,
+    atlases: Res<Assets<CharacterAtlas>>,
+    mut atlas_events: EventReader<AssetEvent<CharacterAtlas>>,
+    query: Query<(Entity, Ref<CharacterLayers>, Option<&Children>)>,
+    layer_query: Query<(), With<LayerSprite>>,
+) {
+    let loaded: Vec<AssetId<CharacterAtlas>> = atlas_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Added { id }
+            | AssetEvent::Modified { id }
+            | AssetEvent::LoadedWithDependencies { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, layers, children) in query.iter() {
+        if !(layers.is_changed() || loaded.contains(&layers.atlas.id())) {
+            continue;
+        }
+        for child in children.into_iter().flatten() {
+            if layer_query.contains(*child) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+        // 图集加载完成时会再次生成
+        let Some(atlas) = atlases.get(&layers.atlas) else {
+            continue;
+        };
+        commands.entity(entity).with_children(|parent| {
+            for (order, name) in layers.layers.iter().enumerate() {
+                let Some(section) = atlas.layers.get(name) else {
+                    warn!("character atlas has no layer {name}");
+                    continue;
+                };
+                // 后面的图层画在上面
+                let translation = atlas.layer_translation(section).extend(order as f32 * 0.01);
+                parent.spawn((
+                    SpriteBundle {
+                        texture: atlas.pages[section.page].clone(),
+                        transform: Transform::from_translation(translation),
+                        ..default()
+                    },
+                    TextureAtlas {
+                        layout: atlas.layouts[section.page].clone(),
+                        index: section.index,
+                    },
+                    LayerSprite,
+                ));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_harness::TestApp;
+
+    fn atlas() -> CharacterAtlas {
+        let section = |index, offset: UVec2| AtlasSection {
+            page: 0,
+            index,
+            size: UVec2::new(20, 10),
+            offset,
+        };
+        CharacterAtlas {
+            canvas: UVec2::new(100, 200),
+            pages: vec![Handle::default()],
+            layouts: vec![Handle::default()],
+            layers: BTreeMap::from([
+                ("body".to_string(), section(0, UVec2::new(40, 95))),
+                ("face/smile".to_string(), section(1, UVec2::new(0, 0))),
+                ("face/angry".to_string(), section(2, UVec2::new(80, 190))),
+            ]),
+        }
+    }
+
+    fn layer_sprites(app: &mut TestApp, character: Entity) -> Vec<(usize, Vec3)> {
+        app.get::<Children>(character)
+            .iter()
+            .map(|child| {
+                (
+                    app.get::<TextureAtlas>(*child).index,
+                    app.get::<Transform>(*child).translation,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spawns_a_sprite_per_layer_and_switches_expressions() {
+        let mut app = TestApp::new(CharacterLayersPlugin);
+        let handle = app
+            .world_mut()
+            .resource_mut::<Assets<CharacterAtlas>>()
+            .add(atlas());
+        let character = app
+            .world_mut()
+            .spawn((
+                SpatialBundle::default(),
+                CharacterLayers::new(handle, ["body", "face/smile"]),
+            ))
+            .id();
+        app.update();
+        assert_eq!(
+            layer_sprites(&mut app, character),
+            [
+                (0, Vec3::new(0.0, 0.0, 0.0)),
+                (1, Vec3::new(-40.0, 95.0, 0.01)),
+            ]
+        );
+
+        app.get_mut::<CharacterLayers>(character).layers[1] = "face/angry".to_string();
+        app.update();
+        assert_eq!(
+            layer_sprites(&mut app, character),
+            [
+                (0, Vec3::new(0.0, 0.0, 0.0)),
+                (2, Vec3::new(40.0, -95.0, 0.01)),
+            ]
+        );
+        assert_eq!(app.entities_with::<LayerSprite>().len(), 2);
+    }
+}
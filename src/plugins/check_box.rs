@@ -1,8 +1,16 @@
 use bevy::prelude::*;
 
+use crate::plugins::focus::{FocusPlugin, Focusable};
+
 pub struct CheckboxPlugin;
 
-#[derive(Component)]
+/// A checkbox, radio button or toggle switch.
+///
+/// Insert it on a `NodeBundle` or spawn it with [`CheckboxBuilder`]; the
+/// visuals are spawned as children on the next update. Checkboxes sharing a
+/// `group` behave as radio buttons: checking one unchecks the others, and a
+/// checked one can not be unchecked by clicking it again.
+#[derive(Component, Clone)]
 pub struct Checkbox {
     pub checked: bool,
     pub size: f32,
@@ -10,8 +18,34 @@ pub struct Checkbox {
     pub check_color: Color,
     pub hovered_color: Color,
     pub disabled: bool,
+    pub disabled_color: Color,
+    pub variant: CheckboxVariant,
+    /// Radio group id, `None` for an independent checkbox.
+    pub group: Option<u32>,
+}
+
+/// How a [`Checkbox`] is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CheckboxVariant {
+    /// A square box with a check mark.
+    #[default]
+    Box,
+    /// A rounded track with a knob sliding to the right when checked.
+    Switch,
 }
 
+/// Sent whenever a [`Checkbox`] is checked or unchecked by the user,
+/// including radio buttons unchecked by a sibling of their group.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CheckboxChanged {
+    pub entity: Entity,
+    pub checked: bool,
+}
+
+/// A text label toggling its checkbox when clicked.
+#[derive(Component)]
+pub struct CheckboxLabel(pub Entity);
+
 #[derive(Component)]
 struct CheckboxBackground;
 
@@ -20,9 +54,14 @@ struct CheckboxCheck;
 
 impl Plugin for CheckboxPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, entities_spawn)
-            .add_systems(Update, checkbox_changed)
-            .add_systems(Update, (update_checkboxes, checkbox_interaction));
+        if !app.is_plugin_added::<FocusPlugin>() {
+            app.add_plugins(FocusPlugin);
+        }
+        app.add_event::<CheckboxChanged>()
+            .add_systems(
+                Update,
+                (update_checkboxes, checkbox_interaction, update_labels).chain(),
+            );
     }
 }
 
@@ -35,80 +74,248 @@ impl Default for Checkbox {
             check_color: Color::srgb(0.0, 0.8, 0.0),
             hovered_color: Color::srgb(0.25, 0.25, 0.25),
             disabled: false,
+            disabled_color: Color::srgb(0.35, 0.35, 0.35),
+            variant: CheckboxVariant::Box,
+            group: None,
+        }
+    }
+}
+
+impl Checkbox {
+    fn background_size(&self) -> Vec2 {
+        match self.variant {
+            CheckboxVariant::Box => Vec2::splat(self.size),
+            CheckboxVariant::Switch => Vec2::new(self.size * 2.0, self.size),
+        }
+    }
+
+    fn background_color(&self, hovered: bool) -> Color {
+        if self.disabled {
+            self.disabled_color
+        } else if hovered {
+            self.hovered_color
+        } else {
+            self.box_color
+        }
+    }
+
+    fn check_color(&self) -> Color {
+        if self.disabled {
+            self.check_color.with_alpha(0.4)
+        } else {
+            self.check_color
+        }
+    }
+
+    fn check_style(&self, style: &mut Style) {
+        let inner = self.size * 0.6;
+        style.width = Val::Px(inner);
+        style.height = Val::Px(inner);
+        match self.variant {
+            CheckboxVariant::Box => {
+                style.display = if self.checked {
+                    Display::Flex
+                } else {
+                    Display::None
+                };
+            }
+            CheckboxVariant::Switch => {
+                // 开关的滑块始终可见，只改变位置
+                let border = 2.0;
+                let travel = self.size * 2.0 - inner - border * 4.0;
+                style.display = Display::Flex;
+                style.position_type = PositionType::Absolute;
+                style.left = Val::Px(border + if self.checked { travel } else { 0.0 });
+            }
         }
     }
 }
 
+/// Spawns a [`Checkbox`] in a row with an optional clickable label.
+///
+/// ```rust,ignore
+/// CheckboxBuilder::new()
+///     .label("Fullscreen")
+///     .switch()
+///     .font(font)
+///     .spawn(parent);
+/// ```
+#[derive(Default)]
+pub struct CheckboxBuilder {
+    checkbox: Checkbox,
+    label: Option<String>,
+    font: Handle<Font>,
+    font_size: f32,
+}
+
+impl CheckboxBuilder {
+    pub fn new() -> Self {
+        Self {
+            font_size: 20.0,
+            ..default()
+        }
+    }
+
+    pub fn checked(mut self, checked: bool) -> Self {
+        self.checkbox.checked = checked;
+        self
+    }
+
+    pub fn size(mut self, size: f32) -> Self {
+        self.checkbox.size = size;
+        self
+    }
+
+    pub fn disabled(mut self, disabled: bool) -> Self {
+        self.checkbox.disabled = disabled;
+        self
+    }
+
+    /// Makes the checkbox a radio button of `group`.
+    pub fn group(mut self, group: u32) -> Self {
+        self.checkbox.group = Some(group);
+        self
+    }
+
+    /// Draws the checkbox as a toggle switch.
+    pub fn switch(mut self) -> Self {
+        self.checkbox.variant = CheckboxVariant::Switch;
+        self
+    }
+
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn font(mut self, font: Handle<Font>) -> Self {
+        self.font = font;
+        self
+    }
+
+    /// Spawns the row as a child of `parent`, returning the [`Checkbox`] entity.
+    pub fn spawn(self, parent: &mut ChildBuilder) -> Entity {
+        let mut checkbox_entity = Entity::PLACEHOLDER;
+        let background_size = self.checkbox.background_size();
+
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(8.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|row| {
+                checkbox_entity = row
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Px(background_size.x),
+                                height: Val::Px(background_size.y),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        self.checkbox.clone(),
+                    ))
+                    .id();
+
+                if let Some(label) = self.label {
+                    row.spawn((
+                        TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font: self.font,
+                                font_size: self.font_size,
+                                color: Color::WHITE,
+                            },
+                        ),
+                        Interaction::default(),
+                        CheckboxLabel(checkbox_entity),
+                    ));
+                }
+            });
+
+        checkbox_entity
+    }
+}
+
+#[allow(clippy::type_complexity)]
 fn update_checkboxes(
     mut commands: Commands,
-    query: Query<(Entity, &Checkbox, Option<&Children>)>,
-    check_query: Query<(&Children, &Parent), With<CheckboxBackground>>,
-    mut check_sign_query: Query<(&mut Style, &mut BackgroundColor), With<CheckboxCheck>>,
+    query: Query<(Entity, Ref<Checkbox>, Option<&Children>)>,
+    mut background_query: Query<
+        (&Children, &Interaction, &mut BackgroundColor),
+        (With<CheckboxBackground>, Without<CheckboxCheck>),
+    >,
+    mut check_sign_query: Query<
+        (&mut Style, &mut BackgroundColor),
+        (With<CheckboxCheck>, Without<CheckboxBackground>),
+    >,
 ) {
     for (entity, checkbox, children) in query.iter() {
         match children {
             Some(children) => {
+                if !checkbox.is_changed() {
+                    continue;
+                }
                 // 更新现有复选框
                 for &child in children.iter() {
-                    // assert_eq!(children.type_id(),child.type_id());
-                    if let Ok((children1, parent)) = check_query.get(child)
-                    {
-                        if parent.get() == entity {
-                            for &child1 in children1 {
-                                if let Ok((mut ch_style, mut ch_bg_color)) =
-                                    check_sign_query.get_mut(child1)
-                                {
-                                    ch_style.display = if checkbox.checked {
-                                        Display::Flex
-                                    } else {
-                                        Display::None
-                                    };
-                                    ch_bg_color.0 = checkbox.check_color;
-                                } else {
-                                    return;
-                                }
-                            }
-                        } else {
-                            return;
+                    let Ok((check_children, interaction, mut bg_color)) =
+                        background_query.get_mut(child)
+                    else {
+                        continue;
+                    };
+                    bg_color.0 = checkbox.background_color(*interaction != Interaction::None);
+                    for &check in check_children.iter() {
+                        if let Ok((mut ch_style, mut ch_bg_color)) = check_sign_query.get_mut(check)
+                        {
+                            checkbox.check_style(&mut ch_style);
+                            ch_bg_color.0 = checkbox.check_color();
                         }
                     }
                 }
             }
             None => {
                 // 创建新的复选框
+                let background_size = checkbox.background_size();
+                let mut check_style = Style::default();
+                checkbox.check_style(&mut check_style);
+                let border_radius = match checkbox.variant {
+                    CheckboxVariant::Box => BorderRadius::ZERO,
+                    CheckboxVariant::Switch => BorderRadius::MAX,
+                };
+
                 let background = commands
                     .spawn((
                         NodeBundle {
                             style: Style {
-                                width: Val::Px(checkbox.size),
-                                height: Val::Px(checkbox.size),
+                                width: Val::Px(background_size.x),
+                                height: Val::Px(background_size.y),
                                 border: UiRect::all(Val::Px(2.0)),
                                 justify_content: JustifyContent::Center,
                                 align_items: AlignItems::Center,
                                 ..default()
                             },
                             border_color: Color::WHITE.into(),
-                            background_color: checkbox.box_color.into(),
+                            border_radius,
+                            background_color: checkbox.background_color(false).into(),
                             ..default()
                         },
                         Interaction::default(),
+                        Focusable::default(),
                         CheckboxBackground,
                     ))
                     .with_children(|parent| {
                         // 勾选标记
                         parent.spawn((
                             NodeBundle {
-                                style: Style {
-                                    width: Val::Px(checkbox.size * 0.6),
-                                    height: Val::Px(checkbox.size * 0.6),
-                                    display: if checkbox.checked {
-                                        Display::Flex
-                                    } else {
-                                        Display::None
-                                    },
-                                    ..default()
-                                },
-                                background_color: checkbox.check_color.into(),
+                                style: check_style,
+                                border_radius,
+                                background_color: checkbox.check_color().into(),
                                 ..default()
                             },
                             CheckboxCheck,
@@ -122,101 +329,179 @@ fn update_checkboxes(
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn checkbox_interaction(
-    mut checkbox_query: Query<(&mut Checkbox, &Children)>,
+    mut checkbox_query: Query<(Entity, &mut Checkbox)>,
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor, &Parent),
         (Changed<Interaction>, With<CheckboxBackground>),
     >,
+    label_query: Query<(&Interaction, &CheckboxLabel), Changed<Interaction>>,
+    mut changed_events: EventWriter<CheckboxChanged>,
 ) {
+    let mut pressed = Vec::new();
+
     for (interaction, mut background_color, parent) in interaction_query.iter_mut() {
-        if let Ok((mut checkbox, _)) = checkbox_query.get_mut(parent.get()) {
-            if checkbox.disabled {
-                continue;
-            }
+        let Ok((entity, checkbox)) = checkbox_query.get(parent.get()) else {
+            continue;
+        };
+        match *interaction {
+            Interaction::Pressed => pressed.push(entity),
+            Interaction::Hovered => background_color.0 = checkbox.background_color(true),
+            Interaction::None => background_color.0 = checkbox.background_color(false),
+        }
+    }
+    for (interaction, label) in label_query.iter() {
+        if *interaction == Interaction::Pressed {
+            pressed.push(label.0);
+        }
+    }
 
-            match *interaction {
-                Interaction::Pressed => {
-                    checkbox.checked = !checkbox.checked;
-                }
-                Interaction::Hovered => {
-                    background_color.0 = checkbox.hovered_color;
-                }
-                Interaction::None => {
-                    background_color.0 = checkbox.box_color;
-                }
+    for entity in pressed {
+        let Ok((_, mut checkbox)) = checkbox_query.get_mut(entity) else {
+            continue;
+        };
+        if checkbox.disabled {
+            continue;
+        }
+        let group = checkbox.group;
+        if group.is_some() && checkbox.checked {
+            // 单选按钮不能通过再次点击取消
+            continue;
+        }
+        checkbox.checked = !checkbox.checked;
+        changed_events.send(CheckboxChanged {
+            entity,
+            checked: checkbox.checked,
+        });
+
+        if group.is_none() {
+            continue;
+        }
+        for (other, mut checkbox) in checkbox_query.iter_mut() {
+            if other != entity && checkbox.group == group && checkbox.checked {
+                checkbox.checked = false;
+                changed_events.send(CheckboxChanged {
+                    entity: other,
+                    checked: false,
+                });
             }
         }
     }
 }
 
-// 使用示例
-fn entities_spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // Root node
-    commands
-        .spawn(NodeBundle {
-            style: Style {
-                top: Val::Px(100.0),
-                width: Val::Px(100.0),
-                height: Val::Px(100.0),
-                flex_direction: FlexDirection::Column,
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::Center,
-                row_gap: Val::Px(10.0),
-                ..default()
-            },
-            ..default()
-        })
-        .with_children(|parent| {
-            // 复选框容器
-            parent
-                .spawn(NodeBundle {
-                    style: Style {
-                        flex_direction: FlexDirection::Row,
-                        align_items: AlignItems::Center,
-                        column_gap: Val::Px(8.0),
-                        ..default()
-                    },
-                    ..default()
-                })
-                .with_children(|parent| {
-                    // 复选框
-                    parent.spawn((
-                        NodeBundle {
-                            style: Style {
-                                width: Val::Px(24.0),
-                                height: Val::Px(24.0),
-                                ..default()
-                            },
-                            ..default()
-                        },
-                        Checkbox {
-                            checked: true,
-                            size: 24.0,
-                            box_color: Color::srgb(0.2, 0.2, 0.2),
-                            check_color: Color::srgb(0.0, 0.8, 0.0),
-                            hovered_color: Color::srgb(0.25, 0.25, 0.25),
-                            disabled: false,
-                        },
-                    ));
-                    let font = asset_server.load("fonts/zfft.ttf");
-                    // 标签文本
-                    parent.spawn(TextBundle::from_section(
-                        "checkbox",
-                        TextStyle {
-                            font,
-                            font_size: 20.0,
-                            color: Color::WHITE,
-                            ..default()
-                        },
-                    ));
-                });
-        });
+fn update_labels(
+    checkbox_query: Query<&Checkbox, Changed<Checkbox>>,
+    mut label_query: Query<(&mut Text, &CheckboxLabel)>,
+) {
+    for (mut text, label) in label_query.iter_mut() {
+        let Ok(checkbox) = checkbox_query.get(label.0) else {
+            continue;
+        };
+        let color = if checkbox.disabled {
+            checkbox.disabled_color
+        } else {
+            Color::WHITE
+        };
+        for section in text.sections.iter_mut() {
+            section.style.color = color;
+        }
+    }
 }
 
-// 监听复选框状态变化
-fn checkbox_changed(query: Query<&Checkbox, Changed<Checkbox>>) {
-    for checkbox in query.iter() {
-        println!("Checkbox state changed: {}", checkbox.checked);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_harness::TestApp;
+
+    /// Spawns a checkbox from `builder` and lets the plugin build its visuals.
+    fn spawn(app: &mut TestApp, builder: CheckboxBuilder) -> Entity {
+        let mut checkbox = Entity::PLACEHOLDER;
+        app.spawn_ui(|parent| checkbox = builder.spawn(parent));
+        app.update();
+        checkbox
+    }
+
+    fn background(app: &TestApp, checkbox: Entity) -> Entity {
+        app.get::<Children>(checkbox)[0]
+    }
+
+    fn check_display(app: &TestApp, checkbox: Entity) -> Display {
+        let check = app.get::<Children>(background(app, checkbox))[0];
+        app.get::<Style>(check).display
+    }
+
+    #[test]
+    fn clicking_toggles_the_checkbox() {
+        let mut app = TestApp::new(CheckboxPlugin);
+        let checkbox = spawn(&mut app, CheckboxBuilder::new());
+        assert_eq!(check_display(&app, checkbox), Display::None);
+
+        app.click(background(&app, checkbox));
+        assert!(app.get::<Checkbox>(checkbox).checked);
+        assert_eq!(check_display(&app, checkbox), Display::Flex);
+        assert_eq!(
+            app.drain_events::<CheckboxChanged>(),
+            [CheckboxChanged {
+                entity: checkbox,
+                checked: true
+            }]
+        );
+
+        app.click(background(&app, checkbox));
+        assert!(!app.get::<Checkbox>(checkbox).checked);
+        assert_eq!(check_display(&app, checkbox), Display::None);
+    }
+
+    #[test]
+    fn clicking_the_label_toggles_the_checkbox() {
+        let mut app = TestApp::new(CheckboxPlugin);
+        let checkbox = spawn(&mut app, CheckboxBuilder::new().label("label"));
+        let label = app.entities_with::<CheckboxLabel>()[0];
+
+        app.click(label);
+        assert!(app.get::<Checkbox>(checkbox).checked);
+    }
+
+    #[test]
+    fn disabled_checkbox_ignores_clicks() {
+        let mut app = TestApp::new(CheckboxPlugin);
+        let checkbox = spawn(&mut app, CheckboxBuilder::new().disabled(true));
+
+        app.click(background(&app, checkbox));
+        assert!(!app.get::<Checkbox>(checkbox).checked);
+        assert!(app.drain_events::<CheckboxChanged>().is_empty());
+    }
+
+    #[test]
+    fn radio_buttons_of_a_group_exclude_each_other() {
+        let mut app = TestApp::new(CheckboxPlugin);
+        let a = spawn(&mut app, CheckboxBuilder::new().group(1).checked(true));
+        let b = spawn(&mut app, CheckboxBuilder::new().group(1));
+        let other_group = spawn(&mut app, CheckboxBuilder::new().group(2).checked(true));
+
+        app.click(background(&app, b));
+        assert!(!app.get::<Checkbox>(a).checked);
+        assert!(app.get::<Checkbox>(b).checked);
+        assert!(app.get::<Checkbox>(other_group).checked);
+        assert_eq!(app.drain_events::<CheckboxChanged>().len(), 2);
+
+        // 已选中的单选按钮不能再次点击取消
+        app.click(background(&app, b));
+        assert!(app.get::<Checkbox>(b).checked);
+        assert!(app.drain_events::<CheckboxChanged>().is_empty());
+    }
+
+    #[test]
+    fn hovering_highlights_the_box() {
+        let mut app = TestApp::new(CheckboxPlugin);
+        let checkbox = spawn(&mut app, CheckboxBuilder::new());
+        let background = background(&app, checkbox);
+        let colors = app.get::<Checkbox>(checkbox).clone();
+
+        app.set_interaction(background, Interaction::Hovered);
+        assert_eq!(app.get::<BackgroundColor>(background).0, colors.hovered_color);
+        app.set_interaction(background, Interaction::None);
+        assert_eq!(app.get::<BackgroundColor>(background).0, colors.box_color);
     }
 }
@@ -1,34 +1,333 @@
 use crate::plugins::drop_down::*;
+use crate::plugins::focus::FocusScope;
 use crate::plugins::processor_bar::{ProgressBarPlugin};
-use bevy::a11y::accesskit::Size;
 use bevy::app::{App, Plugin};
 use bevy::prelude::*;
+use std::time::Duration;
+use crate::core::config_store::ConfigStore;
+use crate::core::locale::{Locale, LocalizedText};
+use crate::global_def::global_define::*;
 use crate::plugins::check_box::CheckboxPlugin;
+use crate::plugins::game_state::{GameState, GameStatePlugin, MenuState, MENU_Z_INDEX};
+use crate::plugins::layout::UiRoot;
 use crate::plugins::slider_bar::SliderBarPlugin;
 
+/// A Bevy Plugin with the settings menu, [`MenuState::Settings`], and
+/// saving the [`GameConfig`].
 pub struct ConfigPlugin;
 
-pub(crate) struct ScreenResource {
-    size: Size,
-}
+/// Send to write the current [`GameConfig`] to the user config file.
+#[derive(Event, Clone, Copy, Default)]
+pub struct SaveConfigEvent;
+
+/// How long the [`GameConfig`] has to stay unchanged before it is saved, so
+/// that dragging a slider doesn't write the file every frame.
+pub const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+/// Send to restore the [`GameConfig`] defaults and write them to the user config file.
+#[derive(Event, Clone, Copy, Default)]
+pub struct ResetConfigEvent;
 
 impl Plugin for ConfigPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_entities)
-            .add_plugins(DropDownPlugin {
-                options: DropDownOptions {
-                    options: vec!["720p".to_string(), "1080p".to_string(), "1440p".to_string()],
+        // 控件插件可能已由 AvgPlugin 或加载界面添加
+        if !app.is_plugin_added::<ProgressBarPlugin>() {
+            app.add_plugins(ProgressBarPlugin);
+        }
+        if !app.is_plugin_added::<DropDownPlugin>() {
+            app.add_plugins(DropDownPlugin);
+        }
+        if !app.is_plugin_added::<SliderBarPlugin>() {
+            app.add_plugins(SliderBarPlugin);
+        }
+        if !app.is_plugin_added::<CheckboxPlugin>() {
+            app.add_plugins(CheckboxPlugin);
+        }
+        if !app.is_plugin_added::<GameStatePlugin>() {
+            app.add_plugins(GameStatePlugin);
+        }
+        app.add_event::<SaveConfigEvent>()
+            .add_event::<ResetConfigEvent>()
+            .add_systems(Startup, report_config_issues)
+            .add_systems(OnEnter(MenuState::Settings), spawn_entities)
+            .add_systems(OnExit(MenuState::Settings), request_save)
+            .add_systems(
+                Update,
+                (
+                    (
+                        resolution_drop_down_changed,
+                        language_drop_down_changed,
+                        leave_settings,
+                    )
+                        .run_if(in_state(MenuState::Settings)),
+                    reset_config,
+                    save_config,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Marks the dropdown that selects [`GameConfig::resolution`].
+#[derive(Component)]
+struct ResolutionDropDown;
+
+/// The dropdown that selects [`GameConfig::language`], with the languages
+/// it offers.
+#[derive(Component)]
+struct LanguageDropDown(Vec<String>);
+
+/// The button leaving the settings menu.
+#[derive(Component)]
+struct SettingsBackButton;
+
+fn spawn_entities(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    locale: Option<Res<Locale>>,
+    ui_root: Res<UiRoot>,
+) {
+    let selected = RESOLUTION_VARIANTS
+        .iter()
+        .position(|(_, resolution)| *resolution == config.resolution)
+        .unwrap_or_default();
+    let builder = DropDownBuilder::new(RESOLUTION_VARIANTS.iter().map(|(name, _)| *name))
+        .selected(selected)
+        .size(Vec2::new(150.0, 30.0))
+        .max_list_height(90.0)
+        .font(asset_server.load("fonts/FiraSans-Bold.ttf"))
+        .font_size(24.0)
+        .arrow(asset_server.load("component/dropdownctl.png"))
+        .style(Style {
+            position_type: PositionType::Absolute,
+            ..default()
+        });
+
+    let mut menu = Entity::PLACEHOLDER;
+    commands.entity(ui_root.0).with_children(|root| {
+        menu = root
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: Color::srgba(0.0, 0.0, 0.0, 0.8).into(),
+                    z_index: ZIndex::Global(MENU_Z_INDEX),
                     ..default()
                 },
-                ..default()
+                Name::new("settings_menu"),
+                StateScoped(MenuState::Settings),
+                FocusScope,
+            ))
+            .with_children(|menu| {
+                menu.spawn((
+                    ButtonBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            right: Val::Px(20.0),
+                            bottom: Val::Px(20.0),
+                            width: Val::Px(150.0),
+                            height: Val::Px(40.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        border_radius: BorderRadius::MAX,
+                        background_color: Color::srgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    },
+                    SettingsBackButton,
+                ))
+                .with_children(|button| {
+                    button.spawn((
+                        TextBundle::from_section(
+                            "Back",
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 24.0,
+                                color: Color::srgb(0.9, 0.9, 0.9),
+                            },
+                        ),
+                        LocalizedText::new("ui.back", "Back"),
+                    ));
+                });
             })
-            .add_plugins(SliderBarPlugin)
-            .add_plugins(ProgressBarPlugin)
-            .add_plugins(CheckboxPlugin);
-        // .add_systems(Update, );
+            .id();
+    });
+
+    let mut drop_down = Entity::PLACEHOLDER;
+    commands
+        .entity(menu)
+        .with_children(|menu| drop_down = builder.spawn(menu));
+    commands.entity(drop_down).insert(ResolutionDropDown);
+
+    // 只有一种语言时不显示语言选项
+    let Some(locale) = locale.filter(|locale| locale.languages().len() > 1) else {
+        return;
+    };
+    let languages: Vec<String> = locale.languages().into_iter().map(str::to_string).collect();
+    let selected = languages
+        .iter()
+        .position(|language| *language == config.language)
+        .unwrap_or_default();
+    let builder = DropDownBuilder::new(languages.iter().map(String::as_str))
+        .selected(selected)
+        .size(Vec2::new(100.0, 30.0))
+        .max_list_height(90.0)
+        .font(asset_server.load("fonts/FiraSans-Bold.ttf"))
+        .font_size(24.0)
+        .arrow(asset_server.load("component/dropdownctl.png"))
+        .style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(160.0),
+            ..default()
+        });
+    commands
+        .entity(menu)
+        .with_children(|menu| drop_down = builder.spawn(menu));
+    commands.entity(drop_down).insert(LanguageDropDown(languages));
+}
+
+fn leave_settings(
+    button_query: Query<&Interaction, (Changed<Interaction>, With<SettingsBackButton>)>,
+    game: Res<State<GameState>>,
+    menu: Res<State<MenuState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    if !button_query.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        return;
+    }
+    if let Some(back) = menu.back(*game.get()) {
+        next_menu.set(back);
+    }
+}
+
+fn resolution_drop_down_changed(
+    mut events: EventReader<DropDownChanged>,
+    mut config: ResMut<GameConfig>,
+    drop_down_query: Query<(), With<ResolutionDropDown>>,
+) {
+    for event in events.read() {
+        if !drop_down_query.contains(event.entity) {
+            continue;
+        }
+        if let Some((_, resolution)) = RESOLUTION_VARIANTS.get(event.index) {
+            config.resolution = *resolution;
+        }
+    }
+}
+
+fn language_drop_down_changed(
+    mut events: EventReader<DropDownChanged>,
+    mut config: ResMut<GameConfig>,
+    drop_down_query: Query<&LanguageDropDown>,
+) {
+    for event in events.read() {
+        let Ok(LanguageDropDown(languages)) = drop_down_query.get(event.entity) else {
+            continue;
+        };
+        if let Some(language) = languages.get(event.index) {
+            config.language.clone_from(language);
+        }
+    }
+}
+
+/// Saves the changes made in the settings menu right away when it is left.
+fn request_save(mut save_events: EventWriter<SaveConfigEvent>) {
+    save_events.send(SaveConfigEvent);
+}
+
+fn report_config_issues(store: Option<Res<ConfigStore>>) {
+    let Some(store) = store else {
+        return;
+    };
+    for issue in store.issues.iter() {
+        if issue.key.is_empty() {
+            warn!("config {}: {}", store.path.display(), issue.message);
+        } else {
+            warn!("config {} `{}`: {}", store.path.display(), issue.key, issue.message);
+        }
     }
 }
 
-fn spawn_entities( mut commands: Commands, _asset_server: ResMut<AssetServer>) {
+fn reset_config(
+    mut events: EventReader<ResetConfigEvent>,
+    mut config: ResMut<GameConfig>,
+    store: Option<ResMut<ConfigStore>>,
+    mut save_events: EventWriter<SaveConfigEvent>,
+) {
+    if events.read().last().is_none() {
+        return;
+    }
+    match store {
+        Some(mut store) => store.reset(&mut config),
+        None => *config = GameConfig::default(),
+    }
+    save_events.send(SaveConfigEvent);
+}
 
+/// Saves the config on request, and once it stopped changing for
+/// [`SAVE_DELAY`] after another system changed it.
+fn save_config(
+    time: Res<Time>,
+    mut events: EventReader<SaveConfigEvent>,
+    config: Res<GameConfig>,
+    store: Option<Res<ConfigStore>>,
+    mut pending: Local<Option<Timer>>,
+) {
+    // 拖动滑块时每帧都在修改，停下来之后才写入文件
+    if config.is_changed() && !config.is_added() {
+        *pending = Some(Timer::new(SAVE_DELAY, TimerMode::Once));
+    }
+    let settled = pending
+        .as_mut()
+        .is_some_and(|timer| timer.tick(time.delta()).finished());
+    let requested = events.read().last().is_some();
+    if !requested && !settled {
+        return;
+    }
+    *pending = None;
+    let Some(store) = store else {
+        return;
+    };
+    match store.save(&config) {
+        Ok(()) => info!("config saved to {}", store.path.display()),
+        Err(err) => error!("can not save config to {}: {}", store.path.display(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_harness::TestApp;
+    use std::fs;
+
+    #[test]
+    fn changes_in_a_row_are_saved_once() {
+        let path = std::env::temp_dir().join(format!("avg_config_{}.toml", std::process::id()));
+        let (config, store) = ConfigStore::load(&path);
+        let mut app = TestApp::new(move |app: &mut App| {
+            app.insert_resource(config.clone())
+                .insert_resource(store.clone())
+                .add_plugins(ConfigPlugin);
+        });
+
+        // 拖动滑块时连续几帧修改音量
+        for volume in [0.25, 0.5, 0.75] {
+            app.world_mut().resource_mut::<GameConfig>().master_volume = volume;
+            app.update();
+            assert!(!path.exists());
+        }
+        app.step(60);
+        let (saved, _) = ConfigStore::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(saved.master_volume, 0.75);
+
+        app.step(60);
+        assert!(!path.exists());
+    }
 }
@@ -1,276 +1,618 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::ecs::system::EntityCommands;
 use bevy::prelude::*;
-use bevy::reflect::List;
+use bevy::ui::RelativeCursorPosition;
 use bevy::ui::Val::{Percent, Px};
 
-#[derive(Default)]
-pub struct DropDownPlugin {
-    pub(crate) options: DropDownOptions,
-    pub(crate) style: Style,
+use crate::plugins::focus::{gamepad_just_pressed, FocusPlugin, FocusScope};
+
+/// A Bevy Plugin driving every [`DropDown`] in the app.
+///
+/// Each dropdown keeps its own options and selection, so any number of them
+/// can be spawned with [`DropDownBuilder`] anywhere in a UI tree.
+pub struct DropDownPlugin;
+
+/// A dropdown selection list.
+///
+/// Lives on the root node spawned by [`DropDownBuilder`]. Set `selected_index`
+/// or `open` to update the widget, read `selected_index` to get the selection.
+#[derive(Component, Clone, Debug, Default)]
+pub struct DropDown {
+    pub options: Vec<String>,
+    pub selected_index: usize,
+    /// Whether the option list is currently shown.
+    pub open: bool,
+    /// The option list scrolls once its content is taller than this, in pixels.
+    pub max_list_height: f32,
+    /// How far the option list is scrolled, in pixels.
+    pub scroll: f32,
+}
+
+impl DropDown {
+    /// The currently selected option, `None` when there are no options.
+    pub fn selected_option(&self) -> Option<&str> {
+        self.options.get(self.selected_index).map(String::as_str)
+    }
 }
 
+/// Sent when the user selects an option of a [`DropDown`].
+#[derive(Event, Clone, Debug, PartialEq)]
+pub struct DropDownChanged {
+    /// The dropdown root entity.
+    pub entity: Entity,
+    pub index: usize,
+    pub value: String,
+}
+
+/// The button showing the current selection and toggling the list.
+#[derive(Component)]
+struct DropDownHeader(Entity);
+
 #[derive(Component)]
-struct ListVisibility(bool); // 用于控制列表可见性的组件
+struct DropDownHeaderText(Entity);
 
+/// The clipping node holding the option list.
 #[derive(Component)]
-struct ListShowText; // 用于控制列表可见性的组件
+struct DropDownList(Entity);
 
+/// The column of options, moved up and down when scrolling.
 #[derive(Component)]
-struct ItemVisibility(bool); // 用于控制列表可见性的组件
+struct DropDownListContent(Entity);
 
-#[derive(Resource, Reflect, Clone, Default)]
-pub struct DropDownOptions {
-    pub(crate) options: Vec<String>,
-    pub(crate) selected_index: usize,
-    pub(crate) selected_option: String,
+#[derive(Component)]
+struct DropDownItem {
+    dropdown: Entity,
+    index: usize,
 }
 
-impl DropDownOptions {
-    fn from_option(options: Vec<String>, option: String) -> Self {
-        let mut index = 0;
-        let mut sel_option = String::new();
-        for (n, item) in options.clone().iter().enumerate() {
-            let Some(val) = item.downcast_ref::<String>() else {
-                continue;
-            };
-            if *val == option {
-                index = n;
-                sel_option = option.clone();
-            }
-        }
+const HEADER_COLOR: Color = Color::srgb(0.15, 0.15, 0.15);
+const ITEM_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+const HOVERED_COLOR: Color = Color::srgb(0.827, 0.827, 0.827);
+const PRESSED_COLOR: Color = Color::srgb(0.0, 1.0, 0.0);
+
+/// Spawns a [`DropDown`] with its header and option list.
+///
+/// ```rust,ignore
+/// let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+/// parent.spawn(NodeBundle::default()).with_children(|parent| {
+///     DropDownBuilder::new(["720p", "1080p", "1440p"])
+///         .selected(1)
+///         .font(font)
+///         .spawn(parent);
+/// });
+/// ```
+pub struct DropDownBuilder {
+    options: Vec<String>,
+    selected_index: usize,
+    size: Vec2,
+    max_list_height: f32,
+    font: Handle<Font>,
+    font_size: f32,
+    arrow: Option<Handle<Image>>,
+    style: Style,
+}
+
+impl DropDownBuilder {
+    pub fn new<S: Into<String>>(options: impl IntoIterator<Item = S>) -> Self {
         Self {
-            options,
-            selected_index: index,
-            selected_option: sel_option,
+            options: options.into_iter().map(Into::into).collect(),
+            selected_index: 0,
+            size: Vec2::new(150.0, 30.0),
+            max_list_height: 150.0,
+            font: Handle::default(),
+            font_size: 24.0,
+            arrow: None,
+            style: Style::default(),
         }
     }
+
+    /// Selects the option at `index`, clamped to the option count.
+    pub fn selected(mut self, index: usize) -> Self {
+        self.selected_index = index;
+        self
+    }
+
+    /// The size of the header and of every option row.
+    pub fn size(mut self, size: Vec2) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn max_list_height(mut self, height: f32) -> Self {
+        self.max_list_height = height;
+        self
+    }
+
+    pub fn font(mut self, font: Handle<Font>) -> Self {
+        self.font = font;
+        self
+    }
+
+    pub fn font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// The arrow image shown at the right of the header.
+    pub fn arrow(mut self, image: Handle<Image>) -> Self {
+        self.arrow = Some(image);
+        self
+    }
+
+    /// Positioning of the dropdown root node; its size is always taken from [`Self::size`].
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Spawns the dropdown as a child of `parent`, returning the root entity.
+    pub fn spawn(self, parent: &mut ChildBuilder) -> Entity {
+        let mut root = parent.spawn_empty();
+        self.build(&mut root);
+        root.id()
+    }
+
+    fn build(self, root: &mut EntityCommands) {
+        let entity = root.id();
+        let selected_index = self.selected_index.min(self.options.len().saturating_sub(1));
+        let text_style = TextStyle {
+            font: self.font.clone(),
+            font_size: self.font_size,
+            color: Color::WHITE,
+        };
+
+        root.insert((
+            NodeBundle {
+                style: Style {
+                    width: Px(self.size.x),
+                    height: Px(self.size.y),
+                    ..self.style
+                },
+                background_color: BackgroundColor::from(HEADER_COLOR),
+                ..default()
+            },
+            DropDown {
+                options: self.options.clone(),
+                selected_index,
+                open: false,
+                max_list_height: self.max_list_height,
+                scroll: 0.0,
+            },
+            Name::new("drop_down"),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Percent(100.0),
+                            height: Percent(100.0),
+                            // vertically center child text
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: HEADER_COLOR.into(),
+                        ..default()
+                    },
+                    DropDownHeader(entity),
+                ))
+                .with_children(|header| {
+                    header.spawn((
+                        TextBundle {
+                            text: Text::from_section(
+                                self.options
+                                    .get(selected_index)
+                                    .cloned()
+                                    .unwrap_or_default(),
+                                text_style.clone(),
+                            ),
+                            style: Style {
+                                width: Percent(80.0),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        DropDownHeaderText(entity),
+                    ));
+                    if let Some(arrow) = self.arrow.clone() {
+                        header.spawn(ImageBundle {
+                            style: Style {
+                                width: Percent(20.0),
+                                ..default()
+                            },
+                            image: UiImage::new(arrow),
+                            ..default()
+                        });
+                    }
+                });
+
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            display: Display::None,
+                            position_type: PositionType::Absolute,
+                            top: Percent(100.0),
+                            width: Percent(100.0),
+                            max_height: Px(self.max_list_height),
+                            overflow: Overflow::clip_y(),
+                            ..default()
+                        },
+                        // 列表需要绘制在其他界面元素之上
+                        z_index: ZIndex::Global(100),
+                        ..default()
+                    },
+                    // 选项按钮会阻挡列表本身的 Interaction，用光标位置判断悬停
+                    RelativeCursorPosition::default(),
+                    // 打开时方向键在选项间移动
+                    FocusScope,
+                    DropDownList(entity),
+                ))
+                .with_children(|list| {
+                    list.spawn((
+                        NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Column,
+                                width: Percent(100.0),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        DropDownListContent(entity),
+                    ))
+                    .with_children(|content| {
+                        for (index, option) in self.options.iter().enumerate() {
+                            content
+                                .spawn((
+                                    ButtonBundle {
+                                        style: Style {
+                                            width: Percent(100.0),
+                                            height: Px(self.size.y),
+                                            flex_shrink: 0.0,
+                                            // horizontally center child text
+                                            justify_content: JustifyContent::Center,
+                                            // vertically center child text
+                                            align_items: AlignItems::Center,
+                                            ..default()
+                                        },
+                                        border_radius: BorderRadius::all(Px(4.0)),
+                                        background_color: ITEM_COLOR.into(),
+                                        ..default()
+                                    },
+                                    DropDownItem {
+                                        dropdown: entity,
+                                        index,
+                                    },
+                                ))
+                                .with_children(|item| {
+                                    item.spawn(TextBundle::from_section(
+                                        option.as_str(),
+                                        text_style.clone(),
+                                    ));
+                                });
+                        }
+                    });
+                });
+        });
+    }
 }
 
 impl Plugin for DropDownPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(self.options.clone())
-            .add_systems(Startup, spawn_entities)
-            .add_systems(
-                Update,
-                (
-                    button_system,
-                    toggle_list_visibility,
-                    clicked_list_item,
-                    list_item_hidden_update,
-                ),
-            );
+        if !app.is_plugin_added::<FocusPlugin>() {
+            app.add_plugins(FocusPlugin);
+        }
+        app.add_event::<DropDownChanged>().add_systems(
+            Update,
+            (
+                button_system,
+                toggle_list_visibility,
+                clicked_list_item,
+                close_lists,
+                scroll_lists,
+                list_item_hidden_update,
+            )
+                .chain(),
+        );
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn button_system(
     mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor),
-        (Changed<Interaction>, With<Button>),
+        (&Interaction, &mut BackgroundColor, Has<DropDownItem>),
+        (
+            Changed<Interaction>,
+            Or<(With<DropDownItem>, With<DropDownHeader>)>,
+        ),
     >,
 ) {
-    for (interaction, mut color) in interaction_query.iter_mut() {
+    for (interaction, mut color, is_item) in interaction_query.iter_mut() {
         *color = match *interaction {
-            Interaction::Pressed => BackgroundColor(Color::srgb(0.0, 1.0, 0.0)),
-            Interaction::Hovered => BackgroundColor(Color::srgb(0.827, 0.827, 0.827)),
-            Interaction::None => BackgroundColor(Color::srgb(0.25, 0.25, 0.25)),
+            Interaction::Pressed => BackgroundColor(PRESSED_COLOR),
+            Interaction::Hovered => BackgroundColor(HOVERED_COLOR),
+            Interaction::None if is_item => BackgroundColor(ITEM_COLOR),
+            Interaction::None => BackgroundColor(HEADER_COLOR),
         };
     }
 }
 
-fn clicked_list_item(
-    mut option: ResMut<DropDownOptions>,
-    mut button_query: Query<(&Interaction, &mut Children, &mut ItemVisibility)>,
-    mut list_query: Query<&mut ListVisibility>,
-    text_query: Query<&mut Text>,
+fn toggle_list_visibility(
+    interaction_query: Query<(&Interaction, &DropDownHeader), Changed<Interaction>>,
+    mut dropdown_query: Query<&mut DropDown>,
 ) {
-    for (interaction, button_child, _) in button_query.iter_mut() {
-        if *interaction == Interaction::Pressed {
-            for entity in &button_child {
-                if let Ok(child_text) = text_query.get(*entity) {
-                    let text = child_text.sections[0].value.clone();
-                    let options = option.options.clone();
-                    option.selected_option = text.clone();
-                    for (n, str) in options.iter().enumerate() {
-                        if let Some(ref_val) = str.downcast_ref::<String>() {
-                            if *ref_val == text {
-                                option.selected_index = n;
-                            }
-                        }
-                    }
-                    for mut list_visibility in list_query.iter_mut() {
-                        list_visibility.0 = false;
-                    }
-                }
-            }
+    for (interaction, header) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Ok(mut dropdown) = dropdown_query.get_mut(header.0) {
+            dropdown.open = !dropdown.open;
         }
     }
 }
 
-fn list_item_hidden_update(
-    option: Res<DropDownOptions>,
-    mut visibility_query: Query<&mut Visibility, With<ItemVisibility>>,
-    list_query: Query<&ListVisibility>,
-    mut text_query: Query<&mut Text, With<ListShowText>>,
+fn clicked_list_item(
+    item_query: Query<(&Interaction, &DropDownItem), Changed<Interaction>>,
+    mut dropdown_query: Query<(Entity, &mut DropDown)>,
+    mut changed_events: EventWriter<DropDownChanged>,
 ) {
-    for mut visibility in visibility_query.iter_mut() {
-        for list in list_query.iter() {
-            //update list visibility
-            if !list.0 {
-                *visibility = Visibility::Hidden;
-                //update list top text
-                for mut list_text in text_query.iter_mut() {
-                    list_text.sections[0].value = option.selected_option.clone();
-                }
-            } else {
-                *visibility = Visibility::Inherited;
-            }
+    for (interaction, item) in item_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Ok((entity, mut dropdown)) = dropdown_query.get_mut(item.dropdown) else {
+            continue;
+        };
+        dropdown.open = false;
+        if dropdown.selected_index == item.index {
+            continue;
         }
+        let Some(value) = dropdown.options.get(item.index).cloned() else {
+            continue;
+        };
+        dropdown.selected_index = item.index;
+        changed_events.send(DropDownChanged {
+            entity,
+            index: item.index,
+            value,
+        });
     }
 }
 
-fn toggle_list_visibility(
-    mut interaction_query: Query<
-        (&Interaction, &mut ListVisibility),
-        (Changed<Interaction>, With<Button>),
-    >,
-    mut visibility_query: Query<&mut ItemVisibility>,
+/// Closes open lists on Escape or B (East) of a gamepad, and on a click
+/// outside of their header and options.
+fn close_lists(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_input: Res<ButtonInput<GamepadButton>>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    header_query: Query<(&Interaction, &DropDownHeader)>,
+    list_query: Query<(&RelativeCursorPosition, &DropDownList)>,
+    mut dropdown_query: Query<(Entity, &mut DropDown)>,
 ) {
-    for (interaction, mut list_visibility) in interaction_query.iter_mut() {
-        if *interaction == Interaction::Pressed {
-            list_visibility.0 = !list_visibility.0; // 切换可见性状态
+    let escape = keyboard_input.just_pressed(KeyCode::Escape)
+        || gamepad_just_pressed(&gamepad_input, GamepadButtonType::East);
+    let clicked = mouse_button_input.just_pressed(MouseButton::Left);
+    if !escape && !clicked {
+        return;
+    }
 
-            for mut item in visibility_query.iter_mut() {
-                item.0 = list_visibility.0; // 更新子节点的可见性
-            }
+    for (entity, mut dropdown) in dropdown_query.iter_mut() {
+        if !dropdown.open {
+            continue;
+        }
+        let inside = header_query
+            .iter()
+            .any(|(interaction, header)| header.0 == entity && *interaction != Interaction::None)
+            || list_query
+                .iter()
+                .any(|(cursor, list)| list.0 == entity && cursor.mouse_over());
+        if escape || !inside {
+            dropdown.open = false;
         }
     }
 }
 
-fn spawn_entities(
-    mut commands: Commands,
-    asset_server: ResMut<AssetServer>,
-    options_res: Res<DropDownOptions>,
+fn scroll_lists(
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    list_query: Query<(&RelativeCursorPosition, &Node, &DropDownList, &Children)>,
+    content_query: Query<&Node, With<DropDownListContent>>,
+    mut dropdown_query: Query<&mut DropDown>,
 ) {
-    let options = &options_res.options;
-
-    commands
-        .spawn(NodeBundle {
-            style: Style {
-                width: Px(150.0),
-                display: Display::Grid,
-                height: Px(30.0),
-                ..default()
-            },
-            background_color: BackgroundColor::from(Color::srgb(0.15, 0.15, 0.15)),
-            ..default()
-        })
-        .with_children(|parent| {
-            let font = asset_server.load("fonts/FiraSans-Bold.ttf");
-            let button_image = asset_server.load("component/dropdownctl.png");
-            parent
-                .spawn(NodeBundle {
-                    style: Style {
-                        width: Px(150.0),
-                        height: Px(30.0),
-                        display: Display::Block,
-                        ..default()
-                    },
-                    ..default()
-                })
-                .with_children(|drop_down| {
-                    drop_down
-                        .spawn(ButtonBundle {
-                            style: {
-                                Style {
-                                    width: Percent(100.0),
-                                    height: Percent(100.0),
-                                    // border: UiRect::all(Px(5.0)),
-                                    // horizontally center child text
-                                    justify_content: JustifyContent::Center,
-                                    // vertically center child text
-                                    align_items: AlignItems::Center,
-                                    ..default()
-                                }
-                            },
-                            // image: UiImage::new(button_image),
-                            interaction: Interaction::None,
-                            background_color: Color::srgb(0.15, 0.15, 0.15).into(),
-                            ..default()
-                        })
-                        .insert(ListVisibility(true))
-                        .with_children(|ctl| {
-                            ctl.spawn(NodeBundle {
-                                style: Style {
-                                    display: Display::Flex,
-                                    width: Percent(100.0),
-                                    height: Percent(100.0),
-                                    ..default()
-                                },
-                                ..default()
-                            })
-                            .with_children(|btn| {
-                                btn.spawn(TextBundle {
-                                    text: Text::from_section(
-                                        String::new(),
-                                        TextStyle {
-                                            font: font.clone(),
-                                            font_size: 24.0,
-                                            color: Color::WHITE,
-                                        },
-                                    ),
-                                    style: Style {
-                                        width: Percent(80.0),
-                                        ..default()
-                                    },
-                                    ..default()
-                                })
-                                .insert(ListShowText);
+    let mut scroll_delta = 0.0;
+    for event in mouse_wheel_events.read() {
+        scroll_delta += match event.unit {
+            MouseScrollUnit::Line => event.y * 30.0,
+            MouseScrollUnit::Pixel => event.y,
+        };
+    }
+    if scroll_delta == 0.0 {
+        return;
+    }
 
-                                btn.spawn(ImageBundle {
-                                    style: Style {
-                                        width: Percent(20.0),
-                                        ..default()
-                                    },
-                                    image: UiImage::new(button_image),
-                                    ..default()
-                                });
-                            });
-                        });
-                });
+    for (cursor, list_node, list, children) in list_query.iter() {
+        if !cursor.mouse_over() {
+            continue;
+        }
+        let Ok(mut dropdown) = dropdown_query.get_mut(list.0) else {
+            continue;
+        };
+        let Some(content_height) = children
+            .iter()
+            .find_map(|child| content_query.get(*child).ok())
+            .map(|node| node.size().y)
+        else {
+            continue;
+        };
+        let max_scroll = (content_height - list_node.size().y).max(0.0);
+        dropdown.scroll = (dropdown.scroll - scroll_delta).clamp(0.0, max_scroll);
+    }
+}
 
-            for option in options {
-                parent
-                    .spawn(ButtonBundle {
-                        style: {
-                            Style {
-                                width: Px(150.0),
-                                height: Px(30.0),
-                                // border: UiRect::all(Px(5.0)),
-                                // horizontally center child text
-                                justify_content: JustifyContent::Center,
-                                // vertically center child text
-                                align_items: AlignItems::Center,
-                                ..default()
-                            }
-                        },
-                        interaction: Interaction::None,
-                        border_radius: BorderRadius::all(Px(4.0)),
-                        background_color: Color::srgb(0.15, 0.15, 0.15).into(),
-                        ..default()
-                    })
-                    .insert(ItemVisibility(true))
-                    .with_children(|drop_down| {
-                        drop_down.spawn(TextBundle {
-                            text: Text::from_section(
-                                option.as_str(),
-                                TextStyle {
-                                    font: font.clone(),
-                                    font_size: 24.0,
-                                    color: Color::WHITE,
-                                },
-                            ),
-                            ..default()
-                        });
-                    });
+fn list_item_hidden_update(
+    dropdown_query: Query<&DropDown, Changed<DropDown>>,
+    mut list_query: Query<(&mut Style, &DropDownList), Without<DropDownListContent>>,
+    mut content_query: Query<(&mut Style, &DropDownListContent), Without<DropDownList>>,
+    mut text_query: Query<(&mut Text, &DropDownHeaderText)>,
+) {
+    for (mut style, list) in list_query.iter_mut() {
+        if let Ok(dropdown) = dropdown_query.get(list.0) {
+            style.display = if dropdown.open {
+                Display::Flex
+            } else {
+                Display::None
+            };
+            style.max_height = Px(dropdown.max_list_height);
+        }
+    }
+    for (mut style, content) in content_query.iter_mut() {
+        if let Ok(dropdown) = dropdown_query.get(content.0) {
+            style.top = Px(-dropdown.scroll);
+        }
+    }
+    for (mut text, header_text) in text_query.iter_mut() {
+        if let Ok(dropdown) = dropdown_query.get(header_text.0) {
+            let selected = dropdown.selected_option().unwrap_or_default();
+            if text.sections[0].value != selected {
+                text.sections[0].value = selected.to_string();
             }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::focus::Focus;
+    use crate::utils::test_harness::TestApp;
+
+    fn spawn(app: &mut TestApp) -> Entity {
+        let mut dropdown = Entity::PLACEHOLDER;
+        app.spawn_ui(|parent| {
+            dropdown = DropDownBuilder::new(["720p", "1080p", "1440p"]).spawn(parent);
         });
+        app.update();
+        dropdown
+    }
+
+    fn header(app: &mut TestApp) -> Entity {
+        app.entities_with::<DropDownHeader>()[0]
+    }
+
+    fn item(app: &mut TestApp, index: usize) -> Entity {
+        app.entities_with::<DropDownItem>()[index]
+    }
+
+    fn list_display(app: &mut TestApp) -> Display {
+        let list = app.entities_with::<DropDownList>()[0];
+        app.get::<Style>(list).display
+    }
+
+    #[test]
+    fn clicking_the_header_toggles_the_list() {
+        let mut app = TestApp::new(DropDownPlugin);
+        let dropdown = spawn(&mut app);
+        assert_eq!(list_display(&mut app), Display::None);
+
+        let header = header(&mut app);
+        app.click(header);
+        assert!(app.get::<DropDown>(dropdown).open);
+        assert_eq!(list_display(&mut app), Display::Flex);
+
+        app.click(header);
+        assert!(!app.get::<DropDown>(dropdown).open);
+        assert_eq!(list_display(&mut app), Display::None);
+    }
+
+    #[test]
+    fn clicking_an_option_selects_it() {
+        let mut app = TestApp::new(DropDownPlugin);
+        let dropdown = spawn(&mut app);
+        let header = header(&mut app);
+        app.click(header);
+
+        let item = item(&mut app, 2);
+        app.click(item);
+        let state = app.get::<DropDown>(dropdown);
+        assert!(!state.open);
+        assert_eq!(state.selected_option(), Some("1440p"));
+        assert_eq!(
+            app.drain_events::<DropDownChanged>(),
+            [DropDownChanged {
+                entity: dropdown,
+                index: 2,
+                value: "1440p".to_string()
+            }]
+        );
+        let header_text = app.entities_with::<DropDownHeaderText>()[0];
+        assert_eq!(app.get::<Text>(header_text).sections[0].value, "1440p");
+    }
+
+    #[test]
+    fn clicking_the_selected_option_only_closes_the_list() {
+        let mut app = TestApp::new(DropDownPlugin);
+        let dropdown = spawn(&mut app);
+        let header = header(&mut app);
+        app.click(header);
+
+        let item = item(&mut app, 0);
+        app.click(item);
+        assert!(!app.get::<DropDown>(dropdown).open);
+        assert!(app.drain_events::<DropDownChanged>().is_empty());
+    }
+
+    #[test]
+    fn escape_or_b_closes_the_list() {
+        let mut app = TestApp::new(DropDownPlugin);
+        let dropdown = spawn(&mut app);
+        let header = header(&mut app);
+        app.click(header);
+
+        app.tap_key(KeyCode::Escape);
+        assert!(!app.get::<DropDown>(dropdown).open);
+
+        app.click(header);
+        app.tap_gamepad_button(GamepadButtonType::East);
+        assert!(!app.get::<DropDown>(dropdown).open);
+    }
+
+    #[test]
+    fn the_keyboard_opens_the_list_and_picks_an_option() {
+        let mut app = TestApp::new(DropDownPlugin);
+        let dropdown = spawn(&mut app);
+        let header = header(&mut app);
+        app.tap_key(KeyCode::Tab);
+        app.update();
+        assert_eq!(app.world().resource::<Focus>().get(), Some(header));
+
+        app.tap_key(KeyCode::Enter);
+        assert!(app.get::<DropDown>(dropdown).open);
+        // 列表打开后焦点留在列表里
+        app.update();
+        assert_eq!(app.world().resource::<Focus>().get(), Some(item(&mut app, 0)));
+        app.tap_key(KeyCode::Tab);
+        app.update();
+        app.tap_key(KeyCode::Enter);
+        let state = app.get::<DropDown>(dropdown);
+        assert!(!state.open);
+        assert_eq!(state.selected_option(), Some("1080p"));
+    }
+
+    #[test]
+    fn clicking_outside_closes_the_list() {
+        let mut app = TestApp::new(DropDownPlugin);
+        let dropdown = spawn(&mut app);
+        let header = header(&mut app);
+        app.click(header);
+        app.set_interaction(header, Interaction::None);
+
+        // 光标在列表内时点击不会关闭
+        let list = app.entities_with::<DropDownList>()[0];
+        app.hover(list, true);
+        app.tap_mouse_button(MouseButton::Left);
+        assert!(app.get::<DropDown>(dropdown).open);
+
+        app.hover(list, false);
+        app.tap_mouse_button(MouseButton::Left);
+        assert!(!app.get::<DropDown>(dropdown).open);
+    }
 }
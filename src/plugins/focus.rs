@@ -0,0 +1,561 @@
+use bevy::input::gamepad::{GamepadButton, GamepadButtonType};
+use bevy::input::InputSystem;
+use bevy::prelude::Val::Px;
+use bevy::prelude::*;
+use bevy::ui::UiSystem;
+use bevy::utils::HashMap;
+
+use crate::plugins::slider_bar::Slider;
+
+/// A Bevy Plugin moving the keyboard and gamepad focus between the
+/// [`Focusable`] nodes of the UI.
+///
+/// Tab and Shift+Tab, or the shoulder buttons, go through the nodes in tab
+/// order; the arrow keys and the D-pad move to the nearest node in their
+/// direction. Enter, Space or the A button press the focused node the way a
+/// click does. The focused node is outlined; a mouse click takes the focus
+/// away, and so does the B button when no scope is shown.
+///
+/// The focus stays within one UI tree: the nodes below the [`FocusScope`]
+/// shown last, e.g. the menu opened last or an open dropdown list, or the
+/// nodes below no scope when none is shown. When the focused node goes away,
+/// the focus moves to the node focused last in the tree shown now; inside a
+/// scope it falls back to the first node.
+pub struct FocusPlugin;
+
+/// A node the focus can move to. Buttons get one when they are spawned.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Focusable {
+    /// Tab goes through the nodes by increasing `tab_index`, then in tree order.
+    pub tab_index: i32,
+}
+
+/// Keeps the focus within the nodes below it while it is shown.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct FocusScope;
+
+/// The focused node.
+#[derive(Resource, Default, Debug)]
+pub struct Focus {
+    entity: Option<Entity>,
+    /// The nodes the focus can move to, in tab order.
+    candidates: Vec<Entity>,
+    /// The scope of the candidates, `None` for the nodes below no scope.
+    scope: Option<Entity>,
+    /// The shown scopes with the frame they were spawned on.
+    scopes: Vec<(Entity, u32)>,
+    /// The node focused last in each scope, focused again when it comes back.
+    remembered: HashMap<Option<Entity>, Entity>,
+    /// The node pressed by the keyboard or a gamepad, released on the next frame.
+    pressed: Option<Entity>,
+    generation: u32,
+}
+
+impl Focus {
+    pub fn get(&self) -> Option<Entity> {
+        self.entity
+    }
+
+    /// Focuses `entity`, or nothing.
+    pub fn set(&mut self, entity: Option<Entity>) {
+        self.entity = entity;
+        if let Some(entity) = entity {
+            self.remembered.insert(self.scope, entity);
+        }
+    }
+}
+
+/// The color of the focus ring.
+const RING_COLOR: Color = Color::srgb(1.0, 0.8, 0.2);
+
+/// Whether a button of `button_type` was pressed on any gamepad this frame.
+pub(crate) fn gamepad_just_pressed(
+    gamepad_input: &ButtonInput<GamepadButton>,
+    button_type: GamepadButtonType,
+) -> bool {
+    gamepad_input
+        .get_just_pressed()
+        .any(|button| button.button_type == button_type)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum FocusMove {
+    Next,
+    Previous,
+    /// Towards a direction of the screen, `y` pointing down.
+    Towards(Vec2),
+}
+
+impl Plugin for FocusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Focus>().add_systems(
+            PreUpdate,
+            (
+                make_buttons_focusable,
+                collect_focusables,
+                move_focus,
+                press_focused,
+                draw_focus_ring,
+            )
+                .chain()
+                .after(InputSystem)
+                .after(UiSystem::Focus),
+        );
+    }
+}
+
+fn make_buttons_focusable(
+    mut commands: Commands,
+    button_query: Query<Entity, (Added<Button>, Without<Focusable>)>,
+) {
+    for entity in button_query.iter() {
+        commands.entity(entity).insert(Focusable::default());
+    }
+}
+
+/// A focusable node found below the roots, with its scope.
+struct Found {
+    entity: Entity,
+    scope: Option<Entity>,
+    tab_index: i32,
+}
+
+/// What [`walk`] reads of every node.
+type NodeQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Option<&'static Style>,
+        Option<&'static Visibility>,
+        Option<&'static Focusable>,
+        Has<FocusScope>,
+    ),
+>;
+
+/// Walks the shown nodes below `entity` in tree order.
+fn walk(
+    entity: Entity,
+    scope: Option<Entity>,
+    node_query: &NodeQuery,
+    children_query: &Query<&Children>,
+    found: &mut Vec<Found>,
+    shown_scopes: &mut Vec<(Entity, usize)>,
+    depth: usize,
+) {
+    let Ok((style, visibility, focusable, is_scope)) = node_query.get(entity) else {
+        return;
+    };
+    if style.is_some_and(|style| style.display == Display::None)
+        || visibility == Some(&Visibility::Hidden)
+    {
+        return;
+    }
+    let scope = if is_scope {
+        shown_scopes.push((entity, depth));
+        Some(entity)
+    } else {
+        scope
+    };
+    if let Some(focusable) = focusable {
+        found.push(Found {
+            entity,
+            scope,
+            tab_index: focusable.tab_index,
+        });
+    }
+    for &child in children_query.get(entity).into_iter().flatten() {
+        walk(
+            child,
+            scope,
+            node_query,
+            children_query,
+            found,
+            shown_scopes,
+            depth + 1,
+        );
+    }
+}
+
+/// Finds the nodes the focus can move to: the ones of the scope shown last,
+/// the innermost one among the scopes shown on the same frame.
+fn collect_focusables(
+    mut focus: ResMut<Focus>,
+    added_scopes: Query<Entity, Added<FocusScope>>,
+    root_query: Query<Entity, (With<Node>, Without<Parent>)>,
+    node_query: NodeQuery,
+    children_query: Query<&Children>,
+) {
+    let focus = focus.as_mut();
+    if !added_scopes.is_empty() {
+        focus.generation += 1;
+        let generation = focus.generation;
+        focus
+            .scopes
+            .extend(added_scopes.iter().map(|scope| (scope, generation)));
+    }
+    focus
+        .scopes
+        .retain(|(scope, _)| node_query.contains(*scope));
+    focus.remembered.retain(|scope, entity| {
+        scope.is_none_or(|scope| node_query.contains(scope)) && node_query.contains(*entity)
+    });
+
+    let mut found = Vec::new();
+    let mut shown_scopes = Vec::new();
+    for root in root_query.iter() {
+        walk(
+            root,
+            None,
+            &node_query,
+            &children_query,
+            &mut found,
+            &mut shown_scopes,
+            0,
+        );
+    }
+    let scope = shown_scopes
+        .iter()
+        .filter(|(scope, _)| found.iter().any(|node| node.scope == Some(*scope)))
+        .filter_map(|&(scope, depth)| {
+            let generation = focus.scopes.iter().find(|(entity, _)| *entity == scope)?.1;
+            Some((scope, generation, depth))
+        })
+        .max_by_key(|&(_, generation, depth)| (generation, depth))
+        .map(|(scope, _, _)| scope);
+    // 树的遍历顺序即 Tab 顺序，稳定排序保留它
+    found.retain(|node| node.scope == scope);
+    found.sort_by_key(|node| node.tab_index);
+    focus.scope = scope;
+    focus.candidates = found.into_iter().map(|node| node.entity).collect();
+}
+
+fn read_move(
+    keyboard_input: &ButtonInput<KeyCode>,
+    gamepad_input: &ButtonInput<GamepadButton>,
+) -> Option<FocusMove> {
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let pressed = |key: KeyCode, button: GamepadButtonType| {
+        keyboard_input.just_pressed(key) || gamepad_just_pressed(gamepad_input, button)
+    };
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        return Some(if shift {
+            FocusMove::Previous
+        } else {
+            FocusMove::Next
+        });
+    }
+    if gamepad_just_pressed(gamepad_input, GamepadButtonType::RightTrigger) {
+        return Some(FocusMove::Next);
+    }
+    if gamepad_just_pressed(gamepad_input, GamepadButtonType::LeftTrigger) {
+        return Some(FocusMove::Previous);
+    }
+    [
+        (KeyCode::ArrowUp, GamepadButtonType::DPadUp, Vec2::NEG_Y),
+        (KeyCode::ArrowDown, GamepadButtonType::DPadDown, Vec2::Y),
+        (KeyCode::ArrowLeft, GamepadButtonType::DPadLeft, Vec2::NEG_X),
+        (KeyCode::ArrowRight, GamepadButtonType::DPadRight, Vec2::X),
+    ]
+    .into_iter()
+    .find(|&(key, button, _)| pressed(key, button))
+    .map(|(_, _, direction)| FocusMove::Towards(direction))
+}
+
+/// The node nearest to `from` in `direction`, favouring the ones in line with it.
+fn nearest(
+    from: Vec2,
+    direction: Vec2,
+    nodes: impl Iterator<Item = (Entity, Vec2)>,
+) -> Option<Entity> {
+    nodes
+        .filter_map(|(entity, position)| {
+            let offset = position - from;
+            let along = offset.dot(direction);
+            (along > 0.5).then(|| (entity, along + 2.0 * offset.perp_dot(direction).abs()))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(entity, _)| entity)
+}
+
+fn move_focus(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_input: Res<ButtonInput<GamepadButton>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut focus: ResMut<Focus>,
+    transform_query: Query<&GlobalTransform>,
+    slider_query: Query<(), With<Slider>>,
+) {
+    // 没有菜单时 B 取消焦点，按键重新用于推进剧情
+    if mouse_input.just_pressed(MouseButton::Left)
+        || (focus.scope.is_none() && gamepad_just_pressed(&gamepad_input, GamepadButtonType::East))
+    {
+        focus.entity = None;
+        return;
+    }
+    let current = focus
+        .entity
+        .filter(|entity| focus.candidates.contains(entity));
+    if focus.entity.is_some() && current.is_none() {
+        // 焦点所在的界面关闭或隐藏了，移到当前界面上次聚焦的节点
+        let remembered = focus
+            .remembered
+            .get(&focus.scope)
+            .copied()
+            .filter(|entity| focus.candidates.contains(entity));
+        let first = focus.scope.and(focus.candidates.first().copied());
+        let entity = remembered.or(first);
+        focus.set(entity);
+        return;
+    }
+
+    let Some(focus_move) = read_move(&keyboard_input, &gamepad_input) else {
+        return;
+    };
+    let Some(current) = current else {
+        let first = focus.candidates.first().copied();
+        focus.set(first);
+        return;
+    };
+    let index = focus
+        .candidates
+        .iter()
+        .position(|entity| *entity == current)
+        .unwrap_or_default();
+    let count = focus.candidates.len();
+    let next = match focus_move {
+        FocusMove::Next => Some(focus.candidates[(index + 1) % count]),
+        FocusMove::Previous => Some(focus.candidates[(index + count - 1) % count]),
+        // 左右键用来调节滑块
+        FocusMove::Towards(direction) if direction.y == 0.0 && slider_query.contains(current) => {
+            None
+        }
+        FocusMove::Towards(direction) => {
+            let position = |entity: Entity| {
+                transform_query
+                    .get(entity)
+                    .map_or(Vec2::ZERO, |transform| transform.translation().truncate())
+            };
+            let from = position(current);
+            let nodes = focus
+                .candidates
+                .iter()
+                .filter(|entity| **entity != current)
+                .map(|&entity| (entity, position(entity)));
+            nearest(from, direction, nodes)
+        }
+    };
+    if let Some(next) = next {
+        focus.set(Some(next));
+    }
+}
+
+/// Presses the focused node on Enter, Space or the A button, and releases
+/// it on the next frame.
+fn press_focused(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_input: Res<ButtonInput<GamepadButton>>,
+    mut focus: ResMut<Focus>,
+    mut interaction_query: Query<&mut Interaction>,
+) {
+    if let Some(pressed) = focus.pressed.take() {
+        if let Ok(mut interaction) = interaction_query.get_mut(pressed) {
+            if *interaction == Interaction::Pressed {
+                *interaction = Interaction::None;
+            }
+        }
+    }
+    let activate = keyboard_input.any_just_pressed([KeyCode::Enter, KeyCode::Space])
+        || gamepad_just_pressed(&gamepad_input, GamepadButtonType::South);
+    if !activate {
+        return;
+    }
+    let Some(entity) = focus
+        .entity
+        .filter(|entity| focus.candidates.contains(entity))
+    else {
+        return;
+    };
+    if let Ok(mut interaction) = interaction_query.get_mut(entity) {
+        *interaction = Interaction::Pressed;
+        focus.pressed = Some(entity);
+    }
+}
+
+fn draw_focus_ring(mut commands: Commands, focus: Res<Focus>, mut ringed: Local<Option<Entity>>) {
+    if *ringed == focus.entity {
+        return;
+    }
+    if let Some(entity) = ringed.take() {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.remove::<Outline>();
+        }
+    }
+    if let Some(entity) = focus.entity {
+        commands.entity(entity).insert(Outline {
+            width: Px(3.0),
+            offset: Px(2.0),
+            color: RING_COLOR,
+        });
+        *ringed = Some(entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_harness::TestApp;
+
+    fn button(parent: &mut ChildBuilder, x: f32, y: f32) -> Entity {
+        parent
+            .spawn(ButtonBundle {
+                transform: Transform::from_xyz(x, y, 0.0),
+                ..default()
+            })
+            .id()
+    }
+
+    fn focused(app: &TestApp) -> Option<Entity> {
+        app.world().resource::<Focus>().get()
+    }
+
+    #[test]
+    fn tab_goes_through_the_buttons_in_tree_order() {
+        let mut app = TestApp::new(FocusPlugin);
+        let mut buttons = Vec::new();
+        app.spawn_ui(|root| {
+            for x in [0.0, 100.0, 200.0] {
+                buttons.push(button(root, x, 0.0));
+            }
+        });
+        app.update();
+        assert_eq!(focused(&app), None);
+
+        app.tap_key(KeyCode::Tab);
+        app.update();
+        assert_eq!(focused(&app), Some(buttons[0]));
+        assert!(app.world().get::<Outline>(buttons[0]).is_some());
+        app.tap_key(KeyCode::Tab);
+        app.update();
+        assert_eq!(focused(&app), Some(buttons[1]));
+        assert!(app.world().get::<Outline>(buttons[0]).is_none());
+
+        app.press_key(KeyCode::ShiftLeft);
+        app.tap_key(KeyCode::Tab);
+        app.update();
+        app.tap_key(KeyCode::Tab);
+        app.release_key(KeyCode::ShiftLeft);
+        app.update();
+        assert_eq!(focused(&app), Some(buttons[2]));
+
+        app.get_mut::<Focusable>(buttons[2]).tab_index = -1;
+        app.tap_key(KeyCode::Tab);
+        app.update();
+        assert_eq!(focused(&app), Some(buttons[0]));
+    }
+
+    #[test]
+    fn arrows_and_the_d_pad_move_to_the_nearest_button() {
+        let mut app = TestApp::new(FocusPlugin);
+        let mut buttons = Vec::new();
+        app.spawn_ui(|root| {
+            buttons.push(button(root, 0.0, 0.0));
+            buttons.push(button(root, 200.0, 10.0));
+            buttons.push(button(root, 20.0, 100.0));
+        });
+        app.update();
+        app.tap_key(KeyCode::ArrowDown);
+        app.update();
+        assert_eq!(focused(&app), Some(buttons[0]));
+
+        app.tap_key(KeyCode::ArrowDown);
+        app.update();
+        assert_eq!(focused(&app), Some(buttons[2]));
+        app.tap_gamepad_button(GamepadButtonType::DPadUp);
+        app.update();
+        assert_eq!(focused(&app), Some(buttons[0]));
+        app.tap_gamepad_button(GamepadButtonType::DPadRight);
+        app.update();
+        assert_eq!(focused(&app), Some(buttons[1]));
+        // 右边没有按钮时焦点不动
+        app.tap_key(KeyCode::ArrowRight);
+        app.update();
+        assert_eq!(focused(&app), Some(buttons[1]));
+    }
+
+    #[test]
+    fn enter_and_the_a_button_press_the_focused_button() {
+        let mut app = TestApp::new(FocusPlugin);
+        let mut target = Entity::PLACEHOLDER;
+        app.spawn_ui(|root| target = button(root, 0.0, 0.0));
+        app.update();
+        app.world_mut().resource_mut::<Focus>().set(Some(target));
+
+        app.tap_key(KeyCode::Enter);
+        assert_eq!(*app.get::<Interaction>(target), Interaction::Pressed);
+        app.update();
+        assert_eq!(*app.get::<Interaction>(target), Interaction::None);
+
+        app.tap_gamepad_button(GamepadButtonType::South);
+        assert_eq!(*app.get::<Interaction>(target), Interaction::Pressed);
+    }
+
+    #[test]
+    fn focus_stays_in_the_scope_shown_last() {
+        let mut app = TestApp::new(FocusPlugin);
+        let mut story = Entity::PLACEHOLDER;
+        app.spawn_ui(|root| story = button(root, 0.0, 0.0));
+        app.update();
+        app.tap_key(KeyCode::Tab);
+        app.update();
+        assert_eq!(focused(&app), Some(story));
+
+        let mut menu_buttons = Vec::new();
+        let menu = app.spawn_ui(|root| {
+            root.spawn((NodeBundle::default(), FocusScope))
+                .with_children(|menu| {
+                    menu_buttons.push(button(menu, 0.0, 0.0));
+                    menu_buttons.push(button(menu, 0.0, 50.0));
+                });
+        });
+        app.update();
+        // 焦点移进新打开的菜单
+        assert_eq!(focused(&app), Some(menu_buttons[0]));
+        app.tap_key(KeyCode::Tab);
+        app.update();
+        app.tap_key(KeyCode::Tab);
+        app.update();
+        assert_eq!(focused(&app), Some(menu_buttons[0]));
+
+        app.world_mut().entity_mut(menu).despawn_recursive();
+        app.update();
+        assert_eq!(focused(&app), Some(story));
+
+        app.tap_mouse_button(MouseButton::Left);
+        assert_eq!(focused(&app), None);
+    }
+
+    #[test]
+    fn b_and_a_despawned_button_take_the_focus_away_outside_of_a_scope() {
+        let mut app = TestApp::new(FocusPlugin);
+        let mut buttons = Vec::new();
+        app.spawn_ui(|root| {
+            buttons.push(button(root, 0.0, 0.0));
+            buttons.push(button(root, 0.0, 50.0));
+        });
+        app.update();
+        app.tap_key(KeyCode::Tab);
+        app.update();
+        assert_eq!(focused(&app), Some(buttons[0]));
+
+        // 焦点所在的按钮消失后不会跳到别的按钮上
+        app.world_mut().entity_mut(buttons[0]).despawn_recursive();
+        app.update();
+        assert_eq!(focused(&app), None);
+
+        app.tap_key(KeyCode::Tab);
+        app.update();
+        assert_eq!(focused(&app), Some(buttons[1]));
+        app.tap_gamepad_button(GamepadButtonType::East);
+        assert_eq!(focused(&app), None);
+    }
+}
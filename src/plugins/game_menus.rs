@@ -0,0 +1,919 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::Val::Px;
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioControl, AudioInstance, AudioTween};
+use serde::{Deserialize, Deserializer};
+use std::fs;
+use std::path::Path;
+
+use crate::core::catalogue::AssetCatalogue;
+use crate::core::event_bus::Condition;
+use crate::core::locale::LocalizedText;
+use crate::core::save_store::write_saves;
+use crate::core::story::{StoryHistory, StoryState};
+use crate::global_def::global_define::GameConfig;
+use crate::plugins::focus::{gamepad_just_pressed, FocusPlugin, FocusScope};
+use crate::plugins::game_state::{GameState, GameStatePlugin, MenuState, MENU_Z_INDEX};
+use crate::plugins::layout::UiRoot;
+use crate::plugins::scene_play::{PlayerAction, PlayerInputSet, StoryStart, FIRST_SCRIPT};
+
+/// A Bevy Plugin with the screens of [`GameState`] and the menus of
+/// [`MenuState`], except for the settings of the `ConfigPlugin`.
+///
+/// The title screen waits for a click, Enter, or A or Start on a gamepad; the
+/// main menu offers the items of the [`TitleScreen`]; the pause menu leads to
+/// the save slots, the backlog of the lines read, the settings and back to
+/// the main menu. Saves are written to the `SaveStore`, if there is one.
+pub struct GameMenusPlugin;
+
+/// The file describing the [`TitleScreen`], in the asset folder.
+pub const TITLE_SCREEN_FILE: &str = "title.toml";
+
+/// The title screen and the main menu, read from [`TITLE_SCREEN_FILE`]:
+///
+/// ```toml
+/// background = "bg.title"        # catalogue id or path
+/// logo = "images/logo.png"       # shown instead of the game title
+/// music = "bgm.main"             # played until the story starts
+///
+/// [[items]]
+/// action = "continue"            # shown once there is a save
+///
+/// [[items]]
+/// action = "new_game"            # starts the first script
+///
+/// [[items]]
+/// action = "start"
+/// script = "scripts/epilogue.avg"
+/// label = "Epilogue"
+/// if = "persistent.route_a == true"
+///
+/// [[items]]
+/// action = "load"                # also "settings" and "quit"
+/// ```
+///
+/// Without the file, or without `items`, the main menu offers continue, new
+/// game, load, settings and quit.
+#[derive(Resource, Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct TitleScreen {
+    pub background: Option<String>,
+    pub logo: Option<String>,
+    pub music: Option<String>,
+    pub items: Vec<TitleItem>,
+}
+
+/// An item of the main menu.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct TitleItem {
+    #[serde(flatten)]
+    pub action: TitleAction,
+    /// The text shown instead of the default one of the action.
+    #[serde(default)]
+    pub label: Option<String>,
+    /// The id of the translations of `label`.
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Hides the item until the condition holds for the persistent
+    /// variables, see [`StoryHistory::progress`].
+    #[serde(default, rename = "if", deserialize_with = "deserialize_condition")]
+    pub condition: Option<Condition>,
+}
+
+/// What an item of the main menu does.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum TitleAction {
+    /// Continues from the slot saved last.
+    Continue,
+    /// Starts [`FIRST_SCRIPT`].
+    NewGame,
+    /// Starts a script, relative to the asset folder.
+    Start {
+        script: String,
+    },
+    Load,
+    Settings,
+    Quit,
+}
+
+fn deserialize_condition<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Condition>, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    text.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+impl Default for TitleScreen {
+    fn default() -> Self {
+        let items = [
+            TitleAction::Continue,
+            TitleAction::NewGame,
+            TitleAction::Load,
+            TitleAction::Settings,
+            TitleAction::Quit,
+        ];
+        Self {
+            background: None,
+            logo: None,
+            music: None,
+            items: items
+                .into_iter()
+                .map(|action| TitleItem {
+                    action,
+                    label: None,
+                    id: None,
+                    condition: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TitleScreen {
+    /// Reads the title screen from `path`; a missing file yields the default.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(toml::from_str(&text)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// The items shown with the saves and persistent variables of `history`.
+    pub fn shown_items<'a>(
+        &'a self,
+        history: &'a StoryHistory,
+    ) -> impl Iterator<Item = &'a TitleItem> {
+        self.items.iter().filter(|item| item.is_shown(history))
+    }
+}
+
+impl TitleItem {
+    pub fn is_shown(&self, history: &StoryHistory) -> bool {
+        if self.action == TitleAction::Continue && history.latest_slot().is_none() {
+            return false;
+        }
+        self.condition
+            .as_ref()
+            .is_none_or(|condition| condition.holds(history.progress()))
+    }
+
+    /// The text of the item, the default one of its action unless given.
+    pub fn label(&self) -> LocalizedText {
+        let mut label = match &self.action {
+            TitleAction::Continue => LocalizedText::new("ui.continue", "Continue"),
+            TitleAction::NewGame => LocalizedText::new("ui.new_game", "New Game"),
+            TitleAction::Start { script } => {
+                let name = Path::new(script)
+                    .file_stem()
+                    .map_or(script.clone(), |stem| stem.to_string_lossy().into_owned());
+                LocalizedText {
+                    id: format!("ui.{name}"),
+                    source: name,
+                }
+            }
+            TitleAction::Load => LocalizedText::new("ui.load", "Load"),
+            TitleAction::Settings => LocalizedText::new("ui.settings", "Settings"),
+            TitleAction::Quit => LocalizedText::new("ui.quit", "Quit"),
+        };
+        if let Some(text) = &self.label {
+            label.source.clone_from(text);
+        }
+        if let Some(id) = &self.id {
+            label.id.clone_from(id);
+        }
+        label
+    }
+
+    pub fn button(&self) -> MenuButton {
+        match &self.action {
+            TitleAction::Continue => MenuButton::Continue,
+            TitleAction::NewGame => MenuButton::Start(FIRST_SCRIPT.to_string()),
+            TitleAction::Start { script } => MenuButton::Start(script.clone()),
+            TitleAction::Load => MenuButton::Open(MenuState::SaveLoad),
+            TitleAction::Settings => MenuButton::Open(MenuState::Settings),
+            TitleAction::Quit => MenuButton::Quit,
+        }
+    }
+}
+
+/// The music of the [`TitleScreen`], playing until the story starts.
+#[derive(Resource)]
+struct TitleMusic(Handle<AudioInstance>);
+
+/// The save slots offered by the save/load menu; slot 0 is the quick slot.
+pub const SAVE_SLOTS: std::ops::RangeInclusive<u32> = 1..=6;
+
+/// How many of the last lines read the backlog shows.
+const BACKLOG_LINES: usize = 12;
+
+/// What a menu button does when pressed.
+#[derive(Component, Clone, Debug, PartialEq, Eq)]
+pub enum MenuButton {
+    /// Continues the story from the slot saved last.
+    Continue,
+    /// Starts the story from the beginning of a script.
+    Start(String),
+    Open(MenuState),
+    /// Goes back the way Escape does.
+    Back,
+    /// Closes the menu.
+    Resume,
+    MainMenu,
+    Quit,
+    Save(u32),
+    Load(u32),
+}
+
+/// The text describing what a save slot holds.
+#[derive(Component)]
+struct SlotLabel(u32);
+
+const BUTTON_COLOR: Color = Color::srgb(0.15, 0.15, 0.15);
+const HOVERED_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+const PRESSED_COLOR: Color = Color::srgb(0.25, 0.5, 0.25);
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+
+impl Plugin for GameMenusPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<GameStatePlugin>() {
+            app.add_plugins(GameStatePlugin);
+        }
+        if !app.is_plugin_added::<FocusPlugin>() {
+            app.add_plugins(FocusPlugin);
+        }
+        // 标题界面在启动前进入，所以在这里读取
+        if !app.world().contains_resource::<TitleScreen>() {
+            let root = match app.world().get_resource::<AssetCatalogue>() {
+                Some(catalogue) => catalogue.root().to_path_buf(),
+                None => FileAssetReader::new("assets").root_path().clone(),
+            };
+            let path = root.join(TITLE_SCREEN_FILE);
+            let title = TitleScreen::load(&path).unwrap_or_else(|err| {
+                error!("can not load {}: {}", path.display(), err);
+                TitleScreen::default()
+            });
+            app.insert_resource(title);
+        }
+        app.add_event::<PlayerAction>()
+            .init_resource::<StoryStart>()
+            .init_resource::<StoryHistory>()
+            .add_systems(OnEnter(GameState::Title), (spawn_title, play_title_music))
+            .add_systems(
+                OnEnter(GameState::MainMenu),
+                (spawn_main_menu, play_title_music),
+            )
+            .add_systems(OnEnter(GameState::InGame), stop_title_music)
+            .add_systems(OnEnter(MenuState::Pause), spawn_pause_menu)
+            .add_systems(OnEnter(MenuState::SaveLoad), spawn_save_load)
+            .add_systems(OnEnter(MenuState::Backlog), spawn_backlog)
+            .add_systems(Last, write_saves)
+            .add_systems(
+                Update,
+                (
+                    leave_title.run_if(in_state(GameState::Title)),
+                    (
+                        highlight_buttons,
+                        press_menu_buttons,
+                        press_story_buttons.in_set(PlayerInputSet::Read),
+                    ),
+                    update_slot_labels
+                        .after(PlayerInputSet::Apply)
+                        .run_if(in_state(MenuState::SaveLoad)),
+                ),
+            );
+    }
+}
+
+fn text_style(asset_server: &AssetServer, font_size: f32) -> TextStyle {
+    TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size,
+        color: TEXT_COLOR,
+    }
+}
+
+/// A full screen node holding the items of a menu in a column.
+fn screen(background: Color) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: Px(16.0),
+            ..default()
+        },
+        background_color: background.into(),
+        z_index: ZIndex::Global(MENU_Z_INDEX),
+        ..default()
+    }
+}
+
+fn spawn_button(
+    parent: &mut ChildBuilder,
+    style: &TextStyle,
+    button: MenuButton,
+    label: LocalizedText,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Px(300.0),
+                    height: Px(50.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                border_radius: BorderRadius::MAX,
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|button| {
+            button.spawn((
+                TextBundle::from_section(label.source.clone(), style.clone()),
+                label,
+            ));
+        });
+}
+
+/// The background and the logo of the title screen, or the game title
+/// without a logo.
+fn spawn_title_images(
+    screen: &mut ChildBuilder,
+    title: &TitleScreen,
+    config: &GameConfig,
+    asset_server: &AssetServer,
+    catalogue: Option<&AssetCatalogue>,
+) {
+    let resolve = |path: &str| {
+        catalogue
+            .map_or(path, |catalogue| catalogue.resolve(path))
+            .to_string()
+    };
+    if let Some(background) = &title.background {
+        screen.spawn(ImageBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            image: UiImage::new(asset_server.load(resolve(background))),
+            ..default()
+        });
+    }
+    match &title.logo {
+        Some(logo) => {
+            screen.spawn(ImageBundle {
+                style: Style {
+                    height: Px(200.0),
+                    ..default()
+                },
+                image: UiImage::new(asset_server.load(resolve(logo))),
+                ..default()
+            });
+        }
+        None => {
+            screen.spawn(TextBundle::from_section(
+                config.title.clone(),
+                text_style(asset_server, 60.0),
+            ));
+        }
+    }
+}
+
+fn spawn_title(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    ui_root: Res<UiRoot>,
+    title: Res<TitleScreen>,
+    catalogue: Option<Res<AssetCatalogue>>,
+) {
+    commands.entity(ui_root.0).with_children(|root| {
+        root.spawn((
+            screen(Color::BLACK),
+            Name::new("title_screen"),
+            StateScoped(GameState::Title),
+        ))
+        .with_children(|screen| {
+            spawn_title_images(screen, &title, &config, &asset_server, catalogue.as_deref());
+            screen.spawn((
+                TextBundle::from_section("Click to start", text_style(&asset_server, 24.0)),
+                LocalizedText::new("ui.click_to_start", "Click to start"),
+            ));
+        });
+    });
+}
+
+fn leave_title(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_input: Res<ButtonInput<GamepadButton>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if mouse_input.just_pressed(MouseButton::Left)
+        || keyboard_input.any_just_pressed([KeyCode::Enter, KeyCode::Space])
+        || gamepad_just_pressed(&gamepad_input, GamepadButtonType::South)
+        || gamepad_just_pressed(&gamepad_input, GamepadButtonType::Start)
+    {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn spawn_main_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    ui_root: Res<UiRoot>,
+    title: Res<TitleScreen>,
+    history: Res<StoryHistory>,
+    catalogue: Option<Res<AssetCatalogue>>,
+) {
+    let style = text_style(&asset_server, 24.0);
+    commands.entity(ui_root.0).with_children(|root| {
+        root.spawn((
+            screen(Color::srgb(0.1, 0.1, 0.2)),
+            Name::new("main_menu"),
+            StateScoped(GameState::MainMenu),
+            FocusScope,
+        ))
+        .with_children(|menu| {
+            spawn_title_images(menu, &title, &config, &asset_server, catalogue.as_deref());
+            for item in title.shown_items(&history) {
+                spawn_button(menu, &style, item.button(), item.label());
+            }
+        });
+    });
+}
+
+fn play_title_music(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    title: Res<TitleScreen>,
+    catalogue: Option<Res<AssetCatalogue>>,
+    audio: Option<Res<Audio>>,
+    playing: Option<Res<TitleMusic>>,
+) {
+    // 从标题进入主菜单时音乐继续播放
+    let (Some(audio), Some(music), None) = (audio, title.music.as_deref(), playing) else {
+        return;
+    };
+    let path = catalogue
+        .as_deref()
+        .map_or(music, |catalogue| catalogue.resolve(music))
+        .to_string();
+    let handle = audio.play(asset_server.load(path)).looped().handle();
+    commands.insert_resource(TitleMusic(handle));
+}
+
+fn stop_title_music(
+    mut commands: Commands,
+    music: Option<Res<TitleMusic>>,
+    audio_instances: Option<ResMut<Assets<AudioInstance>>>,
+) {
+    let (Some(music), Some(mut audio_instances)) = (music, audio_instances) else {
+        return;
+    };
+    if let Some(instance) = audio_instances.get_mut(&music.0) {
+        instance.stop(AudioTween::default());
+    }
+    commands.remove_resource::<TitleMusic>();
+}
+
+fn spawn_pause_menu(mut commands: Commands, asset_server: Res<AssetServer>, ui_root: Res<UiRoot>) {
+    let style = text_style(&asset_server, 24.0);
+    commands.entity(ui_root.0).with_children(|root| {
+        root.spawn((
+            screen(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+            Name::new("pause_menu"),
+            StateScoped(MenuState::Pause),
+            FocusScope,
+        ))
+        .with_children(|menu| {
+            spawn_button(
+                menu,
+                &style,
+                MenuButton::Resume,
+                LocalizedText::new("ui.resume", "Resume"),
+            );
+            spawn_button(
+                menu,
+                &style,
+                MenuButton::Open(MenuState::SaveLoad),
+                LocalizedText::new("ui.save_load", "Save / Load"),
+            );
+            spawn_button(
+                menu,
+                &style,
+                MenuButton::Open(MenuState::Backlog),
+                LocalizedText::new("ui.backlog", "Backlog"),
+            );
+            spawn_button(
+                menu,
+                &style,
+                MenuButton::Open(MenuState::Settings),
+                LocalizedText::new("ui.settings", "Settings"),
+            );
+            spawn_button(
+                menu,
+                &style,
+                MenuButton::MainMenu,
+                LocalizedText::new("ui.main_menu", "Main Menu"),
+            );
+        });
+    });
+}
+
+/// What a save slot holds: the line it was saved at.
+fn slot_text(history: &StoryHistory, slot: u32) -> String {
+    let line = history
+        .slot(slot)
+        .and_then(|snapshot| snapshot.state.line.as_ref());
+    match line {
+        Some(line) => {
+            let text: String = line.text.replace('\n', " ").chars().take(24).collect();
+            format!("{slot}. {text}")
+        }
+        None => format!("{slot}. -"),
+    }
+}
+
+fn spawn_save_load(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    ui_root: Res<UiRoot>,
+    history: Res<StoryHistory>,
+    game: Res<State<GameState>>,
+) {
+    let style = text_style(&asset_server, 24.0);
+    // 在主菜单只能读档
+    let can_save = *game.get() == GameState::InGame;
+    commands.entity(ui_root.0).with_children(|root| {
+        root.spawn((
+            screen(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            Name::new("save_load_menu"),
+            StateScoped(MenuState::SaveLoad),
+            FocusScope,
+        ))
+        .with_children(|menu| {
+            for slot in SAVE_SLOTS {
+                menu.spawn(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        column_gap: Px(16.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn((
+                        TextBundle {
+                            text: Text::from_section(slot_text(&history, slot), style.clone()),
+                            style: Style {
+                                width: Px(400.0),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        SlotLabel(slot),
+                    ));
+                    if can_save {
+                        spawn_button(
+                            row,
+                            &style,
+                            MenuButton::Save(slot),
+                            LocalizedText::new("ui.save", "Save"),
+                        );
+                    }
+                    spawn_button(
+                        row,
+                        &style,
+                        MenuButton::Load(slot),
+                        LocalizedText::new("ui.load", "Load"),
+                    );
+                });
+            }
+            spawn_button(
+                menu,
+                &style,
+                MenuButton::Back,
+                LocalizedText::new("ui.back", "Back"),
+            );
+        });
+    });
+}
+
+fn update_slot_labels(history: Res<StoryHistory>, mut label_query: Query<(&SlotLabel, &mut Text)>) {
+    if !history.is_changed() {
+        return;
+    }
+    for (label, mut text) in label_query.iter_mut() {
+        let value = slot_text(&history, label.0);
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+fn spawn_backlog(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    ui_root: Res<UiRoot>,
+    history: Res<StoryHistory>,
+    state: Option<Res<StoryState>>,
+) {
+    let style = text_style(&asset_server, 22.0);
+    let lines = match state.as_deref() {
+        Some(state) => history.lines(state),
+        None => Vec::new(),
+    };
+    let start = lines.len().saturating_sub(BACKLOG_LINES);
+    commands.entity(ui_root.0).with_children(|root| {
+        root.spawn((
+            screen(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            Name::new("backlog"),
+            StateScoped(MenuState::Backlog),
+            FocusScope,
+        ))
+        .with_children(|menu| {
+            menu.spawn(NodeBundle {
+                style: Style {
+                    width: Px(1000.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Px(8.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|column| {
+                for line in &lines[start..] {
+                    let text = line.text.replace('\n', " ");
+                    let text = match &line.speaker {
+                        Some(speaker) => format!("【{speaker}】{text}"),
+                        None => text,
+                    };
+                    column.spawn(TextBundle::from_section(text, style.clone()));
+                }
+            });
+            spawn_button(
+                menu,
+                &style,
+                MenuButton::Back,
+                LocalizedText::new("ui.back", "Back"),
+            );
+        });
+    });
+}
+
+#[allow(clippy::type_complexity)]
+fn highlight_buttons(
+    mut button_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<MenuButton>),
+    >,
+) {
+    for (interaction, mut color) in button_query.iter_mut() {
+        *color = match interaction {
+            Interaction::Pressed => PRESSED_COLOR,
+            Interaction::Hovered => HOVERED_COLOR,
+            Interaction::None => BUTTON_COLOR,
+        }
+        .into();
+    }
+}
+
+/// Moves between the screens and menus.
+fn press_menu_buttons(
+    button_query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    game: Res<State<GameState>>,
+    menu: Option<Res<State<MenuState>>>,
+    mut next_game: ResMut<NextState<GameState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+    mut exit_events: EventWriter<AppExit>,
+) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            MenuButton::Open(menu) => next_menu.set(*menu),
+            MenuButton::Back => {
+                if let Some(back) = menu.as_ref().and_then(|menu| menu.back(*game.get())) {
+                    next_menu.set(back);
+                }
+            }
+            MenuButton::Resume => next_menu.set(MenuState::Closed),
+            MenuButton::MainMenu => next_game.set(GameState::MainMenu),
+            MenuButton::Quit => {
+                exit_events.send(AppExit::Success);
+            }
+            MenuButton::Continue
+            | MenuButton::Start(_)
+            | MenuButton::Save(_)
+            | MenuButton::Load(_) => {}
+        }
+    }
+}
+
+/// Starts the story from the main menu through the [`StoryStart`], and
+/// saves and loads in the story through [`PlayerAction`]s, so recordings
+/// replay them.
+fn press_story_buttons(
+    button_query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    game: Res<State<GameState>>,
+    history: Res<StoryHistory>,
+    mut start: ResMut<StoryStart>,
+    mut next_game: ResMut<NextState<GameState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+    mut actions: EventWriter<PlayerAction>,
+) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let in_game = *game.get() == GameState::InGame;
+        match button {
+            MenuButton::Continue => {
+                if let Some(snapshot) = history.latest_slot().and_then(|slot| history.slot(slot)) {
+                    *start = StoryStart::Snapshot(snapshot.clone());
+                    next_game.set(GameState::InGame);
+                }
+            }
+            MenuButton::Start(script) => {
+                *start = StoryStart::Script(script.clone());
+                next_game.set(GameState::InGame);
+            }
+            MenuButton::Save(slot) => {
+                actions.send(PlayerAction::Save(*slot));
+            }
+            // 空的存档位不能读取
+            MenuButton::Load(slot) => match history.slot(*slot) {
+                Some(_) if in_game => {
+                    actions.send(PlayerAction::Load(*slot));
+                    next_menu.set(MenuState::Closed);
+                }
+                Some(snapshot) => {
+                    *start = StoryStart::Snapshot(snapshot.clone());
+                    next_game.set(GameState::InGame);
+                }
+                None => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::event_bus::{CommandBus, CommandBusPlugin, VnValue};
+    use crate::core::script::Script;
+    use crate::plugins::layout::LayoutPlugin;
+    use crate::utils::test_harness::TestApp;
+
+    fn menu_app(title: TitleScreen) -> TestApp {
+        TestApp::new(move |app: &mut App| {
+            app.insert_resource(title.clone()).add_plugins((
+                CommandBusPlugin,
+                LayoutPlugin,
+                GameMenusPlugin,
+            ));
+        })
+    }
+
+    fn buttons(app: &mut TestApp) -> Vec<MenuButton> {
+        let entities: Vec<Entity> = app.entities_with::<MenuButton>();
+        entities
+            .into_iter()
+            .map(|entity| app.get::<MenuButton>(entity).clone())
+            .collect()
+    }
+
+    fn button(app: &mut TestApp, wanted: MenuButton) -> Entity {
+        let buttons: Vec<Entity> = app.entities_with::<MenuButton>();
+        buttons
+            .into_iter()
+            .find(|&entity| *app.get::<MenuButton>(entity) == wanted)
+            .unwrap_or_else(|| panic!("no {wanted:?} button"))
+    }
+
+    fn game(app: &TestApp) -> GameState {
+        *app.world().resource::<State<GameState>>().get()
+    }
+
+    #[test]
+    fn goes_from_the_title_into_the_story_and_back() {
+        let mut app = menu_app(TitleScreen::default());
+        assert_eq!(game(&app), GameState::Title);
+        app.tap_mouse_button(MouseButton::Left);
+        app.update();
+        assert_eq!(game(&app), GameState::MainMenu);
+
+        let new_game = button(&mut app, MenuButton::Start(FIRST_SCRIPT.to_string()));
+        app.click(new_game);
+        assert_eq!(game(&app), GameState::InGame);
+        assert!(app.world().get_entity(new_game).is_none());
+
+        app.tap_key(KeyCode::Escape);
+        app.update();
+        let main_menu = button(&mut app, MenuButton::MainMenu);
+        app.click(main_menu);
+        assert_eq!(game(&app), GameState::MainMenu);
+        assert_eq!(
+            *app.world().resource::<State<MenuState>>().get(),
+            MenuState::Closed
+        );
+        assert!(app.world().get_entity(main_menu).is_none());
+    }
+
+    #[test]
+    fn the_a_and_start_buttons_leave_the_title() {
+        let mut app = menu_app(TitleScreen::default());
+        app.tap_gamepad_button(GamepadButtonType::South);
+        app.update();
+        assert_eq!(game(&app), GameState::MainMenu);
+
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Title);
+        app.update();
+        app.tap_gamepad_button(GamepadButtonType::Start);
+        app.update();
+        assert_eq!(game(&app), GameState::MainMenu);
+        assert_eq!(
+            *app.world().resource::<State<MenuState>>().get(),
+            MenuState::Closed
+        );
+    }
+
+    #[test]
+    fn shows_items_once_saved_or_unlocked() {
+        let title: TitleScreen = toml::from_str(
+            r#"
+background = "bg.title"
+
+[[items]]
+action = "continue"
+
+[[items]]
+action = "start"
+script = "scripts/epilogue.avg"
+if = "persistent.route_a == true"
+
+[[items]]
+action = "quit"
+label = "Exit"
+"#,
+        )
+        .unwrap();
+        assert_eq!(title.items[1].label().id, "ui.epilogue");
+        assert_eq!(title.items[2].label().source, "Exit");
+        assert!(toml::from_str::<TitleScreen>("[[items]]\naction = \"quit\"\nif = \"x\"").is_err());
+
+        let mut app = menu_app(title);
+        app.tap_key(KeyCode::Enter);
+        app.update();
+        assert_eq!(buttons(&mut app), [MenuButton::Quit]);
+
+        let script = Script::parse("set persistent.route_a = true\n\"a\"\n").unwrap();
+        app.world_mut().resource_mut::<CommandBus>().start(&script);
+        app.update();
+        app.world_mut().resource_mut::<StoryHistory>().save(2);
+        // 重新进入主菜单
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Title);
+        app.update();
+        app.tap_key(KeyCode::Enter);
+        app.update();
+        let mut shown = buttons(&mut app);
+        shown.sort_by_key(|button| format!("{button:?}"));
+        assert_eq!(
+            shown,
+            [
+                MenuButton::Continue,
+                MenuButton::Quit,
+                MenuButton::Start("scripts/epilogue.avg".to_string()),
+            ]
+        );
+
+        let continue_button = button(&mut app, MenuButton::Continue);
+        app.click(continue_button);
+        assert_eq!(game(&app), GameState::InGame);
+        let StoryStart::Snapshot(snapshot) = app.world().resource::<StoryStart>() else {
+            panic!("the story does not start from the save");
+        };
+        assert_eq!(
+            snapshot.state.variables.get("persistent.route_a"),
+            Some(&VnValue::Bool(true))
+        );
+    }
+}
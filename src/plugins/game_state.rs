@@ -0,0 +1,209 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+
+use crate::plugins::drop_down::DropDown;
+use crate::plugins::focus::gamepad_just_pressed;
+
+/// A Bevy Plugin with the screens of the game, [`GameState`], and the menus
+/// opened over them, [`MenuState`].
+///
+/// Spawn the entities of a screen or menu with `StateScoped` in its
+/// `OnEnter` schedule and they are despawned when it is left; gate its
+/// systems with `run_if(in_state(..))`. Escape, or Start on a gamepad, opens
+/// the pause menu while the story plays and goes back from the other menus.
+pub struct GameStatePlugin;
+
+/// The screen the game shows.
+#[derive(States, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum GameState {
+    #[default]
+    Title,
+    MainMenu,
+    /// The story is played.
+    InGame,
+}
+
+/// The menu opened over the main menu or the story.
+///
+/// It only exists in [`GameState::MainMenu`] and [`GameState::InGame`], and
+/// is closed whenever the game state changes.
+#[derive(SubStates, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[source(GameState = GameState::MainMenu | GameState::InGame)]
+pub enum MenuState {
+    #[default]
+    Closed,
+    Pause,
+    Settings,
+    SaveLoad,
+    Backlog,
+}
+
+impl MenuState {
+    /// The menu Escape goes back to from this one in `game`.
+    pub fn back(self, game: GameState) -> Option<MenuState> {
+        match (game, self) {
+            (GameState::InGame, MenuState::Closed) => Some(MenuState::Pause),
+            (GameState::InGame, MenuState::Pause) => Some(MenuState::Closed),
+            (GameState::InGame, _) => Some(MenuState::Pause),
+            (_, MenuState::Closed) => None,
+            _ => Some(MenuState::Closed),
+        }
+    }
+}
+
+/// The z-index of the menus: above the story, below the loading screen and
+/// open dropdown lists.
+pub const MENU_Z_INDEX: i32 = 50;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<StatesPlugin>() {
+            app.add_plugins(StatesPlugin);
+        }
+        // 可以在添加插件前用 insert_state 指定开始的界面
+        if !app.world().contains_resource::<State<GameState>>() {
+            app.init_state::<GameState>();
+        }
+        app.add_sub_state::<MenuState>()
+            .enable_state_scoped_entities::<GameState>()
+            .enable_state_scoped_entities::<MenuState>()
+            .add_systems(
+                PreUpdate,
+                (
+                    navigate_menus
+                        .after(InputSystem)
+                        .run_if(in_state(GameState::MainMenu).or_else(in_state(GameState::InGame))),
+                    close_menu_on_screen_change.after(navigate_menus),
+                ),
+            );
+    }
+}
+
+/// Opens and closes the menus with Escape or the Start button of a gamepad;
+/// B (East) goes back from an open menu.
+///
+/// Runs before the dropdowns see the key, so Escape closing an open list
+/// does not leave the menu as well.
+fn navigate_menus(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_input: Res<ButtonInput<GamepadButton>>,
+    game: Res<State<GameState>>,
+    menu: Res<State<MenuState>>,
+    drop_down_query: Query<&DropDown>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    let escape = keyboard_input.just_pressed(KeyCode::Escape)
+        || gamepad_just_pressed(&gamepad_input, GamepadButtonType::Start);
+    // B 只用于返回，不打开暂停菜单
+    let back = gamepad_just_pressed(&gamepad_input, GamepadButtonType::East)
+        && *menu.get() != MenuState::Closed;
+    if !escape && !back {
+        return;
+    }
+    if drop_down_query.iter().any(|drop_down| drop_down.open) {
+        return;
+    }
+    if let Some(back) = menu.back(*game.get()) {
+        next_menu.set(back);
+    }
+}
+
+/// Closes the menu along with a change of [`GameState`]; a sub-state keeps
+/// its value when moving between source states it exists in.
+fn close_menu_on_screen_change(
+    game: Res<State<GameState>>,
+    next_game: Res<NextState<GameState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    let NextState::Pending(next) = *next_game else {
+        return;
+    };
+    if next != *game.get() && matches!(*next_menu, NextState::Unchanged) {
+        next_menu.set(MenuState::Closed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_harness::TestApp;
+
+    fn menu(app: &TestApp) -> Option<MenuState> {
+        app.world()
+            .get_resource::<State<MenuState>>()
+            .map(|state| *state.get())
+    }
+
+    #[test]
+    fn escape_pauses_the_story_and_goes_back() {
+        let mut app = TestApp::new(GameStatePlugin);
+        assert_eq!(menu(&app), None);
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::InGame);
+        app.update();
+        assert_eq!(menu(&app), Some(MenuState::Closed));
+
+        app.tap_key(KeyCode::Escape);
+        app.update();
+        assert_eq!(menu(&app), Some(MenuState::Pause));
+        let pause_menu = app.world_mut().spawn(StateScoped(MenuState::Pause)).id();
+
+        app.world_mut()
+            .resource_mut::<NextState<MenuState>>()
+            .set(MenuState::Settings);
+        app.update();
+        assert!(app.world().get_entity(pause_menu).is_none());
+        app.tap_key(KeyCode::Escape);
+        app.update();
+        assert_eq!(menu(&app), Some(MenuState::Pause));
+        app.tap_key(KeyCode::Escape);
+        app.update();
+        assert_eq!(menu(&app), Some(MenuState::Closed));
+    }
+
+    #[test]
+    fn gamepad_start_pauses_and_b_goes_back() {
+        let mut app = TestApp::new(GameStatePlugin);
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::InGame);
+        app.update();
+
+        // 没有打开的菜单时 B 不做任何事
+        app.tap_gamepad_button(GamepadButtonType::East);
+        app.update();
+        assert_eq!(menu(&app), Some(MenuState::Closed));
+        app.tap_gamepad_button(GamepadButtonType::Start);
+        app.update();
+        assert_eq!(menu(&app), Some(MenuState::Pause));
+        app.tap_gamepad_button(GamepadButtonType::East);
+        app.update();
+        assert_eq!(menu(&app), Some(MenuState::Closed));
+    }
+
+    #[test]
+    fn leaving_the_story_closes_the_menu() {
+        let mut app = TestApp::new(GameStatePlugin);
+        let title = app.world_mut().spawn(StateScoped(GameState::Title)).id();
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::InGame);
+        app.update();
+        assert!(app.world().get_entity(title).is_none());
+
+        app.tap_key(KeyCode::Escape);
+        app.update();
+        assert_eq!(menu(&app), Some(MenuState::Pause));
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::MainMenu);
+        app.update();
+        // 切换界面时菜单随之关闭
+        assert_eq!(menu(&app), Some(MenuState::Closed));
+        app.tap_key(KeyCode::Escape);
+        app.update();
+        assert_eq!(menu(&app), Some(MenuState::Closed));
+    }
+}
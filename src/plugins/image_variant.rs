@@ -0,0 +1,153 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::core::catalogue::{best_variant, AssetCatalogue};
+use crate::plugins::layout::BackgroundFit;
+
+/// A Bevy Plugin loading the resolution variant of images that suits the
+/// window, see [`AssetCatalogue::variant`].
+///
+/// The variant is picked from the physical height of the primary window, so
+/// it follows the resolution setting and the display's scale factor. Sprites
+/// with a [`VariantImage`] get the image of the current variant, and a new
+/// one whenever the variant changes. Their size stays the size of the image
+/// at the design resolution; backgrounds are sized by their
+/// [`BackgroundFit`] instead.
+pub struct ImageVariantPlugin;
+
+/// The resolution variant images are loaded in, `None` without a window.
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct ActiveVariant(pub Option<&'static str>);
+
+/// The path or catalogue id of the image a sprite shows, loaded in the
+/// [`ActiveVariant`]. An empty path leaves the sprite's image alone.
+#[derive(Component, Clone, Debug, Default, PartialEq)]
+pub struct VariantImage(pub String);
+
+impl Plugin for ImageVariantPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveVariant>()
+            // 章节在 Startup 中预加载，需要先选好变体
+            .add_systems(PreStartup, select_variant)
+            .add_systems(PreUpdate, select_variant)
+            .add_systems(PostUpdate, load_variant_images);
+    }
+}
+
+impl ActiveVariant {
+    /// The path to load for the image `id_or_path` in this variant.
+    pub fn resolve<'a>(
+        &self,
+        catalogue: Option<&'a AssetCatalogue>,
+        id_or_path: &'a str,
+    ) -> &'a str {
+        match catalogue {
+            Some(catalogue) => catalogue.variant(catalogue.resolve(id_or_path), self.0),
+            None => id_or_path,
+        }
+    }
+}
+
+fn select_variant(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut active: ResMut<ActiveVariant>,
+) {
+    // 无窗口时保留原图
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    let variant = best_variant(window.resolution.physical_height() as f32);
+    if active.0 != Some(variant) {
+        info!("loading images in the {variant} variant");
+        active.0 = Some(variant);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn load_variant_images(
+    active: Res<ActiveVariant>,
+    catalogue: Option<Res<AssetCatalogue>>,
+    asset_server: Res<AssetServer>,
+    mut query: Query<(
+        Ref<VariantImage>,
+        &mut Handle<Image>,
+        Option<&mut Sprite>,
+        Has<BackgroundFit>,
+    )>,
+) {
+    let catalogue = catalogue.as_deref();
+    for (image, mut handle, sprite, is_background) in query.iter_mut() {
+        if !(active.is_changed() || image.is_changed()) || image.0.is_empty() {
+            continue;
+        }
+        let path = active.resolve(catalogue, &image.0);
+        if handle
+            .path()
+            .is_some_and(|current| current.path().to_string_lossy() == path)
+        {
+            continue;
+        }
+        *handle = asset_server.load(path.to_string());
+        // 高分辨率变体按原图尺寸显示
+        let size = catalogue
+            .and_then(|catalogue| catalogue.by_path(catalogue.resolve(&image.0)))
+            .and_then(|entry| entry.dimensions);
+        if let (Some(mut sprite), Some(size), false) = (sprite, size, is_background) {
+            sprite.custom_size = Some(size.as_vec2());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_harness::TestApp;
+    use std::path::Path;
+
+    #[test]
+    fn loads_the_active_variant_and_switches_with_it() {
+        let mut app = TestApp::new(ImageVariantPlugin);
+        let root = std::env::temp_dir().join(format!("avg-variant-test-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("images")).unwrap();
+        for (name, size) in [("ch.png", 4), ("ch@1080p.png", 6)] {
+            image::RgbaImage::new(size, size * 2)
+                .save(root.join("images").join(name))
+                .unwrap();
+        }
+        let catalogue = AssetCatalogue::scan(Path::new(&root)).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        app.world_mut().insert_resource(catalogue);
+
+        let sprite = app
+            .world_mut()
+            .spawn((
+                SpriteBundle::default(),
+                VariantImage("images.ch".to_string()),
+            ))
+            .id();
+        app.update();
+        let path = |app: &TestApp| {
+            app.get::<Handle<Image>>(sprite)
+                .path()
+                .map(|path| path.path().to_string_lossy().into_owned())
+        };
+        assert_eq!(path(&app).as_deref(), Some("images/ch.png"));
+
+        app.world_mut().resource_mut::<ActiveVariant>().0 = Some("1080p");
+        app.update();
+        assert_eq!(path(&app).as_deref(), Some("images/ch@1080p.png"));
+        assert_eq!(
+            app.get::<Sprite>(sprite).custom_size,
+            Some(Vec2::new(4.0, 8.0))
+        );
+
+        // 没有 1440p 时用最接近的 1080p
+        app.world_mut().resource_mut::<ActiveVariant>().0 = Some("1440p");
+        app.update();
+        assert_eq!(path(&app).as_deref(), Some("images/ch@1080p.png"));
+
+        app.world_mut().resource_mut::<ActiveVariant>().0 = None;
+        app.update();
+        assert_eq!(path(&app).as_deref(), Some("images/ch.png"));
+    }
+}
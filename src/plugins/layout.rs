@@ -0,0 +1,231 @@
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use bevy::window::{PrimaryWindow, WindowResized};
+
+use crate::global_def::global_define::{GameConfig, RESOLUTION_720P};
+
+/// The resolution every layout is designed in.
+///
+/// UI nodes use `Val::Px` in design units and sprites use world units equal to
+/// design units; both are scaled to the actual window by [`LayoutPlugin`].
+pub const DESIGN_RESOLUTION: Vec2 = Vec2::new(RESOLUTION_720P.0, RESOLUTION_720P.1);
+
+/// A Bevy Plugin that maps the design resolution onto the actual window.
+///
+/// The design area is scaled uniformly to fit the window and centered, leaving
+/// letterbox bars on the longer axis. The plugin owns the 2D camera and a root
+/// UI node ([`UiRoot`]) of exactly [`DESIGN_RESOLUTION`] size; UI spawned under
+/// that node can be positioned in design pixels and reflows on every resize.
+pub struct LayoutPlugin;
+
+/// The current mapping between design units and logical window pixels.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct VirtualResolution {
+    /// The size layouts are designed in.
+    pub design: Vec2,
+    /// The logical size of the primary window.
+    pub window: Vec2,
+    /// Logical window pixels per design unit.
+    pub scale: f32,
+    /// Size of the letterbox bar on each axis, in logical window pixels.
+    pub offset: Vec2,
+}
+
+impl VirtualResolution {
+    pub fn new(design: Vec2, window: Vec2) -> Self {
+        let scale = (window.x / design.x).min(window.y / design.y).max(f32::EPSILON);
+        Self {
+            design,
+            window,
+            scale,
+            offset: (window - design * scale) / 2.0,
+        }
+    }
+
+    /// Converts a window position (e.g. `Window::cursor_position`) to design units.
+    pub fn window_to_design(&self, position: Vec2) -> Vec2 {
+        (position - self.offset) / self.scale
+    }
+
+    /// The window area in design units, including the letterbox bars.
+    pub fn visible_area(&self) -> Vec2 {
+        self.window / self.scale
+    }
+}
+
+impl Default for VirtualResolution {
+    fn default() -> Self {
+        Self::new(DESIGN_RESOLUTION, DESIGN_RESOLUTION)
+    }
+}
+
+/// The root UI node sized to [`DESIGN_RESOLUTION`], parent of all scaled UI.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct UiRoot(pub Entity);
+
+#[derive(Component)]
+struct UiRootNode;
+
+/// How a background sprite is sized against the window.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BackgroundFit {
+    /// Fit inside the design area, keeping the aspect ratio.
+    Contain,
+    /// Fill the whole window including the letterbox bars, keeping the aspect ratio.
+    #[default]
+    Cover,
+    /// Stretch to the design area, ignoring the aspect ratio.
+    Stretch,
+}
+
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        // 根节点在添加插件时生成，启动时就进入的界面也能用它
+        let root = app
+            .world_mut()
+            .spawn((
+                NodeBundle {
+                    style: root_style(&VirtualResolution::default()),
+                    ..default()
+                },
+                Name::new("ui_root"),
+                UiRootNode,
+            ))
+            .id();
+        app.init_resource::<VirtualResolution>()
+            .insert_resource(UiRoot(root))
+            .add_systems(PreStartup, spawn_entities)
+            .add_systems(
+                Update,
+                (
+                    apply_config_resolution,
+                    update_virtual_resolution,
+                    fit_backgrounds,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Spawns the camera and fits the root UI node to the window.
+fn spawn_entities(
+    mut commands: Commands,
+    mut ui_scale: ResMut<UiScale>,
+    mut virtual_resolution: ResMut<VirtualResolution>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut root_query: Query<&mut Style, With<UiRootNode>>,
+) {
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scaling_mode = ScalingMode::AutoMin {
+        min_width: DESIGN_RESOLUTION.x,
+        min_height: DESIGN_RESOLUTION.y,
+    };
+    commands.spawn(camera);
+
+    if let Ok(window) = window_query.get_single() {
+        *virtual_resolution = VirtualResolution::new(DESIGN_RESOLUTION, window_size(window));
+    }
+    ui_scale.0 = virtual_resolution.scale;
+    for mut style in root_query.iter_mut() {
+        *style = root_style(&virtual_resolution);
+    }
+}
+
+fn root_style(virtual_resolution: &VirtualResolution) -> Style {
+    // UiScale 会同时缩放 left/top，因此偏移量需要换算回设计单位
+    let offset = virtual_resolution.offset / virtual_resolution.scale;
+    Style {
+        position_type: PositionType::Absolute,
+        left: Val::Px(offset.x),
+        top: Val::Px(offset.y),
+        width: Val::Px(virtual_resolution.design.x),
+        height: Val::Px(virtual_resolution.design.y),
+        ..default()
+    }
+}
+
+fn window_size(window: &Window) -> Vec2 {
+    Vec2::new(window.resolution.width(), window.resolution.height())
+}
+
+/// Resizes the primary window when the resolution setting changes.
+fn apply_config_resolution(
+    config: Res<GameConfig>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !config.is_changed() || config.is_added() {
+        return;
+    }
+    for mut window in window_query.iter_mut() {
+        let (width, height) = config.resolution;
+        if window_size(&window) != Vec2::new(width, height) {
+            window.resolution.set(width, height);
+        }
+    }
+}
+
+fn update_virtual_resolution(
+    mut resized_events: EventReader<WindowResized>,
+    mut virtual_resolution: ResMut<VirtualResolution>,
+    mut ui_scale: ResMut<UiScale>,
+    window_query: Query<(), With<PrimaryWindow>>,
+    mut root_query: Query<&mut Style, With<UiRootNode>>,
+) {
+    let Some(resized) = resized_events
+        .read()
+        .filter(|event| window_query.contains(event.window))
+        .last()
+    else {
+        return;
+    };
+
+    let updated = VirtualResolution::new(
+        virtual_resolution.design,
+        Vec2::new(resized.width, resized.height),
+    );
+    if updated == *virtual_resolution {
+        return;
+    }
+    *virtual_resolution = updated;
+    ui_scale.0 = updated.scale;
+    for mut style in root_query.iter_mut() {
+        *style = root_style(&updated);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn fit_backgrounds(
+    virtual_resolution: Res<VirtualResolution>,
+    images: Res<Assets<Image>>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+    mut query: Query<(Ref<Handle<Image>>, Ref<BackgroundFit>, &mut Transform)>,
+) {
+    let loaded = image_events
+        .read()
+        .any(|event| matches!(event, AssetEvent::LoadedWithDependencies { .. }));
+
+    for (handle, fit, mut transform) in query.iter_mut() {
+        if !(loaded || virtual_resolution.is_changed() || handle.is_changed() || fit.is_changed())
+        {
+            continue;
+        }
+        let Some(image) = images.get(handle.id()) else {
+            continue;
+        };
+        let size = image.size_f32();
+        if size.x <= 0.0 || size.y <= 0.0 {
+            continue;
+        }
+
+        let scale = match *fit {
+            BackgroundFit::Contain => Vec2::splat(
+                (virtual_resolution.design / size).min_element(),
+            ),
+            BackgroundFit::Cover => Vec2::splat(
+                (virtual_resolution.visible_area() / size).max_element(),
+            ),
+            BackgroundFit::Stretch => virtual_resolution.design / size,
+        };
+        transform.scale = scale.extend(transform.scale.z);
+    }
+}
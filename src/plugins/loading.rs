@@ -0,0 +1,209 @@
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+
+use crate::plugins::layout::UiRoot;
+use crate::plugins::processor_bar::{AnimatedProgressBar, ProgressBar, ProgressBarPlugin, ProgressLabel};
+
+/// A Bevy Plugin showing a loading screen while the assets of a chapter load.
+///
+/// Send [`LoadChapter`] with a handle to every asset the chapter's script
+/// references. The plugin covers the screen with a progress bar tracking
+/// their `LoadState`, and sends [`ChapterReady`] once all of them are
+/// loaded. Assets that fail to load are listed by path on the loading screen
+/// and the chapter is not entered.
+pub struct LoadingPlugin;
+
+/// Send to load the assets of a chapter behind the loading screen.
+#[derive(Event, Clone, Debug)]
+pub struct LoadChapter {
+    pub name: String,
+    pub assets: Vec<UntypedHandle>,
+}
+
+/// Sent once every asset of a [`LoadChapter`] has loaded.
+#[derive(Event, Clone, Debug)]
+pub struct ChapterReady {
+    pub name: String,
+}
+
+/// The chapter being loaded, and the assets of the current one.
+///
+/// The handles of the current chapter are kept here so its assets stay
+/// loaded until the next chapter replaces them.
+#[derive(Resource, Default)]
+pub struct ChapterLoader {
+    pending: Option<LoadChapter>,
+    current: Vec<UntypedHandle>,
+    /// The paths of the pending assets that failed to load.
+    pub failed: Vec<String>,
+}
+
+impl ChapterLoader {
+    pub fn is_loading(&self) -> bool {
+        self.pending.is_some()
+    }
+}
+
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct LoadingBar;
+
+#[derive(Component)]
+struct LoadingErrors;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ProgressBarPlugin>() {
+            app.add_plugins(ProgressBarPlugin);
+        }
+        app.add_event::<LoadChapter>()
+            .add_event::<ChapterReady>()
+            .init_resource::<ChapterLoader>()
+            .add_systems(Update, (start_loading, track_loading).chain());
+    }
+}
+
+fn start_loading(
+    mut commands: Commands,
+    mut events: EventReader<LoadChapter>,
+    mut loader: ResMut<ChapterLoader>,
+    asset_server: Res<AssetServer>,
+    ui_root: Res<UiRoot>,
+    screen_query: Query<Entity, With<LoadingScreen>>,
+) {
+    // 同一帧多次请求时只加载最后一个章节
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    loader.pending = Some(event.clone());
+    loader.failed.clear();
+    for screen in screen_query.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_style = |font_size: f32, color: Color| TextStyle {
+        font: font.clone(),
+        font_size,
+        color,
+    };
+
+    commands.entity(ui_root.0).with_children(|root| {
+        root.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: Color::BLACK.into(),
+                // 加载界面盖住其他所有界面
+                z_index: ZIndex::Global(100),
+                ..default()
+            },
+            Name::new("loading_screen"),
+            LoadingScreen,
+        ))
+        .with_children(|screen| {
+            screen.spawn(TextBundle::from_section(
+                format!("Loading {}", event.name),
+                text_style(30.0, Color::WHITE),
+            ));
+            screen.spawn((
+                NodeBundle::default(),
+                ProgressBar {
+                    size: Vec2::new(600.0, 30.0),
+                    bar_color: Color::srgb(0.25, 0.75, 0.25),
+                    background_color: Color::srgb(0.15, 0.15, 0.15),
+                    label: ProgressLabel::Percent,
+                    font: font.clone(),
+                    ..default()
+                },
+                AnimatedProgressBar {
+                    target: 0.0,
+                    speed: 200.0,
+                },
+                LoadingBar,
+            ));
+            screen.spawn((
+                TextBundle::from_section("", text_style(20.0, Color::srgb(0.9, 0.3, 0.3))),
+                LoadingErrors,
+            ));
+        });
+    });
+}
+
+fn track_loading(
+    mut commands: Commands,
+    mut loader: ResMut<ChapterLoader>,
+    asset_server: Res<AssetServer>,
+    mut ready_events: EventWriter<ChapterReady>,
+    mut bar_query: Query<&mut AnimatedProgressBar, With<LoadingBar>>,
+    mut error_query: Query<&mut Text, With<LoadingErrors>>,
+    screen_query: Query<Entity, With<LoadingScreen>>,
+) {
+    let Some(pending) = &loader.pending else {
+        return;
+    };
+
+    let mut loaded = 0;
+    let mut failed = Vec::new();
+    for handle in pending.assets.iter() {
+        match asset_server.load_state(handle.id()) {
+            LoadState::Loaded => loaded += 1,
+            LoadState::Failed(_) => failed.push(
+                handle
+                    .path()
+                    .map(|path| path.to_string())
+                    .unwrap_or_else(|| format!("{:?}", handle.id())),
+            ),
+            LoadState::NotLoaded | LoadState::Loading => {}
+        }
+    }
+    let total = pending.assets.len();
+
+    for mut bar in bar_query.iter_mut() {
+        let target = if total == 0 {
+            100.0
+        } else {
+            loaded as f32 / total as f32 * 100.0
+        };
+        if bar.target != target {
+            bar.target = target;
+        }
+    }
+
+    if failed != loader.failed {
+        for path in failed.iter().filter(|path| !loader.failed.contains(path)) {
+            error!("chapter {}: can not load {}", pending.name, path);
+        }
+        for mut text in error_query.iter_mut() {
+            text.sections[0].value = failed
+                .iter()
+                .map(|path| format!("Missing: {path}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        loader.failed = failed;
+    }
+
+    if loaded < total {
+        return;
+    }
+    let Some(pending) = loader.pending.take() else {
+        return;
+    };
+    loader.current = pending.assets;
+    for screen in screen_query.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+    info!("chapter {} loaded", pending.name);
+    ready_events.send(ChapterReady { name: pending.name });
+}
@@ -1,7 +1,15 @@
+pub mod character_layers;
 pub mod config;
 pub mod drop_down;
+pub mod focus;
+pub mod game_menus;
+pub mod game_state;
+pub mod image_variant;
+pub mod layout;
+pub mod loading;
 pub mod scene_play;
 pub mod scroll_view;
 pub mod processor_bar;
+pub mod replay;
 pub mod check_box;
-mod slider_bar;
+pub mod slider_bar;
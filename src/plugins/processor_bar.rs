@@ -1,26 +1,105 @@
+use std::f32::consts::TAU;
+
 use bevy::prelude::*;
 
 pub struct ProgressBarPlugin;
 
-#[derive(Component)]
+/// A progress bar, drawn as a solid bar, a row of segments or a ring.
+///
+/// Insert it on a `NodeBundle`; the visuals are spawned as children on the
+/// next update and rebuilt when the variant, orientation, size or label kind
+/// changes. Add an [`AnimatedProgressBar`] to move `value` smoothly towards a
+/// target instead of setting it directly.
+#[derive(Component, Clone)]
 pub struct ProgressBar {
     pub value: f32,
     pub max_value: f32,
     pub size: Vec2,
     pub bar_color: Color,
     pub background_color: Color,
+    pub variant: ProgressBarVariant,
+    /// Ignored by [`ProgressBarVariant::Radial`].
+    pub orientation: ProgressOrientation,
+    pub label: ProgressLabel,
+    pub font: Handle<Font>,
+    pub font_size: f32,
+    pub label_color: Color,
+    /// Shows activity without a known amount of progress, ignoring `value`.
+    pub indeterminate: bool,
 }
 
-#[derive(Component)]
-struct ProgressBarBackground;
+/// How a [`ProgressBar`] is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ProgressBarVariant {
+    /// A single bar growing along the orientation.
+    #[default]
+    Solid,
+    /// `count` blocks separated by `gap` pixels, lit one after another.
+    Segmented { count: usize, gap: f32 },
+    /// `segments` dots on a ring, lit clockwise from the top.
+    Radial { segments: usize },
+}
 
+/// The direction a [`ProgressBar`] fills in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProgressOrientation {
+    /// Fills from left to right.
+    #[default]
+    Horizontal,
+    /// Fills from bottom to top.
+    Vertical,
+}
+
+/// The text shown centered on a [`ProgressBar`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ProgressLabel {
+    #[default]
+    None,
+    /// The progress as a whole percentage, hidden while indeterminate.
+    Percent,
+    Text(String),
+}
+
+/// Moves [`ProgressBar::value`] towards `target` at `speed` units per second.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct AnimatedProgressBar {
+    pub target: f32,
+    pub speed: f32,
+}
+
+/// The spawned visuals of a [`ProgressBar`].
 #[derive(Component)]
-struct ProgressBarFill;
+struct ProgressBarParts {
+    background: Entity,
+    /// The fill node of a solid bar, or the segments/dots in fill order.
+    cells: Vec<Entity>,
+    label: Option<Entity>,
+    /// What the parts were built for, to detect when they must be rebuilt.
+    built: PartsKey,
+    /// Position of the indeterminate sweep, in `0.0..1.0`.
+    phase: f32,
+}
+
+/// The fields of a [`ProgressBar`] its parts are built from.
+type PartsKey = (ProgressBarVariant, ProgressOrientation, Vec2, bool);
+
+/// Sweeps per second of an indeterminate bar.
+const INDETERMINATE_SPEED: f32 = 0.6;
+
+/// The length of the indeterminate sweep, as a fraction of the bar.
+const INDETERMINATE_LEN: f32 = 0.3;
 
 impl Plugin for ProgressBarPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup,entities_spawn)
-            .add_systems(Update, (update_progress_bars,animate_progress_bars));
+        app.add_systems(
+            Update,
+            (
+                build_progress_bars,
+                animate_progress_bars,
+                update_progress_bars,
+            )
+                .chain(),
+        );
     }
 }
 
@@ -32,117 +111,200 @@ impl Default for ProgressBar {
             size: Vec2::new(200.0, 20.0),
             bar_color: Color::srgba(0.0, 1.0, 0.0, 0.5),
             background_color: Color::BLACK,
+            variant: ProgressBarVariant::Solid,
+            orientation: ProgressOrientation::Horizontal,
+            label: ProgressLabel::None,
+            font: Handle::default(),
+            font_size: 16.0,
+            label_color: Color::WHITE,
+            indeterminate: false,
         }
     }
 }
 
-fn update_progress_bars(
+impl ProgressBar {
+    /// The value as a fraction of `max_value`, in `0.0..=1.0`.
+    pub fn fraction(&self) -> f32 {
+        if self.max_value > 0.0 {
+            (self.value / self.max_value).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    fn parts_key(&self) -> PartsKey {
+        (
+            self.variant,
+            self.orientation,
+            self.size,
+            self.label != ProgressLabel::None,
+        )
+    }
+
+    /// The filled part of the bar as a `start..end` fraction.
+    fn fill_range(&self, phase: f32) -> (f32, f32) {
+        if !self.indeterminate {
+            return (0.0, self.fraction());
+        }
+        if let ProgressBarVariant::Radial { .. } = self.variant {
+            // 环形进度条首尾相连，直接转圈
+            return (phase, phase + INDETERMINATE_LEN);
+        }
+        let end = phase * (1.0 + INDETERMINATE_LEN);
+        ((end - INDETERMINATE_LEN).max(0.0), end.min(1.0))
+    }
+
+    fn label_text(&self) -> String {
+        match &self.label {
+            ProgressLabel::None => String::new(),
+            ProgressLabel::Percent if self.indeterminate => String::new(),
+            ProgressLabel::Percent => format!("{:.0}%", self.fraction() * 100.0),
+            ProgressLabel::Text(text) => text.clone(),
+        }
+    }
+}
+
+/// Whether cell `index` of `count` lies in the filled `start..end` range.
+///
+/// Ranges past `1.0` wrap around, which only the ring uses.
+fn cell_lit(index: usize, count: usize, (start, end): (f32, f32)) -> bool {
+    let center = (index as f32 + 0.5) / count as f32;
+    (center >= start && center < end) || center + 1.0 < end
+}
+
+fn build_progress_bars(
     mut commands: Commands,
-    mut query: Query<(Entity, &ProgressBar, Option<&Children>)>,
-    mut bar_query: Query<(&mut Style, &mut BackgroundColor, Option<&ProgressBarFill>)>,
+    query: Query<(Entity, &ProgressBar, Option<&ProgressBarParts>), Changed<ProgressBar>>,
 ) {
-    for (entity, progress_bar, children) in query.iter() {
-        let progress = (progress_bar.value / progress_bar.max_value).clamp(0.0, 1.0);
-
-        match children {
-            Some(children) => {
-                // 更新现有进度条
-                for &child in children.iter() {
-                    if let Ok((mut style, mut background_color, is_fill)) = bar_query.get_mut(child) {
-                        if is_fill.is_some() {
-                            style.width = Val::Percent(progress * 100.0);
-                            background_color.0 = progress_bar.bar_color;
-                        } else {
-                            background_color.0 = progress_bar.background_color;
+    for (entity, progress_bar, parts) in query.iter() {
+        if let Some(parts) = parts {
+            if parts.built == progress_bar.parts_key() {
+                continue;
+            }
+            commands.entity(parts.background).despawn_recursive();
+        }
+
+        let size = progress_bar.size;
+        let vertical = progress_bar.orientation == ProgressOrientation::Vertical;
+        let mut cells = Vec::new();
+        let mut label = None;
+
+        // 填充和标签都是背景的子节点，保证绘制在背景之上
+        let mut background = commands.spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(size.x),
+                height: Val::Px(size.y),
+                ..default()
+            },
+            ..default()
+        });
+        background.with_children(|parent| {
+            match progress_bar.variant {
+                ProgressBarVariant::Solid => {
+                    let style = if vertical {
+                        Style {
+                            position_type: PositionType::Absolute,
+                            width: Val::Percent(100.0),
+                            ..default()
                         }
-                    }
+                    } else {
+                        Style {
+                            position_type: PositionType::Absolute,
+                            height: Val::Percent(100.0),
+                            ..default()
+                        }
+                    };
+                    cells.push(parent.spawn(NodeBundle { style, ..default() }).id());
                 }
-            }
-            None => {
-                // 创建背景
-                let background = commands
-                    .spawn((
-                        NodeBundle {
+                ProgressBarVariant::Segmented { count, gap } => {
+                    parent
+                        .spawn(NodeBundle {
                             style: Style {
-                                width: Val::Px(progress_bar.size.x),
-                                height: Val::Px(progress_bar.size.y),
+                                width: Val::Percent(100.0),
+                                height: Val::Percent(100.0),
+                                // 竖直进度条从下往上填充
+                                flex_direction: if vertical {
+                                    FlexDirection::ColumnReverse
+                                } else {
+                                    FlexDirection::Row
+                                },
+                                row_gap: Val::Px(gap),
+                                column_gap: Val::Px(gap),
                                 ..default()
                             },
-                            background_color: progress_bar.background_color.into(),
                             ..default()
-                        },
-                        ProgressBarBackground,
-                    ))
-                    .id();
-
-                // 创建填充条
-                let fill = commands
-                    .spawn((
-                        NodeBundle {
+                        })
+                        .with_children(|row| {
+                            for _ in 0..count {
+                                let segment = row.spawn(NodeBundle {
+                                    style: Style {
+                                        flex_grow: 1.0,
+                                        flex_basis: Val::Px(0.0),
+                                        ..default()
+                                    },
+                                    ..default()
+                                });
+                                cells.push(segment.id());
+                            }
+                        });
+                }
+                ProgressBarVariant::Radial { segments } => {
+                    let diameter = size.min_element();
+                    let dot = diameter * 0.12;
+                    let radius = (diameter - dot) / 2.0;
+                    let center = size / 2.0;
+                    for index in 0..segments {
+                        // 从正上方开始顺时针排列
+                        let angle = index as f32 / segments as f32 * TAU;
+                        let position = center + Vec2::new(angle.sin(), -angle.cos()) * radius;
+                        let dot_node = parent.spawn(NodeBundle {
                             style: Style {
-                                width: Val::Percent(progress * 100.0),
-                                height: Val::Percent(100.0),
+                                position_type: PositionType::Absolute,
+                                left: Val::Px(position.x - dot / 2.0),
+                                top: Val::Px(position.y - dot / 2.0),
+                                width: Val::Px(dot),
+                                height: Val::Px(dot),
                                 ..default()
                             },
-                            background_color: progress_bar.bar_color.into(),
+                            border_radius: BorderRadius::MAX,
                             ..default()
-                        },
-                        ProgressBarFill,
-                    ))
-                    .id();
-
-                commands.entity(entity).push_children(&[fill]);
-                commands.entity(entity).push_children(&[background]);
+                        });
+                        cells.push(dot_node.id());
+                    }
+                }
             }
-        }
-    }
-}
-
-// 使用示例
-fn entities_spawn(mut commands: Commands) {
 
-    // Root UI node
-    commands
-        .spawn(NodeBundle {
-            style: Style {
-                width: Val::Percent(100.0),
-                height: Val::Percent(100.0),
-                flex_direction: FlexDirection::Column,
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::Center,
-                ..default()
-            },
-            ..default()
-        })
-        .with_children(|parent| {
-            // 进度条容器
-            parent
-                .spawn(NodeBundle {
-                    style: Style {
-                        width: Val::Px(300.0),
-                        height: Val::Px(30.0),
-                        position_type: PositionType::Absolute,
-                        left: Val::Px(100.0),
-                        top: Val::Px(100.0),
+            if progress_bar.label != ProgressLabel::None {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
                         ..default()
-                    },
-                    ..default()
-                })
-                .insert(ProgressBar {
-                    value: 75.0,
-                    max_value: 100.0,
-                    size: Vec2::new(300.0, 30.0),
-                    bar_color: Color::srgb(0.25, 0.75, 0.25),
-                    background_color: Color::srgb(0.15, 0.15, 0.15),
-                });
+                    })
+                    .with_children(|overlay| {
+                        label = Some(overlay.spawn(TextBundle::default()).id());
+                    });
+            }
         });
-}
+        let background = background.id();
 
-// 添加动画效果示例
-#[derive(Component)]
-struct AnimatedProgressBar {
-    target: f32,
-    speed: f32,
+        commands
+            .entity(entity)
+            .insert(ProgressBarParts {
+                background,
+                cells,
+                label,
+                built: progress_bar.parts_key(),
+                phase: 0.0,
+            })
+            .add_child(background);
+    }
 }
 
 fn animate_progress_bars(
@@ -150,6 +312,9 @@ fn animate_progress_bars(
     mut query: Query<(&mut ProgressBar, &AnimatedProgressBar)>,
 ) {
     for (mut progress_bar, animation) in query.iter_mut() {
+        if progress_bar.value == animation.target {
+            continue;
+        }
         let delta = animation.speed * time.delta_seconds();
         let diff = animation.target - progress_bar.value;
         if diff.abs() > delta {
@@ -159,3 +324,262 @@ fn animate_progress_bars(
         }
     }
 }
+
+fn update_progress_bars(
+    time: Res<Time>,
+    mut query: Query<(Ref<ProgressBar>, &mut ProgressBarParts)>,
+    mut node_query: Query<(&mut Style, &mut BackgroundColor)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (progress_bar, mut parts) in query.iter_mut() {
+        // 重建后的部件会被重新插入，同样算作变化
+        let refresh = progress_bar.is_changed() || parts.is_changed();
+        if progress_bar.indeterminate {
+            parts.phase = (parts.phase + time.delta_seconds() * INDETERMINATE_SPEED).fract();
+        } else if !refresh {
+            continue;
+        }
+        // 等待重建完成后再更新
+        if parts.built != progress_bar.parts_key() {
+            continue;
+        }
+
+        let range = progress_bar.fill_range(parts.phase);
+        let solid = progress_bar.variant == ProgressBarVariant::Solid;
+
+        if let Ok((_, mut background_color)) = node_query.get_mut(parts.background) {
+            // 分段和环形由各个单元格自己绘制底色
+            background_color.0 = if solid {
+                progress_bar.background_color
+            } else {
+                Color::NONE
+            };
+        }
+
+        let count = parts.cells.len();
+        for (index, &cell) in parts.cells.iter().enumerate() {
+            let Ok((mut style, mut background_color)) = node_query.get_mut(cell) else {
+                continue;
+            };
+            if solid {
+                let (start, end) = range;
+                background_color.0 = progress_bar.bar_color;
+                match progress_bar.orientation {
+                    ProgressOrientation::Horizontal => {
+                        style.left = Val::Percent(start * 100.0);
+                        style.width = Val::Percent((end - start) * 100.0);
+                    }
+                    ProgressOrientation::Vertical => {
+                        style.bottom = Val::Percent(start * 100.0);
+                        style.height = Val::Percent((end - start) * 100.0);
+                    }
+                }
+            } else {
+                background_color.0 = if cell_lit(index, count, range) {
+                    progress_bar.bar_color
+                } else {
+                    progress_bar.background_color
+                };
+            }
+        }
+
+        if !refresh {
+            continue;
+        }
+        if let Some(Ok(mut text)) = parts.label.map(|label| text_query.get_mut(label)) {
+            *text = Text::from_section(
+                progress_bar.label_text(),
+                TextStyle {
+                    font: progress_bar.font.clone(),
+                    font_size: progress_bar.font_size,
+                    color: progress_bar.label_color,
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_harness::TestApp;
+
+    const BAR: Color = Color::WHITE;
+    const BACKGROUND: Color = Color::BLACK;
+
+    fn spawn(app: &mut TestApp, progress_bar: ProgressBar) -> Entity {
+        let entity = app
+            .world_mut()
+            .spawn((
+                NodeBundle::default(),
+                ProgressBar {
+                    bar_color: BAR,
+                    background_color: BACKGROUND,
+                    ..progress_bar
+                },
+            ))
+            .id();
+        app.update();
+        entity
+    }
+
+    fn cells(app: &TestApp, entity: Entity) -> Vec<Entity> {
+        app.get::<ProgressBarParts>(entity).cells.clone()
+    }
+
+    /// Which cells are drawn in the bar color.
+    fn lit(app: &TestApp, entity: Entity) -> Vec<bool> {
+        cells(app, entity)
+            .into_iter()
+            .map(|cell| app.get::<BackgroundColor>(cell).0 == BAR)
+            .collect()
+    }
+
+    fn percent(value: Val) -> f32 {
+        let Val::Percent(percent) = value else {
+            panic!("{value:?} is not a percentage");
+        };
+        percent
+    }
+
+    #[test]
+    fn solid_bar_fills_up_to_the_value() {
+        let mut app = TestApp::new(ProgressBarPlugin);
+        let entity = spawn(
+            &mut app,
+            ProgressBar {
+                value: 25.0,
+                label: ProgressLabel::Percent,
+                ..default()
+            },
+        );
+        let fill = cells(&app, entity)[0];
+        assert_eq!(app.get::<Style>(fill).width, Val::Percent(25.0));
+        let background = app.get::<ProgressBarParts>(entity).background;
+        assert_eq!(app.get::<BackgroundColor>(background).0, BACKGROUND);
+
+        app.get_mut::<ProgressBar>(entity).value = 50.0;
+        app.update();
+        assert_eq!(app.get::<Style>(fill).width, Val::Percent(50.0));
+        let label = app.get::<ProgressBarParts>(entity).label.unwrap();
+        assert_eq!(app.get::<Text>(label).sections[0].value, "50%");
+    }
+
+    #[test]
+    fn vertical_bar_fills_from_the_bottom() {
+        let mut app = TestApp::new(ProgressBarPlugin);
+        let entity = spawn(
+            &mut app,
+            ProgressBar {
+                value: 40.0,
+                orientation: ProgressOrientation::Vertical,
+                ..default()
+            },
+        );
+        let fill = cells(&app, entity)[0];
+        let style = app.get::<Style>(fill);
+        assert_eq!(style.bottom, Val::Percent(0.0));
+        assert_eq!(style.height, Val::Percent(40.0));
+    }
+
+    #[test]
+    fn segmented_bar_lights_the_segments_in_order() {
+        let mut app = TestApp::new(ProgressBarPlugin);
+        let entity = spawn(
+            &mut app,
+            ProgressBar {
+                value: 50.0,
+                variant: ProgressBarVariant::Segmented { count: 4, gap: 2.0 },
+                ..default()
+            },
+        );
+        assert_eq!(lit(&app, entity), [true, true, false, false]);
+
+        app.get_mut::<ProgressBar>(entity).value = 80.0;
+        app.update();
+        assert_eq!(lit(&app, entity), [true, true, true, false]);
+    }
+
+    #[test]
+    fn radial_bar_lights_the_dots_clockwise_from_the_top() {
+        let mut app = TestApp::new(ProgressBarPlugin);
+        let entity = spawn(
+            &mut app,
+            ProgressBar {
+                value: 25.0,
+                size: Vec2::splat(100.0),
+                variant: ProgressBarVariant::Radial { segments: 8 },
+                ..default()
+            },
+        );
+        assert_eq!(
+            lit(&app, entity),
+            [true, true, false, false, false, false, false, false]
+        );
+        // 第一个点在正上方，第三个在右边
+        let dots = cells(&app, entity);
+        assert_eq!(app.get::<Style>(dots[0]).top, Val::Px(0.0));
+        assert_eq!(app.get::<Style>(dots[2]).left, Val::Px(88.0));
+    }
+
+    #[test]
+    fn changing_the_size_rebuilds_the_parts() {
+        let mut app = TestApp::new(ProgressBarPlugin);
+        let entity = spawn(&mut app, ProgressBar::default());
+        let old = app.get::<ProgressBarParts>(entity).background;
+
+        app.get_mut::<ProgressBar>(entity).size = Vec2::new(300.0, 30.0);
+        app.update();
+        let background = app.get::<ProgressBarParts>(entity).background;
+        assert_ne!(background, old);
+        assert!(app.world().get_entity(old).is_none());
+        let style = app.get::<Style>(background);
+        assert_eq!((style.width, style.height), (Val::Px(300.0), Val::Px(30.0)));
+    }
+
+    #[test]
+    fn indeterminate_bar_sweeps_and_hides_the_percentage() {
+        let mut app = TestApp::new(ProgressBarPlugin);
+        let entity = spawn(
+            &mut app,
+            ProgressBar {
+                value: 50.0,
+                indeterminate: true,
+                label: ProgressLabel::Percent,
+                ..default()
+            },
+        );
+        let fill = cells(&app, entity)[0];
+        app.step(30);
+        let left = percent(app.get::<Style>(fill).left);
+        let width = percent(app.get::<Style>(fill).width);
+        assert!(left > 0.0);
+        assert!((width - INDETERMINATE_LEN * 100.0).abs() < 0.01);
+
+        app.step(10);
+        assert!(percent(app.get::<Style>(fill).left) > left);
+        let label = app.get::<ProgressBarParts>(entity).label.unwrap();
+        assert_eq!(app.get::<Text>(label).sections[0].value, "");
+    }
+
+    #[test]
+    fn animated_bar_eases_to_its_target() {
+        let mut app = TestApp::new(ProgressBarPlugin);
+        let entity = spawn(&mut app, ProgressBar::default());
+        app.world_mut()
+            .entity_mut(entity)
+            .insert(AnimatedProgressBar {
+                target: 75.0,
+                speed: 25.0,
+            });
+
+        app.step(60);
+        let value = app.get::<ProgressBar>(entity).value;
+        assert!((value - 25.0).abs() < 1.0, "{value}");
+        let fill = cells(&app, entity)[0];
+        assert!((percent(app.get::<Style>(fill).width) - value).abs() < 0.01);
+
+        app.step(180);
+        assert_eq!(app.get::<ProgressBar>(entity).value, 75.0);
+    }
+}
@@ -0,0 +1,269 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::fs;
+
+use bevy::core::FrameCount;
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::text::FontLoader;
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::ExitCondition;
+use serde::{Deserialize, Serialize};
+
+use crate::core::event_bus::{CommandBus, RunCommand};
+use crate::core::story::StoryState;
+use crate::global_def::global_define::GameConfig;
+use crate::plugins::game_state::GameState;
+use crate::plugins::loading::ChapterLoader;
+use crate::plugins::scene_play::{PlayerAction, PlayerInputSet};
+
+/// Command line flag recording the player actions of the session to a file,
+/// e.g. `--record ./bug.toml`.
+pub const RECORD_CLI_FLAG: &str = "--record";
+
+/// Command line flag replaying a recording without a window, e.g.
+/// `--replay ./bug.toml`. The process fails when the story ends in a
+/// different state than the recorded one.
+pub const REPLAY_CLI_FLAG: &str = "--replay";
+
+/// How long a replay may run before it is considered stuck, in frames.
+const MAX_REPLAY_FRAMES: u32 = 60 * 60 * 10;
+
+/// The time every frame of a headless app advances by.
+pub const HEADLESS_FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// A Bevy Plugin recording the [`PlayerAction`]s of a session, or replaying them.
+///
+/// A recording holds every action with the frame it happened in and the
+/// [`StoryState`] the session ended in. Replaying feeds the actions back in
+/// order, each once the story waits for the player and its frame has been
+/// reached, then compares the final state and exits with success or failure.
+/// Replays start in [`GameState::InGame`], skipping the title screen. Use
+/// [`headless_app`] to replay without a window.
+pub enum ReplayPlugin {
+    /// Records to the file, written when the app exits.
+    Record(PathBuf),
+    Replay(InputRecording),
+}
+
+/// One player action of a recording.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedAction {
+    pub frame: u32,
+    pub action: PlayerAction,
+}
+
+/// The player actions of a session and the state the story ended in.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputRecording {
+    pub actions: Vec<RecordedAction>,
+    /// Not checked when missing, e.g. in a hand written recording.
+    pub final_state: Option<StoryState>,
+}
+
+impl InputRecording {
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+impl ReplayPlugin {
+    /// Reads [`RECORD_CLI_FLAG`] or [`REPLAY_CLI_FLAG`] from the command line.
+    ///
+    /// Returns `Ok(None)` when neither is given, and an error when the flag
+    /// has no path or the recording to replay can not be loaded.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg != RECORD_CLI_FLAG && arg != REPLAY_CLI_FLAG {
+                continue;
+            }
+            let Some(path) = args.next().map(PathBuf::from) else {
+                return Err(format!("{arg} needs a file path"));
+            };
+            if arg == RECORD_CLI_FLAG {
+                return Ok(Some(ReplayPlugin::Record(path)));
+            }
+            return InputRecording::load(&path)
+                .map(|recording| Some(ReplayPlugin::Replay(recording)))
+                .map_err(|err| format!("can not load recording {}: {}", path.display(), err));
+        }
+        Ok(None)
+    }
+}
+
+/// Builds an app that runs the story without a window, rendering, audio or
+/// logging.
+///
+/// Every frame advances the clock by a fixed 1/60 s, so timers behave the
+/// same on every run no matter how fast the frames are. Add the story
+/// plugins, e.g. `LayoutPlugin` and `ScenePlayPlugin`, to it.
+pub fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        ImagePlugin::default(),
+        InputPlugin,
+        StatesPlugin,
+        HierarchyPlugin,
+        TransformPlugin,
+        WindowPlugin {
+            primary_window: None,
+            exit_condition: ExitCondition::DontExit,
+            close_when_requested: false,
+        },
+    ))
+    .insert_resource(TimeUpdateStrategy::ManualDuration(HEADLESS_FRAME_TIME))
+    // 没有渲染和界面插件时，补上插件依赖的资源和资产类型
+    .init_asset::<Font>()
+    .init_asset_loader::<FontLoader>()
+    .init_resource::<UiScale>()
+    .init_resource::<GameConfig>();
+    app
+}
+
+#[derive(Resource)]
+struct Recorder {
+    path: PathBuf,
+    recording: InputRecording,
+}
+
+#[derive(Resource)]
+struct Replayer {
+    recording: InputRecording,
+    next: usize,
+    /// Set once the story ran its first command.
+    started: bool,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        match self {
+            ReplayPlugin::Record(path) => {
+                app.insert_resource(Recorder {
+                    path: path.clone(),
+                    recording: InputRecording::default(),
+                })
+                .add_systems(Update, record_actions.after(PlayerInputSet::Apply))
+                .add_systems(Last, save_recording);
+            }
+            ReplayPlugin::Replay(recording) => {
+                app.insert_resource(Replayer {
+                    recording: recording.clone(),
+                    next: 0,
+                    started: false,
+                })
+                .add_systems(Startup, start_story)
+                .add_systems(
+                    Update,
+                    (
+                        feed_actions.in_set(PlayerInputSet::Read),
+                        finish_replay.after(PlayerInputSet::Apply),
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn start_story(next_state: Option<ResMut<NextState<GameState>>>) {
+    if let Some(mut next_state) = next_state {
+        next_state.set(GameState::InGame);
+    }
+}
+
+fn record_actions(
+    frame: Res<FrameCount>,
+    mut actions: EventReader<PlayerAction>,
+    mut recorder: ResMut<Recorder>,
+) {
+    for action in actions.read() {
+        recorder.recording.actions.push(RecordedAction {
+            frame: frame.0,
+            action: *action,
+        });
+    }
+}
+
+fn save_recording(
+    mut exit_events: EventReader<AppExit>,
+    state: Res<StoryState>,
+    mut recorder: ResMut<Recorder>,
+) {
+    if exit_events.read().last().is_none() {
+        return;
+    }
+    recorder.recording.final_state = Some(state.clone());
+    match recorder.recording.save(&recorder.path) {
+        Ok(()) => info!("recording saved to {}", recorder.path.display()),
+        Err(err) => error!("can not save recording to {}: {}", recorder.path.display(), err),
+    }
+}
+
+/// Sends the next recorded action once the story waits for the player.
+///
+/// Only one action is sent per frame, so each one is applied before the
+/// next is looked at.
+fn feed_actions(
+    frame: Res<FrameCount>,
+    loader: Res<ChapterLoader>,
+    bus: Res<CommandBus>,
+    mut replayer: ResMut<Replayer>,
+    mut actions: EventWriter<PlayerAction>,
+) {
+    let Some(next) = replayer.recording.actions.get(replayer.next) else {
+        return;
+    };
+    if loader.is_loading() || !bus.is_waiting_for_player() || frame.0 < next.frame {
+        return;
+    }
+    actions.send(next.action);
+    replayer.next += 1;
+}
+
+/// Exits once every action was replayed and the story settled, comparing
+/// the final state with the recorded one.
+fn finish_replay(
+    frame: Res<FrameCount>,
+    loader: Res<ChapterLoader>,
+    bus: Res<CommandBus>,
+    state: Res<StoryState>,
+    mut run_events: EventReader<RunCommand>,
+    mut replayer: ResMut<Replayer>,
+    mut exit_events: EventWriter<AppExit>,
+) {
+    if run_events.read().last().is_some() {
+        replayer.started = true;
+    }
+    if frame.0 > MAX_REPLAY_FRAMES {
+        error!(
+            "replay stuck after {} of {} actions",
+            replayer.next,
+            replayer.recording.actions.len()
+        );
+        exit_events.send(AppExit::error());
+        return;
+    }
+
+    let settled = !loader.is_loading() && (bus.is_idle() || bus.is_waiting_for_player());
+    if !replayer.started || replayer.next < replayer.recording.actions.len() || !settled {
+        return;
+    }
+    match &replayer.recording.final_state {
+        Some(expected) if *expected != *state => {
+            error!("replay ended in a different state\nexpected: {expected:#?}\nactual: {:#?}", *state);
+            exit_events.send(AppExit::error());
+        }
+        _ => {
+            info!("replay finished after {} actions", replayer.next);
+            exit_events.send(AppExit::Success);
+        }
+    }
+}
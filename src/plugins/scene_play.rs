@@ -1,32 +1,112 @@
+use bevy::asset::io::file::FileAssetReader;
 use bevy::asset::AssetServer;
 use bevy::color::Color;
-use bevy::math::{Vec2, Vec3};
+use bevy::math::Vec3;
 use bevy::prelude::Val::Px;
 use bevy::prelude::*;
 use bevy::text::BreakLineOn;
 use bevy_kira_audio::prelude::*;
+use serde::{Deserialize, Serialize};
 
+use crate::core::catalogue::AssetCatalogue;
 use crate::core::event_bus::*;
+use crate::core::locale::{speaker_id, Locale, LocalizedText};
+use crate::core::script::Script;
+use crate::core::story::{StoryHistory, StorySnapshot, StoryState};
 use crate::utils::string_utils::string_auto_split;
 use bevy::time::Timer;
-use crate::prelude::GameConfig;
+use std::time::Duration;
+use crate::global_def::global_define::GameConfig;
+use crate::plugins::character_layers::{CharacterAtlas, CharacterLayers};
+use crate::plugins::focus::{gamepad_just_pressed, Focus, FocusPlugin, FocusScope};
+use crate::plugins::game_state::{GameState, GameStatePlugin, MenuState};
+use crate::plugins::image_variant::{ActiveVariant, ImageVariantPlugin, VariantImage};
+use crate::plugins::layout::{BackgroundFit, UiRoot, DESIGN_RESOLUTION};
+use crate::plugins::loading::{ChapterLoader, ChapterReady, LoadChapter, LoadingPlugin};
 
 pub struct ScenePlayPlugin;
 
-#[derive(Clone)]
-struct ScenePlayStage {
-    id: u32,
-    background_path: &'static str,
-    character_path: &'static str,
-    music_path: &'static str,
+/// The script the story starts with, relative to the asset folder.
+pub const FIRST_SCRIPT: &str = "scripts/prologue.avg";
+
+/// Where the story starts when the game enters [`GameState::InGame`].
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub enum StoryStart {
+    /// From the beginning of a script, relative to the asset folder, with
+    /// the persistent variables set.
+    Script(String),
+    /// From a snapshot, e.g. a save slot of the [`StoryHistory`].
+    Snapshot(StorySnapshot),
 }
 
-/// A resource that holds a handle to an audio instance for controlling playback.
+impl Default for StoryStart {
+    fn default() -> Self {
+        StoryStart::Script(FIRST_SCRIPT.to_string())
+    }
+}
+
+/// The chapter being played: its script, run once its assets are loaded.
 #[derive(Resource)]
-struct BgmHandle(Handle<AudioInstance>);
+struct Chapter {
+    name: String,
+    script: Script,
+}
+
+impl Chapter {
+    /// Starts loading every asset the chapter references, each path once.
+    ///
+    /// Asset ids are looked up in the catalogue. Music and voices are
+    /// skipped when the app runs without audio.
+    fn load_assets(
+        &self,
+        asset_server: &AssetServer,
+        catalogue: Option<&AssetCatalogue>,
+        variant: &ActiveVariant,
+        audio: bool,
+    ) -> Vec<UntypedHandle> {
+        let mut paths: Vec<&str> = Vec::new();
+        let mut assets = Vec::new();
+        for command in self.script.commands() {
+            let Some(path) = command.asset_path() else {
+                continue;
+            };
+            let sound = matches!(
+                command,
+                VnCommand::PlayMusic { .. } | VnCommand::PlayVoice { .. }
+            );
+            let layered = matches!(command, VnCommand::ShowCharacter { layers, .. } if !layers.is_empty());
+            let path = match catalogue {
+                Some(catalogue) if sound || layered => catalogue.resolve(path),
+                _ => variant.resolve(catalogue, path),
+            };
+            if sound && !audio {
+                continue;
+            }
+            if paths.contains(&path) {
+                continue;
+            }
+            paths.push(path);
+            let handle = if sound {
+                asset_server.load::<bevy_kira_audio::AudioSource>(path.to_string()).untyped()
+            } else if layered {
+                asset_server.load::<CharacterAtlas>(path.to_string()).untyped()
+            } else {
+                asset_server.load::<Image>(path.to_string()).untyped()
+            };
+            assets.push(handle);
+        }
+        assets
+    }
+}
 
+const DIALOGUE_FONT_SIZE: f32 = 35.0;
+
+/// The width dialogue lines are wrapped to, inside the dialogue box padding.
+const DIALOGUE_TEXT_WIDTH: f32 = DESIGN_RESOLUTION.x * 0.98;
+
+/// A resource that holds a handle to an audio instance for controlling playback.
 #[derive(Resource)]
-struct BgImageHandle(Handle<Image>);
+struct BgmHandle(Handle<AudioInstance>);
 
 // #[derive(Resource)]
 // struct CharacterHandle(Handle<Image>);
@@ -46,6 +126,14 @@ pub struct TypingText {
     pub(crate) timer: Timer,
 }
 
+impl TypingText {
+    /// Whether every character of `full_text` is displayed.
+    pub fn is_finished(&self) -> bool {
+        // current_index 按字符计数，不能和字节长度比较
+        self.current_index >= self.full_text.chars().count()
+    }
+}
+
 #[derive(Component)]
 struct CharacterComponent {
     path: &'static str,
@@ -65,40 +153,168 @@ pub struct BackgroundControl;
 #[derive(Component)]
 pub struct CharacterControl;
 
+/// The buttons marked with `T` whose interaction changed.
+type ControlButtons<'w, 's, T> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static Children),
+    (Changed<Interaction>, With<T>),
+>;
+
+/// The menu of a running [`VnCommand::Choice`].
+#[derive(Component)]
+struct ChoiceMenu;
+
+/// A button of a [`ChoiceMenu`], holding the index of its option.
+#[derive(Component)]
+struct ChoiceOption(usize);
+
+/// Something the player did to move the story along.
+///
+/// Input is turned into actions only while the story waits for the player,
+/// so a recorded sequence of actions replays the same story.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayerAction {
+    /// Moves past a fully typed line.
+    Advance,
+    /// Moves past a line right away, even while it is still typing.
+    Skip,
+    /// Picks an option of the open choice.
+    Choose(usize),
+    /// Goes back to the previous line or choice.
+    Rollback,
+    Save(u32),
+    Load(u32),
+}
+
+/// Where player input is turned into [`PlayerAction`]s and where they are
+/// applied, for systems sending or recording actions.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PlayerInputSet {
+    Read,
+    Apply,
+}
+
+/// The slot quick save and quick load use.
+const QUICK_SLOT: u32 = 0;
+
 impl Plugin for ScenePlayPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_entities)
-            .init_resource::<Events<GenericEvent<ScenePlayStage>>>()
-            .add_plugins(AudioPlugin)
+        if !app.is_plugin_added::<CommandBusPlugin>() {
+            app.add_plugins(CommandBusPlugin);
+        }
+        if !app.is_plugin_added::<ImageVariantPlugin>() {
+            app.add_plugins(ImageVariantPlugin);
+        }
+        if !app.is_plugin_added::<GameStatePlugin>() {
+            app.add_plugins(GameStatePlugin);
+        }
+        if !app.is_plugin_added::<FocusPlugin>() {
+            app.add_plugins(FocusPlugin);
+        }
+        app.add_event::<PlayerAction>()
+            .init_resource::<StoryStart>()
+            .configure_sets(Update, PlayerInputSet::Read.before(PlayerInputSet::Apply))
+            .add_systems(OnEnter(GameState::InGame), load_chapter)
+            .add_systems(OnExit(GameState::InGame), leave_chapter)
+            .add_plugins(LoadingPlugin)
             .add_systems(
                 Update,
                 (
-                    update_typing_text,
-                    control_music_play,
-                    control_character_play,
-                    control_background_play,
-                    event_sender_system,
-                    event_receiver_system,
+                    (
+                        spawn_entities,
+                        start_chapter.after(spawn_entities),
+                        run_scene_commands,
+                        run_music_commands,
+                        (
+                            run_dialogue_commands.before(PlayerInputSet::Read),
+                            relocalize_dialogue.after(run_dialogue_commands),
+                            // 打开菜单时故事暂停，点击交给菜单
+                            (read_player_input, read_choice_input)
+                                .chain()
+                                .in_set(PlayerInputSet::Read)
+                                .run_if(in_state(MenuState::Closed))
+                                .run_if(waits_for_player),
+                            apply_player_actions.in_set(PlayerInputSet::Apply),
+                            update_typing_text
+                                .after(PlayerInputSet::Apply)
+                                .run_if(in_state(MenuState::Closed)),
+                        ),
+                        control_music_play,
+                        control_character_play,
+                        control_background_play,
+                    )
+                        .run_if(in_state(GameState::InGame)),
+                    apply_config_settings,
                 ),
             );
     }
 }
 
-fn event_sender_system(
-    mut event_writer: EventWriter<GenericEvent<ScenePlayStage>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+/// Reads the script of the [`StoryStart`] and starts loading its assets,
+/// when the game enters the story.
+fn load_chapter(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    catalogue: Option<Res<AssetCatalogue>>,
+    variant: Res<ActiveVariant>,
+    audio: Option<Res<Audio>>,
+    start: Res<StoryStart>,
+    mut load_events: EventWriter<LoadChapter>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Enter) {
-        let scene_event = GenericEvent {
-            data: ScenePlayStage {
-                id: 1,
-                background_path: "images/bg2_resized.png",
-                character_path: "images/ch5.png",
-                music_path: "images/music1.mp3",
-            },
-        };
-        event_writer.send(scene_event);
-        println!("GenericEvent<ScenePlayStage> sent!");
+    let root = match catalogue.as_deref() {
+        Some(catalogue) => catalogue.root().to_path_buf(),
+        None => FileAssetReader::new("assets").root_path().clone(),
+    };
+    let name = match &*start {
+        StoryStart::Script(name) => name.clone(),
+        // 快照记录了所在的脚本
+        StoryStart::Snapshot(snapshot) => snapshot
+            .state
+            .script
+            .clone()
+            .unwrap_or_else(|| FIRST_SCRIPT.to_string()),
+    };
+    let script = match Script::load(&root.join(&name)) {
+        Ok(script) => script,
+        Err(err) => {
+            error!("can not load {}: {}", name, err);
+            return;
+        }
+    };
+    let chapter = Chapter { name, script };
+    load_events.send(LoadChapter {
+        name: chapter.name.clone(),
+        assets: chapter.load_assets(
+            &asset_server,
+            catalogue.as_deref(),
+            &variant,
+            audio.is_some(),
+        ),
+    });
+    commands.insert_resource(chapter);
+}
+
+/// Stops the story and its music when the game leaves it. The scene is
+/// despawned with the state.
+fn leave_chapter(
+    mut commands: Commands,
+    mut bus: ResMut<CommandBus>,
+    mut state: ResMut<StoryState>,
+    mut history: ResMut<StoryHistory>,
+    audio_instances: Option<ResMut<Assets<AudioInstance>>>,
+    bgm: Option<Res<BgmHandle>>,
+) {
+    bus.clear();
+    *state = StoryState::default();
+    history.clear();
+    commands.remove_resource::<Chapter>();
+    commands.insert_resource(StoryStart::default());
+    if let (Some(bgm), Some(mut audio_instances)) = (bgm, audio_instances) {
+        if let Some(instance) = audio_instances.get_mut(&bgm.0) {
+            instance.stop(AudioTween::default());
+        }
+        commands.remove_resource::<BgmHandle>();
     }
 }
 
@@ -108,18 +324,16 @@ pub fn update_typing_text(
     mut query: Query<(&mut TypingText, &mut Text)>,
 ) {
     for (mut typing_text, mut text) in query.iter_mut() {
-        if input.just_pressed(MouseButton::Left)
-            && typing_text.current_index < typing_text.full_text.len()
-        {
+        if input.just_pressed(MouseButton::Left) && !typing_text.is_finished() {
             typing_text.displayed_text = typing_text.full_text.clone();
-            typing_text.current_index = typing_text.full_text.len();
+            typing_text.current_index = typing_text.full_text.chars().count();
 
             text.sections[0].value = typing_text.displayed_text.clone();
             return;
         }
 
         typing_text.timer.tick(time.delta());
-        if typing_text.timer.finished() && typing_text.current_index < typing_text.full_text.len() {
+        if typing_text.timer.finished() && !typing_text.is_finished() {
             let clone_text = typing_text.clone();
             let Some(update_text) = clone_text.full_text.chars().nth(clone_text.current_index)
             else {
@@ -132,89 +346,437 @@ pub fn update_typing_text(
     }
 }
 
-fn event_receiver_system(
+/// Applies the volume and text speed settings to the audio channel and all typing texts.
+fn apply_config_settings(
+    config: Res<GameConfig>,
+    audio: Option<Res<Audio>>,
+    mut typing_query: Query<&mut TypingText>,
+) {
+    if let Some(audio) = audio.filter(|_| config.is_changed()) {
+        audio.set_volume(config.master_volume as f64);
+    }
+
+    let duration = Duration::from_secs_f32(1.0 / config.text_speed.max(f32::EPSILON));
+    for mut typing_text in typing_query.iter_mut() {
+        if (config.is_changed() || typing_text.is_added()) && typing_text.timer.duration() != duration {
+            typing_text.timer.set_duration(duration);
+        }
+    }
+}
+
+/// Shows and hides the background and character for the commands run by
+/// the [`CommandBus`].
+///
+/// A character shown with layers is drawn from its atlas by the layered
+/// character entity, in place of the character sprite.
+fn run_scene_commands(
+    mut run_events: EventReader<RunCommand>,
     asset_server: Res<AssetServer>,
-    mut event_reader: EventReader<GenericEvent<ScenePlayStage>>,
-    mut background_query: Query<(
-        &mut Handle<Image>,
-        Option<&mut BackgroundComponent>,
-        Option<&mut CharacterComponent>,
+    mut image_query: Query<(
+        &mut VariantImage,
+        &mut Visibility,
+        Has<BackgroundComponent>,
+        Has<CharacterComponent>,
     )>,
+    mut layered_query: Query<(&mut CharacterLayers, &mut Visibility), Without<VariantImage>>,
+) {
+    for event in run_events.read() {
+        match &event.command {
+            VnCommand::ShowBackground { path } => {
+                for (mut image, mut visibility, is_background, _) in image_query.iter_mut() {
+                    if is_background {
+                        image.0.clone_from(path);
+                        *visibility = Visibility::Inherited;
+                    }
+                }
+            }
+            VnCommand::ShowCharacter { path, layers } => {
+                let layered = !layers.is_empty();
+                for (mut image, mut visibility, _, is_character) in image_query.iter_mut() {
+                    if !is_character {
+                        continue;
+                    }
+                    if layered {
+                        *visibility = Visibility::Hidden;
+                    } else {
+                        image.0.clone_from(path);
+                        *visibility = Visibility::Inherited;
+                    }
+                }
+                for (mut character, mut visibility) in layered_query.iter_mut() {
+                    if layered {
+                        *character = CharacterLayers::new(asset_server.load(path.clone()), layers.iter());
+                        *visibility = Visibility::Inherited;
+                    } else {
+                        *visibility = Visibility::Hidden;
+                    }
+                }
+            }
+            VnCommand::HideCharacter => {
+                for (_, mut visibility, _, is_character) in image_query.iter_mut() {
+                    if is_character {
+                        *visibility = Visibility::Hidden;
+                    }
+                }
+                for (_, mut visibility) in layered_query.iter_mut() {
+                    *visibility = Visibility::Hidden;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn run_music_commands(
+    mut commands: Commands,
+    mut run_events: EventReader<RunCommand>,
+    asset_server: Res<AssetServer>,
+    audio: Option<Res<Audio>>,
+    audio_instances: Option<ResMut<Assets<AudioInstance>>>,
+    bgm: Option<Res<BgmHandle>>,
 ) {
-    for (event, _) in event_reader.par_read() {
-        for (mut handle, background, character) in &mut background_query.iter_mut() {
-            if background.is_some() {
-                let path = event.data.background_path;
-                // background.unwrap().path = path.clone();
-                *handle = asset_server.load(path);
+    // 没有音频插件时（例如无窗口回放）忽略音乐命令
+    let (Some(audio), Some(mut audio_instances)) = (audio, audio_instances) else {
+        return;
+    };
+    for event in run_events.read() {
+        // 语音只播放一次，不影响背景音乐
+        if let VnCommand::PlayVoice { path } = &event.command {
+            audio.play(asset_server.load(path.clone()));
+            continue;
+        }
+        let (VnCommand::PlayMusic { .. } | VnCommand::StopMusic) = event.command else {
+            continue;
+        };
+        // 播放新音乐前先停止当前的背景音乐
+        if let Some(instance) = bgm.as_ref().and_then(|bgm| audio_instances.get_mut(&bgm.0)) {
+            instance.stop(AudioTween::default());
+        }
+        if let VnCommand::PlayMusic { path, looped } = &event.command {
+            let mut play = audio.play(asset_server.load(path.clone()));
+            if *looped {
+                play.looped();
             }
-            if character.is_some() {
-                let path = event.data.character_path;
-                // character.unwrap().path = path.clone();
-                *handle = asset_server.load(path);
+            commands.insert_resource(BgmHandle(play.handle()));
+        }
+    }
+}
+
+/// Starts typing the lines of [`VnCommand::Say`] and opens the menus of
+/// [`VnCommand::Choice`].
+fn run_dialogue_commands(
+    mut commands: Commands,
+    mut run_events: EventReader<RunCommand>,
+    asset_server: Res<AssetServer>,
+    ui_root: Res<UiRoot>,
+    state: Res<StoryState>,
+    locale: Option<Res<Locale>>,
+    mut typing_query: Query<(&mut TypingText, &mut Text)>,
+) {
+    for event in run_events.read() {
+        match &event.command {
+            VnCommand::Say { speaker, text, id } => {
+                let text = dialogue_text(locale.as_deref(), speaker.as_deref(), text, id.as_deref());
+                for (mut typing_text, mut display) in typing_query.iter_mut() {
+                    typing_text.full_text = text.clone();
+                    typing_text.displayed_text.clear();
+                    typing_text.current_index = 0;
+                    display.sections[0].value.clear();
+                }
             }
-            {}
+            VnCommand::Choice {
+                options,
+                conditions,
+                ids,
+                ..
+            } => {
+                // 条件不满足的选项不显示
+                let shown: Vec<(usize, &str, Option<&str>)> = options
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| is_option_shown(conditions, *index, &state.variables))
+                    .map(|(index, option)| (index, option.as_str(), ids.get(index).map(String::as_str)))
+                    .collect();
+                spawn_choice_menu(&mut commands, &asset_server, ui_root.0, &shown);
+            }
+            _ => {}
         }
     }
 }
 
+/// The line shown for a [`VnCommand::Say`] in the language of `locale`,
+/// wrapped to the dialogue box.
+fn dialogue_text(locale: Option<&Locale>, speaker: Option<&str>, text: &str, id: Option<&str>) -> String {
+    let (speaker, text) = match locale {
+        Some(locale) => (
+            speaker.map(|speaker| locale.text(&speaker_id(speaker), speaker)),
+            id.map_or(text, |id| locale.text(id, text)),
+        ),
+        None => (speaker, text),
+    };
+    let text = match speaker {
+        Some(speaker) => format!("【{speaker}】\n{text}"),
+        None => text.to_string(),
+    };
+    string_auto_split(&text, DIALOGUE_TEXT_WIDTH, DIALOGUE_FONT_SIZE as usize)
+}
+
+/// Shows the line being read in the new language when the language changes,
+/// keeping as much of it typed as before.
+fn relocalize_dialogue(
+    locale: Option<Res<Locale>>,
+    bus: Res<CommandBus>,
+    mut typing_query: Query<(&mut TypingText, &mut Text)>,
+) {
+    let Some(locale) = locale.filter(|locale| locale.is_changed() && !locale.is_added()) else {
+        return;
+    };
+    let Some((_, VnCommand::Say { speaker, text, id })) = bus.active() else {
+        return;
+    };
+    let full_text = dialogue_text(Some(&locale), speaker.as_deref(), text, id.as_deref());
+    for (mut typing_text, mut display) in typing_query.iter_mut() {
+        let length = full_text.chars().count();
+        let typed = if typing_text.is_finished() {
+            length
+        } else {
+            typing_text.current_index.min(length)
+        };
+        typing_text.full_text.clone_from(&full_text);
+        typing_text.current_index = typed;
+        typing_text.displayed_text = full_text.chars().take(typed).collect();
+        display.sections[0].value.clone_from(&typing_text.displayed_text);
+    }
+}
+
+/// Whether the story waits for the player, and not for its assets.
+fn waits_for_player(loader: Res<ChapterLoader>, bus: Res<CommandBus>) -> bool {
+    !loader.is_loading() && bus.is_waiting_for_player()
+}
+
+/// Turns clicks, keys and gamepad buttons into [`PlayerAction`]s while the
+/// story waits for the player.
+///
+/// Enter and A only advance while no node has the focus, as they press the
+/// focused one instead.
+fn read_player_input(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_input: Res<ButtonInput<GamepadButton>>,
+    focus: Res<Focus>,
+    bus: Res<CommandBus>,
+    typing_query: Query<&TypingText>,
+    mut actions: EventWriter<PlayerAction>,
+) {
+    if let Some((_, VnCommand::Say { .. })) = bus.active() {
+        // 文字还在逐字显示时，点击交给 update_typing_text 直接显示全文
+        let typing = typing_query.iter().any(|typing_text| !typing_text.is_finished());
+        let confirm = keyboard_input.just_pressed(KeyCode::Enter)
+            || gamepad_just_pressed(&gamepad_input, GamepadButtonType::South);
+        if keyboard_input.any_just_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+            actions.send(PlayerAction::Skip);
+        } else if !typing
+            && (mouse_input.just_pressed(MouseButton::Left) || (confirm && focus.get().is_none()))
+        {
+            actions.send(PlayerAction::Advance);
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Backspace) {
+        actions.send(PlayerAction::Rollback);
+    }
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        actions.send(PlayerAction::Save(QUICK_SLOT));
+    }
+    if keyboard_input.just_pressed(KeyCode::F9) {
+        actions.send(PlayerAction::Load(QUICK_SLOT));
+    }
+}
+
+/// Turns the pressed buttons of the open choice into [`PlayerAction`]s.
+fn read_choice_input(
+    option_query: Query<(&Interaction, &ChoiceOption), Changed<Interaction>>,
+    mut actions: EventWriter<PlayerAction>,
+) {
+    for (interaction, option) in option_query.iter() {
+        if *interaction == Interaction::Pressed {
+            actions.send(PlayerAction::Choose(option.0));
+        }
+    }
+}
+
+fn apply_player_actions(
+    mut commands: Commands,
+    mut actions: EventReader<PlayerAction>,
+    mut bus: ResMut<CommandBus>,
+    mut state: ResMut<StoryState>,
+    mut history: ResMut<StoryHistory>,
+    mut typing_query: Query<(&mut TypingText, &mut Text)>,
+    menu_query: Query<Entity, With<ChoiceMenu>>,
+) {
+    for action in actions.read() {
+        let active = bus.active().map(|(id, command)| (id, command.clone()));
+        let leaves_choice = match (*action, active) {
+            (PlayerAction::Advance | PlayerAction::Skip, Some((id, VnCommand::Say { .. }))) => {
+                for (mut typing_text, mut text) in typing_query.iter_mut() {
+                    typing_text.current_index = typing_text.full_text.chars().count();
+                    typing_text.displayed_text = typing_text.full_text.clone();
+                    text.sections[0].value = typing_text.displayed_text.clone();
+                }
+                bus.complete(id);
+                false
+            }
+            (
+                PlayerAction::Choose(index),
+                Some((
+                    id,
+                    VnCommand::Choice {
+                        options,
+                        variable,
+                        conditions,
+                        ..
+                    },
+                )),
+            ) => {
+                if index >= options.len() || !is_option_shown(&conditions, index, &state.variables) {
+                    continue;
+                }
+                state.variables.insert(variable, VnValue::Int(index as i64));
+                bus.complete(id)
+            }
+            (PlayerAction::Rollback, _) => history.rollback(&mut bus, &mut state),
+            (PlayerAction::Save(slot), _) => {
+                history.save(slot);
+                false
+            }
+            (PlayerAction::Load(slot), _) => history.load(slot, &mut bus, &mut state),
+            _ => false,
+        };
+        if leaves_choice {
+            for menu in menu_query.iter() {
+                commands.entity(menu).despawn_recursive();
+            }
+        }
+    }
+}
+
+fn spawn_choice_menu(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    ui_root: Entity,
+    options: &[(usize, &str, Option<&str>)],
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    commands.entity(ui_root).with_children(|root| {
+        root.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(70.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Px(20.0),
+                    ..default()
+                },
+                ..default()
+            },
+            Name::new("choice_menu"),
+            ChoiceMenu,
+            FocusScope,
+            StateScoped(GameState::InGame),
+        ))
+        .with_children(|menu| {
+            for &(index, option, id) in options {
+                menu.spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Px(400.0),
+                            height: Px(50.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        border_radius: BorderRadius::MAX,
+                        background_color: Color::srgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    },
+                    ChoiceOption(index),
+                ))
+                .with_children(|button| {
+                    let mut text = button.spawn(TextBundle::from_section(
+                        option,
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 24.0,
+                            color: Color::srgb(0.9, 0.9, 0.9),
+                        },
+                    ));
+                    if let Some(id) = id {
+                        text.insert(LocalizedText::new(id, option));
+                    }
+                });
+            }
+        });
+    });
+}
+
 fn control_music_play(
-    mut audio_instances: ResMut<Assets<AudioInstance>>,
-    handle: Res<BgmHandle>,
-    mut text_query: Query<&mut Text>,
-    mut button_query: Query<
-        (&Interaction, &Children),
-        (Changed<Interaction>, With<AudioPlayControl>),
-    >,
+    mut commands: Commands,
+    audio_instances: Option<ResMut<Assets<AudioInstance>>>,
+    handle: Option<Res<BgmHandle>>,
+    text_query: Query<(), With<Text>>,
+    mut button_query: ControlButtons<AudioPlayControl>,
 ) {
+    // 章节加载完成前还没有背景音乐
+    let (Some(handle), Some(mut audio_instances)) = (handle, audio_instances) else {
+        return;
+    };
     for (button, children) in button_query.iter_mut() {
-        match button {
-            Interaction::Pressed => {
-                println!("audio ctl button is pressed");
-                if let Some(instance) = audio_instances.get_mut(&handle.0) {
-                    match instance.state() {
-                        PlaybackState::Playing { .. } => {
-                            instance.pause(AudioTween::default());
-                            for &child in children.iter() {
-                                if let Ok(mut text) = text_query.get_mut(child) {
-                                    text.sections[0].value = "StopPlay".to_string();
-                                    // 修改文本
-                                }
-                            }
+        if *button != Interaction::Pressed {
+            continue;
+        }
+        println!("audio ctl button is pressed");
+        if let Some(instance) = audio_instances.get_mut(&handle.0) {
+            match instance.state() {
+                PlaybackState::Playing { .. } => {
+                    instance.pause(AudioTween::default());
+                    for &child in children.iter() {
+                        if text_query.contains(child) {
+                            // 修改文本，由 LocalePlugin 翻译
+                            commands
+                                .entity(child)
+                                .insert(LocalizedText::new("ui.stop_play", "StopPlay"));
                         }
-                        PlaybackState::Paused { .. } => {
-                            instance.resume(AudioTween::default());
-                            for &child in children.iter() {
-                                if let Ok(mut text) = text_query.get_mut(child) {
-                                    text.sections[0].value = "AudioPlay".to_string();
-                                    // 修改文本
-                                }
-                            }
+                    }
+                }
+                PlaybackState::Paused { .. } => {
+                    instance.resume(AudioTween::default());
+                    for &child in children.iter() {
+                        if text_query.contains(child) {
+                            commands
+                                .entity(child)
+                                .insert(LocalizedText::new("ui.audio_play", "AudioPlay"));
                         }
-                        _ => {}
                     }
                 }
+                _ => {}
             }
-            _ => {}
         }
     }
 }
 
 fn control_background_play(
-    asset_server: Res<AssetServer>,
-    mut entity_query: Query<(&mut Handle<Image>, &BackgroundComponent)>,
-    mut button_query: Query<
-        (&Interaction, &Children),
-        (Changed<Interaction>, With<BackgroundControl>),
-    >,
+    mut entity_query: Query<(&mut VariantImage, &BackgroundComponent)>,
+    mut button_query: ControlButtons<BackgroundControl>,
 ) {
     for (interaction, _) in button_query.iter_mut() {
         match interaction {
             Interaction::Pressed => {
                 println!("background pressed button");
-                for (mut handle, background) in &mut entity_query.iter_mut() {
-                    let path = background.path;
-                    *handle = asset_server.load(path);
+                for (mut image, background) in &mut entity_query.iter_mut() {
+                    image.0 = background.path.to_string();
                 }
             }
             Interaction::Hovered => {}
@@ -224,20 +786,15 @@ fn control_background_play(
 }
 
 fn control_character_play(
-    asset_server: Res<AssetServer>,
-    mut entity_query: Query<(&mut Handle<Image>, &CharacterComponent)>,
-    mut button_query: Query<
-        (&Interaction, &Children),
-        (Changed<Interaction>, With<CharacterControl>),
-    >,
+    mut entity_query: Query<(&mut VariantImage, &CharacterComponent)>,
+    mut button_query: ControlButtons<CharacterControl>,
 ) {
     for (interaction, _) in button_query.iter_mut() {
         match interaction {
             Interaction::Pressed => {
                 println!("character pressed button");
-                for (mut handle, character) in &mut entity_query.iter_mut() {
-                    let path = character.path;
-                    *handle = asset_server.load(path);
+                for (mut image, character) in &mut entity_query.iter_mut() {
+                    image.0 = character.path.to_string();
                 }
             }
             Interaction::Hovered => {}
@@ -246,63 +803,88 @@ fn control_character_play(
     }
 }
 
-fn spawn_entities(mut commands: Commands, asset_server: Res<AssetServer>, audio: Res<Audio>,config:Res<GameConfig>) {
+/// Spawns the scene once the chapter's assets are loaded.
+fn spawn_entities(
+    mut commands: Commands,
+    mut ready_events: EventReader<ChapterReady>,
+    asset_server: Res<AssetServer>,
+    ui_root: Res<UiRoot>,
+    chapter: Option<Res<Chapter>>,
+) {
+    let Some(chapter) = chapter else {
+        return;
+    };
+    if !ready_events.read().any(|event| event.name == chapter.name) {
+        return;
+    }
     let font = asset_server.load("fonts/zfft.ttf");
-    let background_handle = asset_server.load("images/bg2_resized.png");
-    let character_handle = asset_server.load("images/ch5.png");
-
-    commands.spawn(Camera2dBundle::default());
 
+    // 背景和立绘由脚本命令显示
     commands
         .spawn(SpriteBundle {
-            texture: background_handle,
             transform: Transform {
                 translation: Vec3::new(0.0, 0.0, 0.0), // Position it at the center
                 ..Default::default()
             },
+            visibility: Visibility::Hidden,
             ..Default::default()
         })
         .insert(BackgroundComponent {
             path: "images/bg1_resized.png",
-        });
+        })
+        .insert(VariantImage::default())
+        .insert(BackgroundFit::Cover)
+        .insert(StateScoped(GameState::InGame));
 
+    let character_position = Vec3::new(-(DESIGN_RESOLUTION.x / 4f32), 0.0, 1.0);
     commands
         .spawn(SpriteBundle {
-            texture: character_handle,
-            transform: Transform::from_translation(Vec3::new(
-                -(config.resolution.0 / 4f32),
-                0.0,
-                1.0,
-            )),
+            transform: Transform::from_translation(character_position),
+            visibility: Visibility::Hidden,
             ..Default::default()
         })
         .insert(CharacterComponent {
             path: "images/ch4.png",
-        });
+        })
+        .insert(VariantImage::default())
+        .insert(StateScoped(GameState::InGame));
+
+    // 按图层显示的立绘
+    commands.spawn((
+        SpatialBundle {
+            transform: Transform::from_translation(character_position),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        CharacterLayers::default(),
+        Name::new("layered_character"),
+        StateScoped(GameState::InGame),
+    ));
 
     let slightly_smaller_text_style = TextStyle {
         font,
-        font_size: 35.0,
+        font_size: DIALOGUE_FONT_SIZE,
         ..default()
     };
-    let box_size = Vec2::new(config.resolution.0, config.resolution.1 * 0.3);
-    let box_position = Vec2::new(0.0, -config.resolution.1 / 4f32);
-    let box_text_position = Vec2::new(-config.resolution.0 / 3f32, 0.0);
+    let padding = DESIGN_RESOLUTION.x * 0.01;
 
-    commands
-        .spawn(NodeBundle {
-            style: Style {
-                display: Display::Flex,
-                width: Val::Percent(box_size.x),
-                height: Val::Percent(box_size.y),
-                padding: UiRect::all(Px(config.resolution.0 * 0.01)),
-                top: Px(config.resolution.1 * 0.7),
+    commands.entity(ui_root.0).with_children(|root| {
+        root.spawn((
+            NodeBundle {
+                style: Style {
+                    display: Display::Flex,
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(30.0),
+                    padding: UiRect::all(Px(padding)),
+                    top: Val::Percent(70.0),
+                    ..default()
+                },
+                background_color: BackgroundColor::from(Color::srgb(0.25, 0.25, 0.75)),
                 ..default()
             },
-            background_color: BackgroundColor::from(Color::srgb(0.25, 0.25, 0.75)),
-            transform: Transform::from_translation(box_position.extend(2.0)),
-            ..default()
-        })
+            StateScoped(GameState::InGame),
+        ))
         .with_children(|builder| {
             builder
                 .spawn(NodeBundle {
@@ -320,26 +902,162 @@ fn spawn_entities(mut commands: Commands, asset_server: Res<AssetServer>, audio:
                                 justify: JustifyText::Left,
                                 linebreak_behavior: BreakLineOn::AnyCharacter,
                             },
-                            // ensure the text is drawn on top of the box
-                            transform: Transform::from_translation(box_text_position.extend(1.0)),
                             ..default()
                         })
                         .insert(TypingText {
-                            full_text: string_auto_split(
-                                "欢迎游玩DS \n开始游戏",
-                                config.resolution.0,
-                                35,
-                            ),
+                            full_text: String::new(),
                             displayed_text: "".to_string(),
                             current_index: 0,
                             timer: Timer::from_seconds(0.2, TimerMode::Repeating),
                         });
                 });
         });
+    });
+}
+
+/// Runs the chapter once its assets are loaded, from the beginning or from
+/// the snapshot of the [`StoryStart`].
+fn start_chapter(
+    mut ready_events: EventReader<ChapterReady>,
+    chapter: Option<Res<Chapter>>,
+    start: Res<StoryStart>,
+    mut bus: ResMut<CommandBus>,
+    mut state: ResMut<StoryState>,
+    history: Res<StoryHistory>,
+) {
+    let Some(chapter) = chapter else {
+        return;
+    };
+    if !ready_events.read().any(|event| event.name == chapter.name) {
+        return;
+    }
+    bus.start(&chapter.script);
+    if let StoryStart::Snapshot(snapshot) = &*start {
+        snapshot.restore(&mut bus, &mut state);
+        return;
+    }
+    state.script = Some(chapter.name.clone());
+    state.variables.extend(
+        history
+            .progress()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone())),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::replay::HEADLESS_FRAME_TIME;
+    use crate::utils::test_harness::TestApp;
+
+    /// A typing text showing one character every 3 frames.
+    fn spawn(app: &mut TestApp, full_text: &str) -> Entity {
+        app.world_mut()
+            .spawn((
+                TextBundle::from_section("", TextStyle::default()),
+                TypingText {
+                    full_text: full_text.to_string(),
+                    displayed_text: String::new(),
+                    current_index: 0,
+                    timer: Timer::new(HEADLESS_FRAME_TIME * 3, TimerMode::Repeating),
+                },
+            ))
+            .id()
+    }
+
+    fn typing_app() -> TestApp {
+        TestApp::new(|app: &mut App| {
+            app.add_systems(Update, update_typing_text);
+        })
+    }
+
+    fn shown(app: &TestApp, entity: Entity) -> &str {
+        &app.get::<Text>(entity).sections[0].value
+    }
+
+    #[test]
+    fn types_one_character_per_tick() {
+        let mut app = typing_app();
+        let text = spawn(&mut app, "你好!");
+
+        app.step(3);
+        assert_eq!(shown(&app, text), "你");
+        app.step(3);
+        assert_eq!(shown(&app, text), "你好");
+        assert!(!app.get::<TypingText>(text).is_finished());
+        app.step(3);
+        assert_eq!(shown(&app, text), "你好!");
+        assert!(app.get::<TypingText>(text).is_finished());
+
+        app.step(30);
+        assert_eq!(shown(&app, text), "你好!");
+    }
+
+    #[test]
+    fn switches_the_language_of_the_line_being_typed() {
+        let mut app = TestApp::new(|app: &mut App| {
+            let mut locale = Locale::new("zh");
+            let mut table = crate::core::locale::StringTable::default();
+            table.insert("start.hello", "Hello!");
+            locale.insert("en", table);
+            app.add_plugins(CommandBusPlugin)
+                .insert_resource(locale)
+                .add_systems(Update, (relocalize_dialogue, update_typing_text).chain());
+        });
+        app.world_mut().resource_mut::<CommandBus>().push(VnCommand::Say {
+            speaker: None,
+            text: "你好!".to_string(),
+            id: Some("start.hello".to_string()),
+        });
+        let text = spawn(&mut app, "你好!");
+        app.step(3);
+        assert_eq!(shown(&app, text), "你");
+
+        app.world_mut().resource_mut::<Locale>().set_language("en");
+        app.update();
+        assert_eq!(shown(&app, text), "H");
+        assert_eq!(app.get::<TypingText>(text).full_text, "Hello!");
+    }
+
+    #[test]
+    fn click_shows_the_whole_line() {
+        let mut app = typing_app();
+        let text = spawn(&mut app, "……欢迎");
+        app.step(3);
+
+        app.tap_mouse_button(MouseButton::Left);
+        assert_eq!(shown(&app, text), "……欢迎");
+        let typing = app.get::<TypingText>(text);
+        assert_eq!(typing.displayed_text, "……欢迎");
+        assert!(typing.is_finished());
+    }
+
+    #[test]
+    fn a_advances_unless_a_button_has_the_focus() {
+        let mut app = TestApp::new(|app: &mut App| {
+            app.add_plugins(CommandBusPlugin)
+                .add_event::<PlayerAction>()
+                .init_resource::<Focus>()
+                .add_systems(Update, read_player_input);
+        });
+        app.world_mut().resource_mut::<CommandBus>().push(VnCommand::Say {
+            speaker: None,
+            text: "你好!".to_string(),
+            id: None,
+        });
+        app.update();
 
-    // 加载音频文件
-    let music = asset_server.load("music/bgmusic1.ogg");
+        app.tap_gamepad_button(GamepadButtonType::South);
+        assert_eq!(app.drain_events::<PlayerAction>(), [PlayerAction::Advance]);
 
-    let handle = audio.play(music).looped().handle();
-    commands.insert_resource(BgmHandle(handle));
+        // Enter 和 A 按下的是获得焦点的按钮
+        let button = app.spawn_ui(|_| {});
+        app.world_mut().resource_mut::<Focus>().set(Some(button));
+        app.tap_gamepad_button(GamepadButtonType::South);
+        app.tap_key(KeyCode::Enter);
+        assert!(app.drain_events::<PlayerAction>().is_empty());
+        app.tap_mouse_button(MouseButton::Left);
+        assert_eq!(app.drain_events::<PlayerAction>(), [PlayerAction::Advance]);
+    }
 }
@@ -1,315 +1,1197 @@
 use bevy::app::{App, Plugin};
 use bevy::color::Color;
 use bevy::core::Name;
-use bevy::input::mouse::MouseWheel;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
 use bevy::math::Vec2;
-use bevy::prelude::Val::Px;
+use bevy::prelude::Val::{Percent, Px};
 use bevy::prelude::*;
-use crate::prelude::GameConfig;
+use bevy::ui::{FocusPolicy, RelativeCursorPosition};
+use bevy::utils::HashMap;
+use bevy::window::PrimaryWindow;
+
+use crate::plugins::focus::Focus;
+
 
 /// A Bevy Plugin for creating a scroll view functionality.
 ///
-/// This plugin adds systems for managing a user interface scrollable
-/// view, handling user inputs for scrolling and dragging interactions.
+/// This plugin adds systems for managing scrollable containers spawned with
+/// [`ScrollViewBuilder`]: the mouse wheel, dragging the scroll bar thumb,
+/// clicking the scroll bar track, the arrow/page keys while the cursor is
+/// over the view and the focus is not outside of it, and dragging the content
+/// itself with the mouse or a touch, which keeps scrolling with inertia after
+/// release. Wheel, key and track
+/// scrolling is interpolated towards its target instead of jumping.
+///
+/// Use [`ScrollCommandsExt::scroll_to`] to bring a child into view, and
+/// [`ScrollViewBuilder::spawn_list`] for lists too long to spawn at once.
 ///
-/// When added to an app, it initializes entities
-/// and components for the scroll view.
+/// Every scroll view keeps its own state, so any number of them can coexist.
 ///
 /// Usage:
 ///
-/// ```rust
+/// ```rust,ignore
 /// use crate::ScrollViewPlugin;
 /// use bevy::prelude::*;
 ///
 /// fn main() {
 ///     App::new()
 ///         .add_plugins(DefaultPlugins)
-///         .add_plugin(ScrollViewPlugin)
+///         .add_plugins(ScrollViewPlugin)
 ///         .run();
 /// }
 /// ```
 pub struct ScrollViewPlugin;
 
-/// A resource struct for managing scroll view updates.
-///
-/// This resource keeps track of the current value of the scroll view
-/// to handle updates and modifications.
-#[derive(Resource)]
-struct ScrollViewResource {
-    value: i32,
+/// The axes a [`ScrollView`] can scroll along.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScrollAxis {
+    #[default]
+    Vertical,
+    Horizontal,
+    Both,
+}
+
+impl ScrollAxis {
+    pub fn vertical(self) -> bool {
+        matches!(self, ScrollAxis::Vertical | ScrollAxis::Both)
+    }
+
+    pub fn horizontal(self) -> bool {
+        matches!(self, ScrollAxis::Horizontal | ScrollAxis::Both)
+    }
 }
 
-/// A struct representing a scroll view component.
+/// A scroll container.
 ///
-/// This struct is used to track the state and configuration
-/// of a scroll view UI element, including its dimensions,
-/// position, and interaction state.
-#[derive(Component, Clone, Default)]
+/// Lives on the clipping viewport node and links the content node moved
+/// inside it and the scroll bars driving it. The scrollable length is
+/// measured from the laid out [`Node`] sizes of the viewport and the
+/// content, so the content can change freely.
+#[derive(Component, Clone, Debug)]
 pub struct ScrollView {
-    /// The current top position of the scroll view in pixels.
-    pub current_top: f32,
+    /// The node holding the scrolled children.
+    pub content: Entity,
+    /// The vertical scroll bar track, if any.
+    pub vertical_bar: Option<Entity>,
+    /// The horizontal scroll bar track, if any.
+    pub horizontal_bar: Option<Entity>,
+    pub axis: ScrollAxis,
+    /// How far the content is scrolled, in pixels from its top left corner.
+    pub offset: Vec2,
+    /// Where smooth scrolling is heading; equals `offset` once it settled.
+    pub target: Vec2,
+    /// The inertia left after a content drag, in pixels per second.
+    pub velocity: Vec2,
+    /// How fast `offset` approaches `target`, per second. `0.0` disables smoothing.
+    pub smoothing: f32,
+    /// The fraction of inertia lost per second after a content drag.
+    pub friction: f32,
+    /// The distance of one mouse wheel line or arrow key press, in pixels.
+    pub line_height: f32,
+    /// The visible size, measured from the viewport node.
+    pub viewport_size: Vec2,
+    /// The full content size, measured from the content node.
+    pub content_size: Vec2,
+}
+
+impl ScrollView {
+    /// The largest valid offset on each axis.
+    pub fn max_offset(&self) -> Vec2 {
+        (self.content_size - self.viewport_size).max(Vec2::ZERO)
+    }
 
-    /// The current length (height) of the scroll view in pixels.
-    pub current_len: f32,
+    /// Smoothly scrolls by `delta` pixels from the current target.
+    pub fn scroll_by(&mut self, delta: Vec2) {
+        let target = self.target + delta;
+        self.scroll_to_offset(target);
+    }
 
-    /// The top position of the parent container in pixels.
-    pub parent_top: f32,
+    /// Smoothly scrolls to `offset` on the enabled axes, clamped to the content.
+    pub fn scroll_to_offset(&mut self, offset: Vec2) {
+        self.target = self.clamp(offset);
+        self.velocity = Vec2::ZERO;
+        if self.smoothing <= 0.0 {
+            self.offset = self.target;
+        }
+    }
 
-    /// The length (height) of the parent container in pixels.
-    pub parent_len: f32,
+    /// Jumps to `offset` on the enabled axes without smoothing, clamped to the content.
+    pub fn set_offset(&mut self, offset: Vec2) {
+        self.offset = self.clamp(offset);
+        self.target = self.offset;
+        self.velocity = Vec2::ZERO;
+    }
 
-    /// Flag indicating whether the scroll view is currently being dragged.
-    ///
-    /// Used to track the dragging state during user interaction.
-    pub is_dragging: bool,
+    fn clamp(&self, offset: Vec2) -> Vec2 {
+        let max = self.max_offset();
+        let mut clamped = self.offset;
+        if self.axis.vertical() {
+            clamped.y = offset.y.clamp(0.0, max.y);
+        }
+        if self.axis.horizontal() {
+            clamped.x = offset.x.clamp(0.0, max.x);
+        }
+        clamped
+    }
 
-    /// The offset value when dragging starts, in pixels.
-    ///
-    /// Used to calculate the new position based on initial click position.
-    pub drag_offset: f32,
+    /// Offset, viewport length and content length along one axis.
+    fn axis_lengths(&self, vertical: bool) -> (f32, f32, f32) {
+        if vertical {
+            (self.offset.y, self.viewport_size.y, self.content_size.y)
+        } else {
+            (self.offset.x, self.viewport_size.x, self.content_size.x)
+        }
+    }
+}
 
-    /// Flag indicating whether this instance represents a scroll bar.
-    pub bar: bool,
+/// A scroll bar track of a [`ScrollView`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ScrollBar {
+    pub view: Entity,
+    pub thumb: Entity,
+    pub vertical: bool,
+}
+
+/// The draggable thumb of a [`ScrollBar`].
+#[derive(Component, Clone, Copy, Debug)]
+struct ScrollThumb {
+    bar: Entity,
+}
 
-    /// The top position for the view window relative to its parent.
-    pub view_top: f32,
+/// Present on a thumb while it is dragged, holding where in the thumb it was grabbed.
+#[derive(Component)]
+struct ScrollThumbDragging(f32);
 
-    /// The length (height) of the view window in pixels.
-    pub view_len: f32,
+/// Tracks a mouse or touch drag on the content of a [`ScrollView`].
+#[derive(Component, Default)]
+struct ContentDrag {
+    /// Where the pointer went down, `None` when no pointer is down on the view.
+    start: Option<Vec2>,
+    last: Vec2,
+    /// Set once the pointer moved far enough to count as a drag and not a click.
+    active: bool,
+    /// The smoothed pointer speed, handed to the view as inertia on release.
+    velocity: Vec2,
+}
 
-    /// The total length (height) of the scrollable content in pixels.
-    pub content_len: f32,
+/// Scroll a [`ScrollView`] so that a descendant of its content becomes visible.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ScrollToEntity {
+    /// The entity holding the [`ScrollView`].
+    pub view: Entity,
+    pub target: Entity,
+    pub align: ScrollAlign,
 }
 
-/// Component representing a hidden text field.
+/// Where [`ScrollToEntity`] places its target in the viewport.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScrollAlign {
+    /// Scroll as little as possible to make the target fully visible.
+    #[default]
+    Nearest,
+    Start,
+    End,
+}
+
+/// Programmatic scrolling through [`Commands`].
+pub trait ScrollCommandsExt {
+    /// Scrolls `view` so that its content descendant `target` is fully
+    /// visible, aligned to the viewport `align`. Waits for `target` to be laid
+    /// out, so it can be called right after spawning it.
+    fn scroll_to(&mut self, view: Entity, target: Entity, align: ScrollAlign);
+}
+
+impl ScrollCommandsExt for Commands<'_, '_> {
+    fn scroll_to(&mut self, view: Entity, target: Entity, align: ScrollAlign) {
+        self.add(move |world: &mut World| {
+            world.send_event(ScrollToEntity {
+                view,
+                target,
+                align,
+            });
+        });
+    }
+}
+
+/// The shortest a thumb gets, in pixels, so it stays grabbable on long content.
+const MIN_THUMB_LEN: f32 = 16.0;
+
+/// How far the pointer moves before a press on the content becomes a drag, in pixels.
+const DRAG_THRESHOLD: f32 = 5.0;
+
+/// Inertia below this speed stops, in pixels per second.
+const MIN_VELOCITY: f32 = 10.0;
+
+/// How many frames [`ScrollToEntity`] waits for its target to be laid out.
+const SCROLL_TO_RETRIES: u32 = 10;
+
+/// Supplies the rows of a virtualised list, see [`ScrollViewBuilder::spawn_list`].
 ///
-/// This component can be used to associate entities that should be
-/// treated as hidden text fields within the user interface.
-#[derive(Component)]
-pub struct TextFiledHidden;
+/// Rows are built on demand when they scroll into view and torn down again
+/// when they leave it, so `build_row` must only depend on `index`.
+pub trait ListDataSource: Send + Sync + 'static {
+    /// The number of rows.
+    fn len(&self) -> usize;
 
-/// Component representing a button for hiding a text field.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The height assumed for rows that have not been laid out yet.
+    fn estimated_row_height(&self) -> f32 {
+        30.0
+    }
+
+    /// Spawns the children of row `index` into `row`, a full width column node.
+    fn build_row(&self, index: usize, row: &mut ChildBuilder);
+}
+
+/// A virtualised list filling the content of a [`ScrollView`].
 ///
-/// This component can be used to identify the button entity
-/// associated with the action to hide or show text fields
-/// within the user interface.
+/// Lives next to the [`ScrollView`] on its viewport. Only the rows in view,
+/// plus `overscan` rows on each side, exist as entities; rows leaving the
+/// view are recycled for the ones entering it. Row heights start at
+/// [`ListDataSource::estimated_row_height`] and are replaced by the laid out
+/// height once a row has been visible.
 #[derive(Component)]
-pub struct TextFiledHiddenButton;
+pub struct VirtualList {
+    source: Box<dyn ListDataSource>,
+    /// Extra rows kept alive above and below the visible ones.
+    pub overscan: usize,
+    /// Measured row heights, `None` until a row has been laid out.
+    heights: Vec<Option<f32>>,
+    /// The live rows by index.
+    rows: HashMap<usize, Entity>,
+    /// Hidden rows waiting to be reused.
+    pool: Vec<Entity>,
+    dirty: bool,
+}
 
-impl Plugin for ScrollViewPlugin {
-    fn build(&self, app: &mut App) {
-        app.insert_resource(ScrollViewResource { value: 0 })
-            .add_systems(Startup, spawn_entities)
-            .add_systems(
-                Update,
-                (
-                    scroll_view_system,
-                    scroll_bar_drag_system,
-                    scroll_view_drag_system,
-                    text_filed_hidden,
-                ),
-            );
+impl VirtualList {
+    pub fn new(source: impl ListDataSource) -> Self {
+        Self {
+            source: Box::new(source),
+            overscan: 3,
+            heights: Vec::new(),
+            rows: HashMap::default(),
+            pool: Vec::new(),
+            dirty: false,
+        }
+    }
+
+    pub fn source(&self) -> &dyn ListDataSource {
+        self.source.as_ref()
+    }
+
+    /// Replaces the data source and rebuilds every row.
+    pub fn set_source(&mut self, source: impl ListDataSource) {
+        self.source = Box::new(source);
+        self.refresh();
+    }
+
+    /// Rebuilds every row and forgets the measured heights, for when the data
+    /// behind the source changed. Rows appended at the end show up without it.
+    pub fn refresh(&mut self) {
+        self.dirty = true;
+    }
+
+    /// The distance from the top of the list to row `index`, in pixels, for
+    /// use with [`ScrollView::scroll_to_offset`].
+    pub fn row_offset(&self, index: usize) -> f32 {
+        let estimate = self.source.estimated_row_height();
+        (0..index.min(self.source.len()))
+            .map(|i| self.heights.get(i).copied().flatten().unwrap_or(estimate))
+            .sum()
+    }
+
+    /// The row entity currently showing `index`, if it is spawned.
+    pub fn row_entity(&self, index: usize) -> Option<Entity> {
+        self.rows.get(&index).copied()
     }
 }
 
-/// Spawns UI entities with scrolling capabilities for a Bevy application.
+/// Spawns a [`ScrollView`] with its content node and scroll bars.
 ///
-/// This system sets up a scrolling view with a hidden text field button,
-/// a scrollable content area, and a scroll bar.
-///
-/// It organizes UI components vertically, providing a paginated view
-/// for the content items.
-///
-/// # Parameters
-/// - `commands`: Command queue for spawning entities.
-/// - `asset_server`: Access to the asset loader for loading fonts.
-///
-/// # Panics
-/// This function might panic if any of the UI component initialization fails.
-///
-///
-/// Ensure the appropriate resources (e.g., font files) exist in your asset folder.
-fn spawn_entities(mut commands: Commands, asset_server: Res<AssetServer>,config:Res<GameConfig>) {
-    let view_size = Vec2::new(config.resolution.0 * 0.3, config.resolution.1 * 0.2);
-    let view_position = Vec2::new(config.resolution.0 * 0.3, config.resolution.1 * 0.4);
-    let cover_size = Vec2::new(config.resolution.0 * 0.3, config.resolution.1);
-
-    commands
-        .spawn((
+/// ```rust,ignore
+/// ScrollViewBuilder::new(Vec2::new(300.0, 200.0))
+///     .axis(ScrollAxis::Both)
+///     .spawn(parent, |content| {
+///         content.spawn(TextBundle::from_section("Item", TextStyle::default()));
+///     });
+/// ```
+pub struct ScrollViewBuilder {
+    size: Vec2,
+    axis: ScrollAxis,
+    bar_width: f32,
+    line_height: f32,
+    style: Style,
+    background_color: Color,
+    track_color: Color,
+    thumb_color: Color,
+    overscan: usize,
+}
+
+impl ScrollViewBuilder {
+    pub fn new(size: Vec2) -> Self {
+        Self {
+            size,
+            axis: ScrollAxis::Vertical,
+            bar_width: 16.0,
+            line_height: 30.0,
+            style: Style::default(),
+            background_color: Color::NONE,
+            track_color: Color::srgb(0.9, 0.9, 0.9),
+            thumb_color: Color::srgb(0.5, 0.5, 0.5),
+            overscan: 3,
+        }
+    }
+
+    pub fn axis(mut self, axis: ScrollAxis) -> Self {
+        self.axis = axis;
+        self
+    }
+
+    pub fn bar_width(mut self, width: f32) -> Self {
+        self.bar_width = width;
+        self
+    }
+
+    pub fn line_height(mut self, line_height: f32) -> Self {
+        self.line_height = line_height;
+        self
+    }
+
+    /// Positioning of the outer node; its size is always taken from [`Self::new`].
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn background_color(mut self, color: Color) -> Self {
+        self.background_color = color;
+        self
+    }
+
+    /// Rows kept alive beyond each edge of the view by [`Self::spawn_list`].
+    pub fn overscan(mut self, rows: usize) -> Self {
+        self.overscan = rows;
+        self
+    }
+
+    /// Spawns the scroll view as a child of `parent` and fills its content
+    /// node with `spawn_content`.
+    ///
+    /// Returns the outer node entity, the [`ScrollView`] component lives on
+    /// its viewport descendant.
+    pub fn spawn(
+        self,
+        parent: &mut ChildBuilder,
+        spawn_content: impl FnOnce(&mut ChildBuilder),
+    ) -> Entity {
+        self.spawn_view(parent, spawn_content).0
+    }
+
+    /// Spawns a vertical scroll view whose rows come from `source` and are
+    /// only spawned while in view, see [`VirtualList`].
+    ///
+    /// Returns the outer node entity, the [`ScrollView`] and [`VirtualList`]
+    /// components live on its viewport descendant.
+    pub fn spawn_list(mut self, parent: &mut ChildBuilder, source: impl ListDataSource) -> Entity {
+        self.axis = ScrollAxis::Vertical;
+        let mut list = VirtualList::new(source);
+        list.overscan = self.overscan;
+        let (outer, viewport) = self.spawn_view(parent, |_| {});
+        parent.add_command(move |world: &mut World| {
+            world.entity_mut(viewport).insert(list);
+        });
+        outer
+    }
+
+    /// Spawns the view and returns its outer node and viewport entities.
+    fn spawn_view(
+        self,
+        parent: &mut ChildBuilder,
+        spawn_content: impl FnOnce(&mut ChildBuilder),
+    ) -> (Entity, Entity) {
+        let mut view = ScrollView {
+            content: Entity::PLACEHOLDER,
+            vertical_bar: None,
+            horizontal_bar: None,
+            axis: self.axis,
+            offset: Vec2::ZERO,
+            target: Vec2::ZERO,
+            velocity: Vec2::ZERO,
+            smoothing: 12.0,
+            friction: 0.95,
+            line_height: self.line_height,
+            viewport_size: Vec2::ZERO,
+            content_size: Vec2::ZERO,
+        };
+        let mut viewport = Entity::PLACEHOLDER;
+
+        let mut outer = parent.spawn((
             NodeBundle {
                 style: Style {
-                    width: Px(view_size.x),
-                    height: Px(view_size.y),
-                    left: Px(view_position.x),
-                    top: Px(view_position.y),
-                    overflow: Overflow::clip(),
-                    ..default()
+                    flex_direction: FlexDirection::Column,
+                    width: Px(self.size.x),
+                    height: Px(self.size.y),
+                    ..self.style.clone()
                 },
-                background_color: BackgroundColor::from(Color::srgb(0.25, 0.25, 0.75)),
-                transform: Transform::from_translation(view_position.extend(2.0)),
+                background_color: self.background_color.into(),
                 ..default()
             },
             Name::new("scroll_view"),
-            TextFiledHidden,
-        ))
-        .with_children(|builder| {
-            builder
-                .spawn((
-                    NodeBundle {
-                        style: Style {
-                            width: Px(cover_size.x - 16.0),
-                            height: Px(cover_size.y),
-                            left: Px(16.0),
-                            ..default()
-                        },
+        ));
+
+        outer.with_children(|outer| {
+            outer
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        flex_grow: 1.0,
+                        min_height: Px(0.0),
                         ..default()
                     },
-                    Name::new("content_view"),
-                ))
-                .insert(ScrollView {
-                    content_len: cover_size.y + view_size.y,
-                    view_top: view_position.y,
-                    view_len: view_size.y,
-                    bar: false,
                     ..default()
                 })
-                .with_children(|content| {
-                    for i in 0..10 {
-                        content.spawn(TextBundle {
-                            text: Text::from_section(
-                                format!("Item {}", i + 1),
-                                TextStyle {
-                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                    font_size: 30.0,
-                                    color: Color::BLACK,
+                .with_children(|row| {
+                    viewport = row
+                        .spawn((
+                            NodeBundle {
+                                style: Style {
+                                    flex_grow: 1.0,
+                                    min_width: Px(0.0),
+                                    overflow: Overflow::clip(),
+                                    ..default()
                                 },
-                            ),
-                            style: Style {
-                                margin: UiRect::all(Px(10.0)),
                                 ..default()
                             },
-                            ..default()
-                        });
+                            // 子节点会阻挡视口的 Interaction，用光标位置判断悬停
+                            RelativeCursorPosition::default(),
+                            ContentDrag::default(),
+                            Name::new("scroll_viewport"),
+                        ))
+                        .with_children(|viewport| {
+                            view.content = viewport
+                                .spawn((
+                                    NodeBundle {
+                                        style: Style {
+                                            position_type: PositionType::Absolute,
+                                            flex_direction: FlexDirection::Column,
+                                            // 只在垂直方向滚动时，内容宽度跟随视口
+                                            width: if self.axis.horizontal() {
+                                                Val::Auto
+                                            } else {
+                                                Percent(100.0)
+                                            },
+                                            ..default()
+                                        },
+                                        ..default()
+                                    },
+                                    Name::new("scroll_content"),
+                                ))
+                                .with_children(spawn_content)
+                                .id();
+                        })
+                        .id();
+
+                    if self.axis.vertical() {
+                        view.vertical_bar = Some(self.spawn_bar(row, viewport, true));
                     }
                 });
+
+            if self.axis.horizontal() {
+                view.horizontal_bar = Some(self.spawn_bar(outer, viewport, false));
+            }
+
+            outer.add_command(move |world: &mut World| {
+                world.entity_mut(viewport).insert(view);
+            });
         });
-    commands
-        .spawn((
+
+        (outer.id(), viewport)
+    }
+
+    fn spawn_bar(&self, parent: &mut ChildBuilder, view: Entity, vertical: bool) -> Entity {
+        let (width, height) = if vertical {
+            (Px(self.bar_width), Percent(100.0))
+        } else {
+            (Percent(100.0), Px(self.bar_width))
+        };
+
+        let mut bar = parent.spawn((
             NodeBundle {
                 style: Style {
-                    width: Px(16.0),
-                    height: Px(view_size.y),
-                    left: Px(view_position.x - 16.0),
-                    top: Px(view_position.y),
+                    width,
+                    height,
+                    flex_shrink: 0.0,
                     ..default()
                 },
-                background_color: BackgroundColor::from(Color::srgb(0.9, 0.9, 0.9)),
-                transform: Transform::from_translation(view_position.extend(5.0)),
+                background_color: self.track_color.into(),
                 ..default()
             },
+            Interaction::default(),
             Name::new("scroll_bar"),
-            TextFiledHidden,
-        ))
-        .with_children(|status| {
-            status
+        ));
+        let bar_entity = bar.id();
+
+        let mut thumb = Entity::PLACEHOLDER;
+        bar.with_children(|bar| {
+            thumb = bar
                 .spawn((
                     NodeBundle {
                         style: Style {
-                            width: Px(16.0),
-                            height: Px(20.0),
+                            position_type: PositionType::Absolute,
+                            width,
+                            height,
                             ..default()
                         },
-                        transform: Transform::from_translation(view_position.extend(2.0)),
-                        background_color: BackgroundColor::from(Color::srgb(0.5, 0.5, 0.5)),
+                        background_color: self.thumb_color.into(),
+                        // 拖动滑块时不触发轨道点击
+                        focus_policy: FocusPolicy::Block,
                         ..default()
                     },
+                    Interaction::default(),
+                    ScrollThumb { bar: bar_entity },
                     Name::new("scroll_status"),
                 ))
-                .insert(ScrollView {
-                    parent_top: view_position.y,
-                    parent_len: view_size.y,
-                    current_top: view_position.y,
-                    current_len: 20.0,
-                    is_dragging: false,
-                    drag_offset: 0.0,
-                    bar: true,
-                    ..default()
-                });
+                .id();
+        });
+        bar.insert(ScrollBar {
+            view,
+            thumb,
+            vertical,
         });
+
+        bar_entity
+    }
 }
+
+/// Component representing a hidden text field.
+///
+/// This component can be used to associate entities that should be
+/// treated as hidden text fields within the user interface.
+#[derive(Component)]
+pub struct TextFiledHidden;
+
+/// Component representing a button for hiding a text field.
+///
+/// This component can be used to identify the button entity
+/// associated with the action to hide or show text fields
+/// within the user interface.
+#[derive(Component)]
+pub struct TextFiledHiddenButton;
+
+impl Plugin for ScrollViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ScrollToEntity>()
+            .add_systems(
+                Update,
+                (
+                    measure_scroll_views,
+                    scroll_view_system,
+                    scroll_view_keyboard_system,
+                    scroll_bar_track_system,
+                    scroll_bar_drag_system,
+                    content_drag_system,
+                    scroll_to_entity_system,
+                    animate_scroll_views,
+                    virtual_list_system,
+                    scroll_view_layout_system,
+                    text_filed_hidden,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Reads the laid out viewport and content sizes, re-clamping the offset
+/// when the content shrinks.
+///
+/// Views that have no size, because they are hidden or not laid out yet,
+/// keep the sizes measured last.
+fn measure_scroll_views(
+    mut view_query: Query<(&mut ScrollView, &Node)>,
+    content_query: Query<&Node, Without<ScrollView>>,
+) {
+    for (mut view, viewport_node) in view_query.iter_mut() {
+        let Ok(content_node) = content_query.get(view.content) else {
+            continue;
+        };
+        let viewport_size = viewport_node.size();
+        if viewport_size == Vec2::ZERO {
+            continue;
+        }
+        let content_size = content_node.size();
+        if view.viewport_size != viewport_size || view.content_size != content_size {
+            view.viewport_size = viewport_size;
+            view.content_size = content_size;
+            // 内容变短时把偏移和目标都收回范围内
+            view.offset = view.clamp(view.offset);
+            view.target = view.clamp(view.target);
+        }
+    }
+}
+
+fn scroll_view_system(
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut view_query: Query<(&mut ScrollView, &RelativeCursorPosition)>,
+) {
+    let mut scroll_delta = Vec2::ZERO;
+    let mut line_delta = Vec2::ZERO;
+
+    // 读取鼠标滚轮事件
+    for event in mouse_wheel_events.read() {
+        match event.unit {
+            MouseScrollUnit::Line => line_delta += Vec2::new(event.x, event.y),
+            MouseScrollUnit::Pixel => scroll_delta += Vec2::new(event.x, event.y),
+        }
+    }
+    if scroll_delta == Vec2::ZERO && line_delta == Vec2::ZERO {
+        return;
+    }
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    for (mut view, cursor) in view_query.iter_mut() {
+        if !cursor.mouse_over() {
+            continue;
+        }
+        let mut delta = -(scroll_delta + line_delta * view.line_height);
+        // 只能水平滚动或按住 Shift 时，纵向滚轮用于水平滚动
+        if view.axis == ScrollAxis::Horizontal || (shift && view.axis == ScrollAxis::Both) {
+            delta = Vec2::new(delta.x + delta.y, 0.0);
+        }
+        view.scroll_by(delta);
+    }
+}
+
+/// Scrolls the hovered view with the arrow, Page and Home/End keys, unless
+/// they belong to a focused node outside of it.
+fn scroll_view_keyboard_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    focus: Option<Res<Focus>>,
+    parent_query: Query<&Parent>,
+    mut view_query: Query<(Entity, &mut ScrollView, &RelativeCursorPosition)>,
+) {
+    let focused = focus.and_then(|focus| focus.get());
+    for (entity, mut view, cursor) in view_query.iter_mut() {
+        if !cursor.mouse_over() {
+            continue;
+        }
+        // 焦点在视图外时按键用于移动焦点或操作控件
+        if let Some(focused) = focused {
+            if focused != entity && !parent_query.iter_ancestors(focused).any(|e| e == entity) {
+                continue;
+            }
+        }
+        let line = view.line_height;
+        let page = view.viewport_size;
+        let mut delta = Vec2::ZERO;
+        if keyboard_input.just_pressed(KeyCode::ArrowUp) {
+            delta.y -= line;
+        }
+        if keyboard_input.just_pressed(KeyCode::ArrowDown) {
+            delta.y += line;
+        }
+        if keyboard_input.just_pressed(KeyCode::ArrowLeft) {
+            delta.x -= line;
+        }
+        if keyboard_input.just_pressed(KeyCode::ArrowRight) {
+            delta.x += line;
+        }
+        if keyboard_input.just_pressed(KeyCode::PageUp) {
+            delta.y -= page.y;
+        }
+        if keyboard_input.just_pressed(KeyCode::PageDown) {
+            delta.y += page.y;
+        }
+        if keyboard_input.just_pressed(KeyCode::Home) {
+            delta = -view.content_size;
+        }
+        if keyboard_input.just_pressed(KeyCode::End) {
+            delta = view.content_size;
+        }
+        if delta != Vec2::ZERO {
+            view.scroll_by(delta);
+        }
+    }
+}
+
+/// Pages towards the cursor when the track outside of the thumb is clicked.
+fn scroll_bar_track_system(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    bar_query: Query<(&Interaction, &ScrollBar), Changed<Interaction>>,
+    thumb_query: Query<(&Node, &GlobalTransform)>,
+    mut view_query: Query<&mut ScrollView>,
+) {
+    let Some(cursor_position) = window_query
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+    else {
+        return;
+    };
+
+    for (interaction, bar) in bar_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let (Ok(mut view), Ok((thumb_node, thumb_transform))) =
+            (view_query.get_mut(bar.view), thumb_query.get(bar.thumb))
+        else {
+            continue;
+        };
+        let thumb_rect = thumb_node.logical_rect(thumb_transform);
+        let (cursor, thumb_min, thumb_max) = if bar.vertical {
+            (cursor_position.y, thumb_rect.min.y, thumb_rect.max.y)
+        } else {
+            (cursor_position.x, thumb_rect.min.x, thumb_rect.max.x)
+        };
+        let page = if bar.vertical {
+            Vec2::new(0.0, view.viewport_size.y)
+        } else {
+            Vec2::new(view.viewport_size.x, 0.0)
+        };
+        if cursor < thumb_min {
+            view.scroll_by(-page);
+        } else if cursor > thumb_max {
+            view.scroll_by(page);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
 fn scroll_bar_drag_system(
-    mut scroll_query: Query<(&mut Style, &mut ScrollView)>,
-    current_window: Query<&mut Window>,
-    mouse_button_input: ResMut<ButtonInput<MouseButton>>,
+    mut commands: Commands,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    thumb_query: Query<(
+        Entity,
+        &Interaction,
+        &ScrollThumb,
+        &Node,
+        &GlobalTransform,
+        Option<&ScrollThumbDragging>,
+    )>,
+    bar_query: Query<(&ScrollBar, &Node, &GlobalTransform), Without<ScrollThumb>>,
+    mut view_query: Query<&mut ScrollView>,
 ) {
-    // println!("windows size:{:?}",current_window.iter().len());
-    for (mut style, mut scroll_bar) in scroll_query.iter_mut() {
-        // 判断是否有鼠标按钮被按下
-        if mouse_button_input.just_pressed(MouseButton::Left) {
-            // println!("scroll_bar_pressed");
-            // 获取鼠标点击位置
-            for window in current_window.iter() {
-                let Some(cursor_position) = window.cursor_position() else {
+    let cursor_position = window_query
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position());
+
+    for (entity, interaction, thumb, thumb_node, thumb_transform, dragging) in thumb_query.iter() {
+        let Ok((bar, bar_node, bar_transform)) = bar_query.get(thumb.bar) else {
+            continue;
+        };
+        let along = |position: Vec2| if bar.vertical { position.y } else { position.x };
+
+        // 判断是否正在拖动
+        let grab = match dragging {
+            Some(_) if !mouse_button_input.pressed(MouseButton::Left) => {
+                // 当鼠标按钮释放时，停止拖动
+                commands.entity(entity).remove::<ScrollThumbDragging>();
+                continue;
+            }
+            Some(dragging) => dragging.0,
+            None => {
+                let Some(cursor_position) = cursor_position else {
                     continue;
                 };
-                // println!("cursor_position: {:?}", cursor_position);
-                if cursor_position.y >= scroll_bar.current_top
-                    && cursor_position.y <= scroll_bar.current_top + scroll_bar.current_len
+                if *interaction != Interaction::Pressed
+                    || !mouse_button_input.just_pressed(MouseButton::Left)
                 {
-                    scroll_bar.is_dragging = true;
-                    scroll_bar.drag_offset = cursor_position.y - scroll_bar.current_top;
+                    continue;
                 }
+                let thumb_rect = thumb_node.logical_rect(thumb_transform);
+                let grab = along(cursor_position) - along(thumb_rect.min);
+                commands.entity(entity).insert(ScrollThumbDragging(grab));
+                grab
             }
+        };
+
+        let (Some(cursor_position), Ok(mut view)) = (cursor_position, view_query.get_mut(bar.view))
+        else {
+            continue;
+        };
+        let bar_rect = bar_node.logical_rect(bar_transform);
+        let track_len = along(bar_rect.size());
+        let thumb_len = along(thumb_node.size());
+        let free_len = track_len - thumb_len;
+        if free_len <= 0.0 {
+            continue;
         }
 
-        // 判断是否正在拖动
-        if scroll_bar.is_dragging {
-            // 获取鼠标当前位置
-            for window in current_window.iter() {
-                let Some(cursor_position) = window.cursor_position() else {
-                    continue;
-                };
-                let new_top = cursor_position.y - scroll_bar.drag_offset;
+        // 滑块位置按比例换算为内容偏移
+        let fraction = ((along(cursor_position) - grab - along(bar_rect.min)) / free_len).clamp(0.0, 1.0);
+        let max_offset = view.max_offset();
+        let mut offset = view.offset;
+        if bar.vertical {
+            offset.y = fraction * max_offset.y;
+        } else {
+            offset.x = fraction * max_offset.x;
+        }
+        view.set_offset(offset);
+    }
+}
 
-                // 确保新的位置在父容器的范围内
-                if new_top >= scroll_bar.parent_top
-                    && new_top + scroll_bar.current_len
-                        <= scroll_bar.parent_top + scroll_bar.parent_len
-                {
-                    style.top = Val::Px(new_top - scroll_bar.parent_top);
-                    scroll_bar.current_top = new_top;
-                } else {
-                    if new_top < scroll_bar.parent_top {
-                        style.top = Val::Px(0.0);
-                        scroll_bar.current_top = scroll_bar.parent_top;
-                    }
-                    if new_top + scroll_bar.current_len
-                        > scroll_bar.parent_top + scroll_bar.parent_len
-                    {
-                        style.top = Val::Px(scroll_bar.parent_len - scroll_bar.current_len);
-                        scroll_bar.current_top = scroll_bar.parent_len - scroll_bar.current_len;
+/// Scrolls by dragging the content with the mouse or a touch, handing the
+/// release speed over to inertia.
+///
+/// A press only becomes a drag after moving [`DRAG_THRESHOLD`] pixels, so
+/// buttons inside the content can still be clicked.
+fn content_drag_system(
+    time: Res<Time>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    mut view_query: Query<(&mut ScrollView, &mut ContentDrag, &Node, &GlobalTransform)>,
+) {
+    // 触摸优先于鼠标
+    let (position, just_pressed, pressed) = match touches.iter().next() {
+        Some(touch) => (
+            Some(touch.position()),
+            touches.just_pressed(touch.id()),
+            true,
+        ),
+        None => (
+            window_query
+                .get_single()
+                .ok()
+                .and_then(|window| window.cursor_position()),
+            mouse_button_input.just_pressed(MouseButton::Left),
+            mouse_button_input.pressed(MouseButton::Left),
+        ),
+    };
+    let delta_seconds = time.delta_seconds().max(f32::EPSILON);
+
+    for (mut view, mut drag, node, transform) in view_query.iter_mut() {
+        if just_pressed {
+            if let Some(position) = position.filter(|p| node.logical_rect(transform).contains(*p)) {
+                drag.start = Some(position);
+                drag.last = position;
+                drag.active = false;
+                drag.velocity = Vec2::ZERO;
+                // 按下时停止惯性滚动
+                let offset = view.offset;
+                view.set_offset(offset);
+            }
+            continue;
+        }
+
+        let Some(start) = drag.start else {
+            continue;
+        };
+        if !pressed {
+            if drag.active {
+                view.velocity = drag.velocity;
+            }
+            *drag = ContentDrag::default();
+            continue;
+        }
+        let Some(position) = position else {
+            continue;
+        };
+        if !drag.active && position.distance(start) < DRAG_THRESHOLD {
+            continue;
+        }
+        drag.active = true;
+
+        // 手指向上移动时内容向下滚动
+        let delta = drag.last - position;
+        drag.last = position;
+        let offset = view.offset + delta;
+        view.set_offset(offset);
+        drag.velocity = drag.velocity.lerp(delta / delta_seconds, 0.5);
+    }
+}
+
+fn scroll_to_entity_system(
+    mut events: EventReader<ScrollToEntity>,
+    mut pending: Local<Vec<(ScrollToEntity, u32)>>,
+    node_query: Query<(&Node, &GlobalTransform)>,
+    mut view_query: Query<&mut ScrollView>,
+) {
+    pending.extend(events.read().map(|event| (*event, 0)));
+
+    pending.retain_mut(|(event, tries)| {
+        let Ok(mut view) = view_query.get_mut(event.view) else {
+            return false;
+        };
+        let (Ok((target_node, target_transform)), Ok((content_node, content_transform))) =
+            (node_query.get(event.target), node_query.get(view.content))
+        else {
+            return false;
+        };
+        // 目标刚生成时还没有完成布局，等待下一帧
+        if target_node.size() == Vec2::ZERO || view.viewport_size == Vec2::ZERO {
+            *tries += 1;
+            return *tries < SCROLL_TO_RETRIES;
+        }
+
+        let content_min = content_node.logical_rect(content_transform).min;
+        let target_rect = target_node.logical_rect(target_transform);
+        let start = target_rect.min - content_min;
+        let end = target_rect.max - content_min;
+        let current = view.target;
+        let viewport = view.viewport_size;
+        let offset = Vec2::new(
+            align_offset(current.x, start.x, end.x, viewport.x, event.align),
+            align_offset(current.y, start.y, end.y, viewport.y, event.align),
+        );
+        view.scroll_to_offset(offset);
+        false
+    });
+}
+
+fn align_offset(current: f32, start: f32, end: f32, viewport: f32, align: ScrollAlign) -> f32 {
+    match align {
+        ScrollAlign::Start => start,
+        ScrollAlign::End => end - viewport,
+        ScrollAlign::Nearest if start < current => start,
+        ScrollAlign::Nearest if end > current + viewport => end - viewport,
+        ScrollAlign::Nearest => current,
+    }
+}
+
+/// Moves the offset towards the target, or along the inertia left by a drag.
+fn animate_scroll_views(
+    time: Res<Time>,
+    mut view_query: Query<(&mut ScrollView, Option<&ContentDrag>)>,
+) {
+    let delta_seconds = time.delta_seconds();
+
+    for (mut view, drag) in view_query.iter_mut() {
+        if drag.is_some_and(|drag| drag.active) {
+            continue;
+        }
+
+        if view.velocity != Vec2::ZERO {
+            if view.velocity.length() < MIN_VELOCITY {
+                view.velocity = Vec2::ZERO;
+                continue;
+            }
+            let next = view.offset + view.velocity * delta_seconds;
+            let clamped = view.clamp(next);
+            // 撞到边界的轴立即停止
+            let mut velocity = view.velocity * (1.0 - view.friction).powf(delta_seconds);
+            if clamped.x != next.x {
+                velocity.x = 0.0;
+            }
+            if clamped.y != next.y {
+                velocity.y = 0.0;
+            }
+            view.offset = clamped;
+            view.target = clamped;
+            view.velocity = velocity;
+            continue;
+        }
+
+        if view.offset == view.target {
+            continue;
+        }
+        let t = 1.0 - (-view.smoothing * delta_seconds).exp();
+        let offset = view.offset.lerp(view.target, t);
+        view.offset = if view.smoothing <= 0.0 || offset.distance(view.target) < 0.5 {
+            view.target
+        } else {
+            offset
+        };
+    }
+}
+
+/// Measures the live rows of each [`VirtualList`], then spawns, recycles and
+/// positions rows so that exactly the visible range plus overscan exists.
+fn virtual_list_system(
+    mut commands: Commands,
+    mut view_query: Query<(&mut ScrollView, &mut VirtualList)>,
+    mut node_query: Query<(&Node, &mut Style), Without<ScrollView>>,
+) {
+    for (mut view, mut list) in view_query.iter_mut() {
+        let list = &mut *list;
+        let len = list.source.len();
+        let estimate = list.source.estimated_row_height();
+
+        if list.dirty {
+            list.dirty = false;
+            list.heights.clear();
+            list.pool.extend(list.rows.drain().map(|(_, row)| row));
+            for &row in list.pool.iter() {
+                commands.entity(row).despawn_descendants();
+            }
+        }
+        list.heights.resize(len, None);
+
+        // 测量上一帧布局好的行；视口上方的行高度变化时保持可见内容不动
+        let mut top = 0.0;
+        let mut first_visible = len;
+        for (index, height) in list.heights.iter().enumerate() {
+            top += height.unwrap_or(estimate);
+            if top > view.offset.y {
+                first_visible = index;
+                break;
+            }
+        }
+        let mut anchor_delta = 0.0;
+        for (&index, &row) in list.rows.iter() {
+            if index >= len {
+                continue;
+            }
+            let Ok((node, _)) = node_query.get(row) else {
+                continue;
+            };
+            let height = node.size().y;
+            // 字体未加载完时高度为零，继续使用估计值
+            if height <= 0.0 {
+                continue;
+            }
+            let old = list.heights[index].unwrap_or(estimate);
+            list.heights[index] = Some(height);
+            if index < first_visible {
+                anchor_delta += height - old;
+            }
+        }
+        if anchor_delta != 0.0 {
+            view.offset.y += anchor_delta;
+            view.target.y += anchor_delta;
+        }
+
+        let mut tops = Vec::with_capacity(len + 1);
+        tops.push(0.0);
+        for height in list.heights.iter() {
+            let top = tops[tops.len() - 1] + height.unwrap_or(estimate);
+            tops.push(top);
+        }
+        let total = tops[len];
+
+        let view_top = view.offset.y;
+        let view_bottom = view_top + view.viewport_size.y;
+        let first = tops[1..].partition_point(|&bottom| bottom <= view_top);
+        let last = tops[..len].partition_point(|&top| top < view_bottom);
+        let range = first.saturating_sub(list.overscan)..(last + list.overscan).min(len);
+
+        // 回收离开可见范围的行
+        let mut released = Vec::new();
+        list.rows.retain(|index, row| {
+            let keep = range.contains(index);
+            if !keep {
+                released.push(*row);
+            }
+            keep
+        });
+        for row in released {
+            commands.entity(row).despawn_descendants();
+            if let Ok((_, mut style)) = node_query.get_mut(row) {
+                style.display = Display::None;
+            }
+            list.pool.push(row);
+        }
+
+        for index in range {
+            let row_top = Px(tops[index]);
+            if let Some(&row) = list.rows.get(&index) {
+                if let Ok((_, mut style)) = node_query.get_mut(row) {
+                    if style.top != row_top {
+                        style.top = row_top;
                     }
                 }
+                continue;
             }
 
-            // 当鼠标按钮释放时，停止拖动
-            if mouse_button_input.just_released(MouseButton::Left) {
-                scroll_bar.is_dragging = false;
+            let row = match list.pool.pop() {
+                Some(row) => {
+                    if let Ok((_, mut style)) = node_query.get_mut(row) {
+                        style.display = Display::Flex;
+                        style.top = row_top;
+                    }
+                    row
+                }
+                None => {
+                    let row = commands
+                        .spawn((
+                            NodeBundle {
+                                style: Style {
+                                    position_type: PositionType::Absolute,
+                                    flex_direction: FlexDirection::Column,
+                                    width: Percent(100.0),
+                                    top: row_top,
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            Name::new("scroll_list_row"),
+                        ))
+                        .id();
+                    commands.entity(view.content).add_child(row);
+                    row
+                }
+            };
+            let source = list.source.as_ref();
+            commands
+                .entity(row)
+                .with_children(|row| source.build_row(index, row));
+            list.rows.insert(index, row);
+        }
+
+        // 行是绝对定位的，内容高度需要显式给出
+        if let Ok((_, mut style)) = node_query.get_mut(view.content) {
+            if style.height != Px(total) {
+                style.height = Px(total);
             }
         }
     }
 }
 
-pub fn scroll_view_drag_system(mut scroll_query: Query<(&mut Style, &mut ScrollView)>) {
-    let mut offset = 0.0;
-    for (_, scroll_bar) in scroll_query.iter_mut().filter(|p| p.1.bar) {
-        offset = (scroll_bar.parent_top - scroll_bar.current_top) / scroll_bar.parent_len
-    }
-    for (mut view_style, mut scroll_view) in scroll_query.iter_mut().filter(|p| !p.1.bar) {
-        //if offset = 0, view_top = parent_top, if offset = 1, view_top = parent_top - ( view_len - parent_len )
-        scroll_view.view_top =
-            scroll_view.parent_top - offset * (scroll_view.view_len - scroll_view.parent_len);
-        view_style.top = Val::Px(scroll_view.parent_top - scroll_view.view_top);
+/// Moves the content by the offset and sizes the thumbs to the visible fraction.
+fn scroll_view_layout_system(
+    view_query: Query<&ScrollView, Changed<ScrollView>>,
+    mut content_query: Query<&mut Style, (Without<ScrollThumb>, Without<ScrollBar>)>,
+    mut bar_query: Query<(&ScrollBar, &Node, &mut Style), Without<ScrollThumb>>,
+    mut thumb_query: Query<&mut Style, With<ScrollThumb>>,
+) {
+    for view in view_query.iter() {
+        if let Ok(mut style) = content_query.get_mut(view.content) {
+            style.top = Px(-view.offset.y);
+            style.left = Px(-view.offset.x);
+        }
+
+        for bar_entity in [view.vertical_bar, view.horizontal_bar].into_iter().flatten() {
+            let Ok((bar, bar_node, mut bar_style)) = bar_query.get_mut(bar_entity) else {
+                continue;
+            };
+            let (offset, viewport_len, content_len) = view.axis_lengths(bar.vertical);
+            let scrollable = content_len > viewport_len && viewport_len > 0.0;
+            bar_style.display = if scrollable {
+                Display::Flex
+            } else {
+                Display::None
+            };
+            if !scrollable {
+                continue;
+            }
+
+            let Ok(mut thumb_style) = thumb_query.get_mut(bar.thumb) else {
+                continue;
+            };
+            // 轨道尚未完成布局时按视口长度估算
+            let track_len = if bar.vertical {
+                bar_node.size().y
+            } else {
+                bar_node.size().x
+            };
+            let track_len = if track_len > 0.0 { track_len } else { viewport_len };
+            let thumb_len = (viewport_len / content_len * track_len).clamp(MIN_THUMB_LEN.min(track_len), track_len);
+            let thumb_pos = offset / (content_len - viewport_len) * (track_len - thumb_len);
+            if bar.vertical {
+                thumb_style.height = Px(thumb_len);
+                thumb_style.top = Px(thumb_pos);
+            } else {
+                thumb_style.width = Px(thumb_len);
+                thumb_style.left = Px(thumb_pos);
+            }
+        }
     }
 }
 
@@ -317,61 +1199,357 @@ fn text_filed_hidden(
     mut filed_query: Query<&mut Visibility, With<TextFiledHidden>>,
     button_query: Query<&Interaction, (Changed<Interaction>, With<TextFiledHiddenButton>)>,
 ) {
-    // println!("button pressed!");
     for button in button_query.iter() {
-        match *button {
-            Interaction::Pressed => {
-                for mut node in filed_query.iter_mut() {
-                    let vcp = (*node).clone();
-                    // println!("vcp:{:?}",vcp);
-                    match vcp {
-                        Visibility::Inherited => {
-                            *node = Visibility::Hidden;
-                        }
-                        Visibility::Hidden => {
-                            *node = Visibility::Inherited;
-                        }
-                        _ => {
-                            return;
-                        }
-                    }
+        if *button != Interaction::Pressed {
+            continue;
+        }
+        for mut node in filed_query.iter_mut() {
+            match *node {
+                Visibility::Inherited => {
+                    *node = Visibility::Hidden;
+                }
+                Visibility::Hidden => {
+                    *node = Visibility::Inherited;
+                }
+                _ => {
+                    return;
                 }
-            }
-            _ => {
-                continue;
             }
         }
     }
 }
 
-fn scroll_view_system(
-    mut scroll_query: Query<(&mut Style, &mut ScrollView)>,
-    mut mouse_wheel_events: EventReader<MouseWheel>,
-) {
-    let mut scroll_delta = 0.0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_harness::TestApp;
 
-    // 读取鼠标滚轮事件
-    for event in mouse_wheel_events.par_read() {
-        scroll_delta += event.0.y; // 每次滚动的像素值
-    }
-
-    // 更新子容器的位置
-    if scroll_delta != 0.0 {
-        for (mut style, mut content) in scroll_query.iter_mut() {
-            let cst = content.current_top.clone();
-            let len = content.current_len;
-            // print!(
-            //     "len:{:?},pl:{:?},cst{:?},pt{:?}\n",
-            //     len, content.parent_len, cst, content.parent_top
-            // );
-            if cst + scroll_delta <= (content.parent_top + content.parent_len - len)
-                && cst + scroll_delta >= content.parent_top
-            {
-                // println!("compare! {:?}\n", scroll_delta);
-                // println!("has current");
-                style.top = Val::Px(cst + scroll_delta - content.parent_top);
-                content.current_top += scroll_delta;
+    /// Spawns a 200x100 view over 400x1000 of content, with the cursor over it.
+    fn spawn(app: &mut TestApp, axis: ScrollAxis) -> Entity {
+        spawn_with(app, axis, |_| {})
+    }
+
+    fn spawn_with(
+        app: &mut TestApp,
+        axis: ScrollAxis,
+        spawn_content: impl FnOnce(&mut ChildBuilder),
+    ) -> Entity {
+        app.spawn_ui(|parent| {
+            ScrollViewBuilder::new(Vec2::new(200.0, 100.0))
+                .axis(axis)
+                .spawn(parent, spawn_content);
+        });
+        app.update();
+        let view = app.entities_with::<ScrollView>()[0];
+        // 没有界面布局，直接写入测量结果
+        let mut state = app.get_mut::<ScrollView>(view);
+        state.viewport_size = Vec2::new(200.0, 100.0);
+        state.content_size = Vec2::new(400.0, 1000.0);
+        app.hover(view, true);
+        view
+    }
+
+    fn settle(app: &mut TestApp) {
+        app.step(120);
+    }
+
+    #[test]
+    fn wheel_scrolls_the_hovered_view_smoothly() {
+        let mut app = TestApp::new(ScrollViewPlugin);
+        let view = spawn(&mut app, ScrollAxis::Vertical);
+
+        app.scroll_lines(0.0, -2.0);
+        app.update();
+        let state = app.get::<ScrollView>(view);
+        assert_eq!(state.target, Vec2::new(0.0, 60.0));
+        assert!(state.offset.y > 0.0 && state.offset.y < 60.0);
+
+        settle(&mut app);
+        let state = app.get::<ScrollView>(view);
+        assert_eq!(state.offset, state.target);
+        let content = state.content;
+        assert_eq!(app.get::<Style>(content).top, Px(-60.0));
+    }
+
+    #[test]
+    fn wheel_ignores_views_without_the_cursor() {
+        let mut app = TestApp::new(ScrollViewPlugin);
+        let view = spawn(&mut app, ScrollAxis::Vertical);
+        app.hover(view, false);
+
+        app.scroll_lines(0.0, -2.0);
+        app.update();
+        assert_eq!(app.get::<ScrollView>(view).target, Vec2::ZERO);
+    }
+
+    #[test]
+    fn scrolling_stops_at_the_content_edges() {
+        let mut app = TestApp::new(ScrollViewPlugin);
+        let view = spawn(&mut app, ScrollAxis::Vertical);
+
+        app.scroll_lines(0.0, -100.0);
+        app.update();
+        assert_eq!(app.get::<ScrollView>(view).target, Vec2::new(0.0, 900.0));
+
+        app.scroll_lines(0.0, 100.0);
+        app.update();
+        assert_eq!(app.get::<ScrollView>(view).target, Vec2::ZERO);
+    }
+
+    #[test]
+    fn keys_scroll_by_lines_and_pages() {
+        let mut app = TestApp::new(ScrollViewPlugin);
+        let view = spawn(&mut app, ScrollAxis::Vertical);
+
+        app.tap_key(KeyCode::ArrowDown);
+        assert_eq!(app.get::<ScrollView>(view).target.y, 30.0);
+        app.tap_key(KeyCode::PageDown);
+        assert_eq!(app.get::<ScrollView>(view).target.y, 130.0);
+        app.tap_key(KeyCode::End);
+        assert_eq!(app.get::<ScrollView>(view).target.y, 900.0);
+        app.tap_key(KeyCode::Home);
+        assert_eq!(app.get::<ScrollView>(view).target.y, 0.0);
+    }
+
+    #[test]
+    fn keys_belong_to_a_node_focused_outside_of_the_view() {
+        let mut app = TestApp::new(ScrollViewPlugin);
+        app.world_mut().init_resource::<Focus>();
+        let mut inside = Entity::PLACEHOLDER;
+        let view = spawn_with(&mut app, ScrollAxis::Vertical, |content| {
+            inside = content.spawn(ButtonBundle::default()).id();
+        });
+        let outside = app.spawn_ui(|_| {});
+
+        app.world_mut().resource_mut::<Focus>().set(Some(outside));
+        app.tap_key(KeyCode::ArrowDown);
+        assert_eq!(app.get::<ScrollView>(view).target.y, 0.0);
+
+        app.world_mut().resource_mut::<Focus>().set(Some(inside));
+        app.tap_key(KeyCode::ArrowDown);
+        assert_eq!(app.get::<ScrollView>(view).target.y, 30.0);
+
+        app.world_mut().resource_mut::<Focus>().set(None);
+        app.tap_key(KeyCode::PageDown);
+        assert_eq!(app.get::<ScrollView>(view).target.y, 130.0);
+    }
+
+    #[test]
+    fn vertical_wheel_scrolls_a_horizontal_view_sideways() {
+        let mut app = TestApp::new(ScrollViewPlugin);
+        let view = spawn(&mut app, ScrollAxis::Horizontal);
+
+        app.scroll_lines(0.0, -2.0);
+        settle(&mut app);
+        let state = app.get::<ScrollView>(view);
+        assert_eq!(state.offset, Vec2::new(60.0, 0.0));
+        let content = state.content;
+        assert_eq!(app.get::<Style>(content).left, Px(-60.0));
+    }
+
+    #[test]
+    fn shift_wheel_scrolls_a_two_axis_view_sideways() {
+        let mut app = TestApp::new(ScrollViewPlugin);
+        let view = spawn(&mut app, ScrollAxis::Both);
+
+        app.press_key(KeyCode::ShiftLeft);
+        app.scroll_lines(0.0, -2.0);
+        app.update();
+        assert_eq!(app.get::<ScrollView>(view).target, Vec2::new(60.0, 0.0));
+    }
+
+    #[test]
+    fn dragging_the_thumb_scrolls_in_proportion() {
+        let mut app = TestApp::new(ScrollViewPlugin);
+        let view = spawn(&mut app, ScrollAxis::Vertical);
+        let bar = app.get::<ScrollView>(view).vertical_bar.unwrap();
+        let thumb = app.get::<ScrollBar>(bar).thumb;
+        // 轨道高 100，滑块高 10，可移动 90
+        app.lay_out(bar, Rect::new(184.0, 0.0, 200.0, 100.0));
+        app.lay_out(thumb, Rect::new(184.0, 0.0, 200.0, 10.0));
+
+        app.move_cursor(Vec2::new(192.0, 5.0));
+        *app.get_mut::<Interaction>(thumb) = Interaction::Pressed;
+        app.press_mouse_button(MouseButton::Left);
+        app.update();
+        assert_eq!(app.get::<ScrollView>(view).offset.y, 0.0);
+
+        app.move_cursor(Vec2::new(192.0, 50.0));
+        app.update();
+        assert_eq!(app.get::<ScrollView>(view).offset.y, 450.0);
+        app.move_cursor(Vec2::new(192.0, 400.0));
+        app.update();
+        assert_eq!(app.get::<ScrollView>(view).offset.y, 900.0);
+
+        app.release_mouse_button(MouseButton::Left);
+        app.update();
+        app.move_cursor(Vec2::new(192.0, 5.0));
+        app.update();
+        assert_eq!(app.get::<ScrollView>(view).offset.y, 900.0);
+    }
+
+    #[test]
+    fn a_content_fling_slows_down_to_rest() {
+        let mut app = TestApp::new(ScrollViewPlugin);
+        let view = spawn(&mut app, ScrollAxis::Vertical);
+        let content = app.get::<ScrollView>(view).content;
+        app.lay_out(view, Rect::new(0.0, 0.0, 200.0, 100.0));
+        app.lay_out(content, Rect::new(0.0, 0.0, 200.0, 1000.0));
+
+        app.move_cursor(Vec2::new(100.0, 80.0));
+        app.press_mouse_button(MouseButton::Left);
+        app.update();
+        for y in [70.0, 60.0, 50.0] {
+            app.move_cursor(Vec2::new(100.0, y));
+            app.update();
+        }
+        assert_eq!(app.get::<ScrollView>(view).offset.y, 30.0);
+
+        app.release_mouse_button(MouseButton::Left);
+        app.update();
+        let state = app.get::<ScrollView>(view);
+        let (offset, speed) = (state.offset.y, state.velocity.y);
+        assert!(offset > 30.0 && speed > 0.0);
+        app.step(10);
+        let state = app.get::<ScrollView>(view);
+        assert!(state.offset.y > offset && state.velocity.y < speed);
+
+        settle(&mut app);
+        let state = app.get::<ScrollView>(view);
+        assert_eq!(state.velocity, Vec2::ZERO);
+        assert_eq!(state.offset, state.target);
+        let rest = state.offset.y;
+        assert!(rest > 60.0 && rest < 900.0, "{rest}");
+        app.step(10);
+        assert_eq!(app.get::<ScrollView>(view).offset.y, rest);
+    }
+
+    #[test]
+    fn scroll_to_brings_the_target_into_view() {
+        let mut app = TestApp::new(ScrollViewPlugin);
+        let mut items = Vec::new();
+        let view = spawn_with(&mut app, ScrollAxis::Vertical, |content| {
+            for _ in 0..10 {
+                items.push(content.spawn(NodeBundle::default()).id());
             }
+        });
+        let content = app.get::<ScrollView>(view).content;
+
+        // 目标还没有布局时等待
+        app.world_mut()
+            .commands()
+            .scroll_to(view, items[5], ScrollAlign::Nearest);
+        app.world_mut().flush();
+        app.update();
+        assert_eq!(app.get::<ScrollView>(view).target, Vec2::ZERO);
+
+        app.lay_out(content, Rect::new(0.0, 0.0, 200.0, 1000.0));
+        for (index, &item) in items.iter().enumerate() {
+            let top = index as f32 * 100.0;
+            app.lay_out(item, Rect::new(0.0, top, 200.0, top + 100.0));
+        }
+        app.update();
+        settle(&mut app);
+        // 最少滚动：目标的底边对齐视口底边
+        assert_eq!(app.get::<ScrollView>(view).offset, Vec2::new(0.0, 500.0));
+
+        app.world_mut()
+            .commands()
+            .scroll_to(view, items[2], ScrollAlign::Start);
+        app.world_mut().flush();
+        settle(&mut app);
+        assert_eq!(app.get::<ScrollView>(view).offset, Vec2::new(0.0, 200.0));
+    }
+
+    /// A list of rows with nothing but an empty node.
+    struct Rows(usize);
+
+    impl ListDataSource for Rows {
+        fn len(&self) -> usize {
+            self.0
         }
+
+        fn build_row(&self, _index: usize, row: &mut ChildBuilder) {
+            row.spawn(NodeBundle::default());
+        }
+    }
+
+    /// Spawns a 200x100 list of `len` rows, 30 pixels high until laid out.
+    fn spawn_list(app: &mut TestApp, len: usize) -> Entity {
+        app.spawn_ui(|parent| {
+            ScrollViewBuilder::new(Vec2::new(200.0, 100.0)).spawn_list(parent, Rows(len));
+        });
+        app.update();
+        let view = app.entities_with::<VirtualList>()[0];
+        let mut state = app.get_mut::<ScrollView>(view);
+        state.viewport_size = Vec2::new(200.0, 100.0);
+        state.content_size = Vec2::new(200.0, len as f32 * 30.0);
+        app.update();
+        view
+    }
+
+    fn spawned_rows(app: &TestApp, view: Entity) -> usize {
+        let content = app.get::<ScrollView>(view).content;
+        app.get::<Children>(content).len()
+    }
+
+    #[test]
+    fn a_long_list_only_spawns_the_rows_in_view() {
+        let mut app = TestApp::new(ScrollViewPlugin);
+        let view = spawn_list(&mut app, 10_000);
+        // 4 行可见，上下各多留 3 行
+        assert_eq!(spawned_rows(&app, view), 7);
+
+        for offset in [150_000.0, 3_000.0, 299_900.0, 0.0] {
+            app.get_mut::<ScrollView>(view)
+                .set_offset(Vec2::new(0.0, offset));
+            app.update();
+            assert!(spawned_rows(&app, view) <= 10);
+        }
+        let content = app.get::<ScrollView>(view).content;
+        assert_eq!(app.get::<Style>(content).height, Px(300_000.0));
+    }
+
+    #[test]
+    fn scrolling_a_long_list_to_a_row_shows_it() {
+        let mut app = TestApp::new(ScrollViewPlugin);
+        let view = spawn_list(&mut app, 10_000);
+        let offset = app.get::<VirtualList>(view).row_offset(5000);
+        assert_eq!(offset, 150_000.0);
+
+        app.get_mut::<ScrollView>(view)
+            .scroll_to_offset(Vec2::new(0.0, offset));
+        settle(&mut app);
+        assert_eq!(app.get::<ScrollView>(view).offset.y, offset);
+        let list = app.get::<VirtualList>(view);
+        assert!(list.row_entity(0).is_none());
+        let row = list.row_entity(5000).unwrap();
+        let style = app.get::<Style>(row);
+        assert_eq!((style.display, style.top), (Display::Flex, Px(offset)));
+        assert_eq!(app.get::<Children>(row).len(), 1);
+    }
+
+    #[test]
+    fn rows_growing_above_the_view_keep_the_visible_row_in_place() {
+        let mut app = TestApp::new(ScrollViewPlugin);
+        let view = spawn_list(&mut app, 10_000);
+        app.get_mut::<ScrollView>(view)
+            .set_offset(Vec2::new(0.0, 150_000.0));
+        app.update();
+        let shown = |app: &TestApp| {
+            let row = app.get::<VirtualList>(view).row_entity(5000).unwrap();
+            let Px(top) = app.get::<Style>(row).top else {
+                panic!("the row has no top");
+            };
+            top - app.get::<ScrollView>(view).offset.y
+        };
+        assert_eq!(shown(&app), 0.0);
+
+        // 视口上方的一行布局后比估计的高 30
+        let above = app.get::<VirtualList>(view).row_entity(4998).unwrap();
+        app.lay_out(above, Rect::new(0.0, 0.0, 200.0, 60.0));
+        app.update();
+        assert_eq!(app.get::<ScrollView>(view).offset.y, 150_030.0);
+        assert_eq!(shown(&app), 0.0);
     }
 }
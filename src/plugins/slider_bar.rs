@@ -1,103 +1,345 @@
-use bevy::app::{App, Startup};
+use bevy::app::App;
 use bevy::color::Color;
 use bevy::math::Vec2;
 use bevy::prelude::*;
 use bevy::ui::Style;
-use bevy::ui::Val::Px;
+use bevy::ui::Val::{Percent, Px};
 use bevy::utils::default;
+use bevy::window::PrimaryWindow;
+
+use crate::global_def::global_define::GameConfig;
+use crate::plugins::focus::{gamepad_just_pressed, Focus, FocusPlugin, Focusable};
 
 pub struct SliderBarPlugin;
 
+/// An interactive horizontal slider.
+///
+/// Insert it on a `NodeBundle`; the track, fill and handle are spawned as
+/// children on the next update. The slider reacts to dragging the handle,
+/// clicking anywhere on the track and, while focused, the left and right
+/// arrow keys or D-pad buttons.
 #[derive(Clone, Component)]
-struct SliderBarStage {
+pub struct Slider {
     pub value: f32,
-    pub max_value: f32,
+    pub min: f32,
+    pub max: f32,
+    /// The increment values snap to. `0.0` means continuous; the arrow keys
+    /// then move by a hundredth of the range.
+    pub step: f32,
     pub size: Vec2,
     pub bar_color: Color,
     pub background_color: Color,
     pub ctl_color: Color,
+    pub disabled: bool,
+}
+
+/// Binds a [`Slider`] to a [`GameConfig`] field in both directions.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SliderBinding {
+    /// `GameConfig::master_volume`
+    MasterVolume,
+    /// `GameConfig::text_speed`
+    TextSpeed,
+}
+
+/// Sent whenever the user changes the value of a [`Slider`].
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct SliderChanged {
+    pub entity: Entity,
+    pub value: f32,
+}
+
+#[derive(Component)]
+struct SliderDragging;
+
+#[derive(Component)]
+struct SliderFill;
+
+#[derive(Component)]
+struct SliderHandle;
+
+impl Default for Slider {
+    fn default() -> Self {
+        Self {
+            value: 0.0,
+            min: 0.0,
+            max: 100.0,
+            step: 0.0,
+            size: Vec2::new(300.0, 30.0),
+            bar_color: Color::srgb(0.25, 0.75, 0.25),
+            background_color: Color::srgb(0.15, 0.15, 0.15),
+            ctl_color: Color::srgb(1.0, 1.0, 1.0),
+            disabled: false,
+        }
+    }
+}
+
+impl Slider {
+    /// The value as a fraction of the range, in `0.0..=1.0`.
+    pub fn fraction(&self) -> f32 {
+        if self.max > self.min {
+            ((self.value - self.min) / (self.max - self.min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Clamps `value` into the range and snaps it to `step`.
+    pub fn snap(&self, value: f32) -> f32 {
+        let value = value.clamp(self.min, self.max);
+        if self.step > 0.0 {
+            (self.min + ((value - self.min) / self.step).round() * self.step).clamp(self.min, self.max)
+        } else {
+            value
+        }
+    }
+
+    fn key_step(&self) -> f32 {
+        if self.step > 0.0 {
+            self.step
+        } else {
+            (self.max - self.min) / 100.0
+        }
+    }
+}
+
+impl SliderBinding {
+    fn read(&self, config: &GameConfig) -> f32 {
+        match self {
+            SliderBinding::MasterVolume => config.master_volume,
+            SliderBinding::TextSpeed => config.text_speed,
+        }
+    }
+
+    fn write(&self, config: &mut GameConfig, value: f32) {
+        match self {
+            SliderBinding::MasterVolume => config.master_volume = value,
+            SliderBinding::TextSpeed => config.text_speed = value,
+        }
+    }
 }
 
 impl Plugin for SliderBarPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_entities)
-            .add_systems(Update, update_slider_plugin);
+        if !app.is_plugin_added::<FocusPlugin>() {
+            app.add_plugins(FocusPlugin);
+        }
+        app.add_event::<SliderChanged>()
+            .add_systems(
+                Update,
+                (
+                    update_slider_plugin,
+                    slider_pointer_input,
+                    slider_keyboard_input,
+                    sync_slider_bindings,
+                    update_slider_visuals,
+                )
+                    .chain(),
+            );
     }
 }
 
 fn update_slider_plugin(
     mut commands: Commands,
-    mut entity_query: Query<(Entity, &SliderBarStage, Option<&mut Children>)>,
+    entity_query: Query<(Entity, &Slider), Without<Children>>,
 ) {
-    for (entity, stage, mut children) in entity_query.iter_mut() {
-        match children {
-            Some(_) => {}
-            None => {
-                let background = commands
-                    .spawn(NodeBundle {
-                        style: Style {
-                            width: Val::Percent(100.0),
-                            height: Val::Percent(100.0),
-                            ..default()
-                        },
-                        background_color: BackgroundColor::from(stage.background_color),
-                        ..default()
-                    })
-                    .id();
-
-                let fill = commands
-                    .spawn((NodeBundle {
-                        style: Style {
-                            width: Val::Percent(0.5 * 100.0),
-                            height: Val::Percent(100.0),
-                            ..default()
-                        },
-                        background_color: stage.bar_color.into(),
+    for (entity, slider) in entity_query.iter() {
+        let fraction = slider.fraction();
+        let handle_width = slider.size.x * 0.05;
+
+        let background = commands
+            .spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Percent(100.0),
+                    height: Percent(100.0),
+                    ..default()
+                },
+                background_color: BackgroundColor::from(slider.background_color),
+                ..default()
+            })
+            .id();
+
+        let fill = commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Percent(fraction * 100.0),
+                        height: Percent(100.0),
                         ..default()
-                    },))
-                    .id();
-
-                let slider_stage = commands
-                    .spawn(NodeBundle {
-                        style: Style {
-                            top: Px(-stage.size.y * 0.05),
-                            width: Px(stage.size.x * 0.05),
-                            height: Px(stage.size.y * 1.1),
-                            ..default()
-                        },
-                        background_color: BackgroundColor::from(stage.ctl_color),
+                    },
+                    background_color: slider.bar_color.into(),
+                    ..default()
+                },
+                SliderFill,
+            ))
+            .id();
+
+        let handle = commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Percent(fraction * 100.0),
+                        top: Px(-slider.size.y * 0.05),
+                        margin: UiRect::left(Px(-handle_width / 2.0)),
+                        width: Px(handle_width),
+                        height: Px(slider.size.y * 1.1),
                         ..default()
-                    })
-                    .id();
+                    },
+                    background_color: BackgroundColor::from(slider.ctl_color),
+                    ..default()
+                },
+                SliderHandle,
+            ))
+            .id();
 
-                println!("create plugin");
-                commands
-                    .entity(entity)
-                    .push_children(&[background, fill, slider_stage]);
+        // 根节点接收点击，子节点只负责绘制
+        commands
+            .entity(entity)
+            .insert((Interaction::default(), Focusable::default()))
+            .push_children(&[background, fill, handle]);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn slider_pointer_input(
+    mut commands: Commands,
+    mut focus: ResMut<Focus>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut slider_query: Query<(
+        Entity,
+        &mut Slider,
+        &Interaction,
+        &Node,
+        &GlobalTransform,
+        Option<&SliderDragging>,
+    )>,
+    mut changed_events: EventWriter<SliderChanged>,
+) {
+    let cursor_position = window_query
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position());
+
+    for (entity, mut slider, interaction, node, transform, dragging) in slider_query.iter_mut() {
+        if slider.disabled {
+            continue;
+        }
+
+        if dragging.is_none() {
+            if *interaction == Interaction::Pressed
+                && mouse_button_input.just_pressed(MouseButton::Left)
+            {
+                commands.entity(entity).insert(SliderDragging);
+                focus.set(Some(entity));
+            } else {
+                continue;
             }
+        } else if !mouse_button_input.pressed(MouseButton::Left) {
+            commands.entity(entity).remove::<SliderDragging>();
+            continue;
+        }
+
+        let Some(cursor_position) = cursor_position else {
+            continue;
+        };
+        let rect = node.logical_rect(transform);
+        if rect.width() <= 0.0 {
+            continue;
+        }
+        let fraction = ((cursor_position.x - rect.min.x) / rect.width()).clamp(0.0, 1.0);
+        let value = slider.snap(slider.min + fraction * (slider.max - slider.min));
+        if value != slider.value {
+            slider.value = value;
+            changed_events.send(SliderChanged { entity, value });
         }
     }
 }
 
-fn spawn_entities(mut commands: Commands, assert_server: ResMut<AssetServer>) {
-    commands
-        .spawn(NodeBundle {
-            style: Style {
-                width: Val::Px(300.0),
-                height: Val::Px(30.0),
-                position_type: PositionType::Absolute,
-                left: Val::Px(100.0),
-                top: Val::Px(200.0),
-                ..default()
-            },
-            ..default()
-        })
-        .insert(SliderBarStage {
-            value: 0.0,
-            max_value: 100.0,
-            size: Vec2::new(300.0, 30.0),
-            bar_color: Color::srgb(0.25, 0.75, 0.25),
-            background_color: Color::srgb(0.15, 0.15, 0.15),
-            ctl_color: Color::srgb(1.0, 1.0, 1.0),
-        });
+/// Moves the focused slider; the up and down keys move the focus instead.
+fn slider_keyboard_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepad_input: Res<ButtonInput<GamepadButton>>,
+    focus: Res<Focus>,
+    mut slider_query: Query<&mut Slider>,
+    mut changed_events: EventWriter<SliderChanged>,
+) {
+    let Some(entity) = focus.get() else {
+        return;
+    };
+    let Ok(mut slider) = slider_query.get_mut(entity) else {
+        return;
+    };
+    if slider.disabled {
+        return;
+    }
+
+    let step = slider.key_step();
+    let mut value = slider.value;
+    if keyboard_input.just_pressed(KeyCode::ArrowLeft)
+        || gamepad_just_pressed(&gamepad_input, GamepadButtonType::DPadLeft)
+    {
+        value -= step;
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowRight)
+        || gamepad_just_pressed(&gamepad_input, GamepadButtonType::DPadRight)
+    {
+        value += step;
+    }
+    if keyboard_input.just_pressed(KeyCode::Home) {
+        value = slider.min;
+    }
+    if keyboard_input.just_pressed(KeyCode::End) {
+        value = slider.max;
+    }
+
+    let value = slider.snap(value);
+    if value != slider.value {
+        slider.value = value;
+        changed_events.send(SliderChanged { entity, value });
+    }
+}
+
+/// Writes user changes to the bound config field, and config changes made
+/// elsewhere back to the slider.
+fn sync_slider_bindings(
+    mut config: ResMut<GameConfig>,
+    mut changed_events: EventReader<SliderChanged>,
+    mut slider_query: Query<(&mut Slider, &SliderBinding)>,
+) {
+    for event in changed_events.read() {
+        if let Ok((_, binding)) = slider_query.get(event.entity) {
+            if binding.read(&config) != event.value {
+                binding.write(&mut config, event.value);
+            }
+        }
+    }
+
+    for (mut slider, binding) in slider_query.iter_mut() {
+        let value = slider.snap(binding.read(&config));
+        if slider.value != value && (config.is_changed() || slider.is_added()) {
+            slider.value = value;
+        }
+    }
+}
+
+fn update_slider_visuals(
+    slider_query: Query<(&Slider, &Children), Changed<Slider>>,
+    mut fill_query: Query<&mut Style, (With<SliderFill>, Without<SliderHandle>)>,
+    mut handle_query: Query<&mut Style, (With<SliderHandle>, Without<SliderFill>)>,
+) {
+    for (slider, children) in slider_query.iter() {
+        let fraction = slider.fraction();
+        for &child in children.iter() {
+            if let Ok(mut style) = fill_query.get_mut(child) {
+                style.width = Percent(fraction * 100.0);
+            }
+            if let Ok(mut style) = handle_query.get_mut(child) {
+                style.left = Percent(fraction * 100.0);
+            }
+        }
+    }
 }
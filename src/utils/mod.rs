@@ -1,2 +1,4 @@
 pub mod image_utils;
 pub mod string_utils;
+#[cfg(test)]
+pub mod test_harness;
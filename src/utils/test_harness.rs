@@ -0,0 +1,227 @@
+use bevy::app::Plugins;
+use bevy::ecs::event::Event;
+use bevy::input::gamepad::{
+    Gamepad, GamepadButtonChangedEvent, GamepadButtonType, GamepadEvent,
+};
+use bevy::input::keyboard::{Key, KeyboardInput, NativeKey};
+use bevy::input::mouse::{MouseButtonInput, MouseScrollUnit, MouseWheel};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use bevy::reflect::Struct;
+use bevy::ui::RelativeCursorPosition;
+use bevy::window::PrimaryWindow;
+
+use crate::plugins::replay::headless_app;
+
+/// A headless app for testing plugins, see [`headless_app`].
+///
+/// There is no UI layout, so nodes keep a zero size until [`TestApp::lay_out`]
+/// places them, and `Interaction` and `RelativeCursorPosition` only change
+/// when a test sets them. Input is
+/// sent as events, the way winit would, and applied on the next
+/// [`TestApp::update`]. Every update advances the clock by 1/60 s.
+pub struct TestApp {
+    pub app: App,
+}
+
+impl TestApp {
+    pub fn new<M>(plugins: impl Plugins<M>) -> Self {
+        let mut app = headless_app();
+        app.add_plugins(plugins);
+        // 运行 Startup
+        app.update();
+        Self { app }
+    }
+
+    pub fn update(&mut self) {
+        self.app.update();
+    }
+
+    pub fn step(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.app.update();
+        }
+    }
+
+    pub fn world(&self) -> &World {
+        self.app.world()
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        self.app.world_mut()
+    }
+
+    /// Spawns a root node and its children, returning the root.
+    pub fn spawn_ui(&mut self, spawn_children: impl FnOnce(&mut ChildBuilder)) -> Entity {
+        let world = self.world_mut();
+        let root = world
+            .commands()
+            .spawn(NodeBundle::default())
+            .with_children(spawn_children)
+            .id();
+        world.flush();
+        root
+    }
+
+    pub fn get<T: Component>(&self, entity: Entity) -> &T {
+        self.world()
+            .get::<T>(entity)
+            .unwrap_or_else(|| panic!("{entity:?} has no {}", std::any::type_name::<T>()))
+    }
+
+    pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Mut<'_, T> {
+        self.world_mut()
+            .get_mut::<T>(entity)
+            .unwrap_or_else(|| panic!("{entity:?} has no {}", std::any::type_name::<T>()))
+    }
+
+    /// The entities with a `T`, in spawn order.
+    pub fn entities_with<T: Component>(&mut self) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = self
+            .world_mut()
+            .query_filtered::<Entity, With<T>>()
+            .iter(self.app.world())
+            .collect();
+        entities.sort();
+        entities
+    }
+
+    /// Takes the `E` events sent since the last call.
+    pub fn drain_events<E: Event>(&mut self) -> Vec<E> {
+        self.world_mut().resource_mut::<Events<E>>().drain().collect()
+    }
+
+    /// Sets the `Interaction` of `entity` and runs an update.
+    pub fn set_interaction(&mut self, entity: Entity, interaction: Interaction) {
+        *self.get_mut::<Interaction>(entity) = interaction;
+        self.update();
+    }
+
+    /// Presses and releases the button `entity`, the way `ui_focus_system` would.
+    pub fn click(&mut self, entity: Entity) {
+        self.set_interaction(entity, Interaction::Pressed);
+        self.set_interaction(entity, Interaction::Hovered);
+    }
+
+    /// Puts the cursor over the middle of `entity`, or takes it away.
+    pub fn hover(&mut self, entity: Entity, over: bool) {
+        let mut cursor = self.get_mut::<RelativeCursorPosition>(entity);
+        cursor.normalized_visible_node_rect = Rect::new(0.0, 0.0, 1.0, 1.0);
+        cursor.normalized = over.then_some(Vec2::splat(0.5));
+    }
+
+    pub fn press_key(&mut self, key_code: KeyCode) {
+        self.send_key(key_code, ButtonState::Pressed);
+    }
+
+    pub fn release_key(&mut self, key_code: KeyCode) {
+        self.send_key(key_code, ButtonState::Released);
+    }
+
+    /// Presses `key_code` for one update, then releases it.
+    pub fn tap_key(&mut self, key_code: KeyCode) {
+        self.press_key(key_code);
+        self.update();
+        self.release_key(key_code);
+    }
+
+    fn send_key(&mut self, key_code: KeyCode, state: ButtonState) {
+        self.world_mut().send_event(KeyboardInput {
+            key_code,
+            logical_key: Key::Unidentified(NativeKey::Unidentified),
+            state,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+
+    pub fn press_mouse_button(&mut self, button: MouseButton) {
+        self.send_mouse_button(button, ButtonState::Pressed);
+    }
+
+    pub fn release_mouse_button(&mut self, button: MouseButton) {
+        self.send_mouse_button(button, ButtonState::Released);
+    }
+
+    /// Presses `button` for one update, then releases it.
+    pub fn tap_mouse_button(&mut self, button: MouseButton) {
+        self.press_mouse_button(button);
+        self.update();
+        self.release_mouse_button(button);
+    }
+
+    fn send_mouse_button(&mut self, button: MouseButton, state: ButtonState) {
+        self.world_mut().send_event(MouseButtonInput {
+            button,
+            state,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+
+    /// Moves the cursor to `position` in the primary window, which is
+    /// spawned the first time.
+    pub fn move_cursor(&mut self, position: Vec2) {
+        let world = self.world_mut();
+        let window = world
+            .query_filtered::<Entity, With<PrimaryWindow>>()
+            .get_single(world)
+            .unwrap_or_else(|_| world.spawn((Window::default(), PrimaryWindow)).id());
+        world
+            .get_mut::<Window>(window)
+            .unwrap()
+            .set_cursor_position(Some(position));
+    }
+
+    /// Gives `entity` the size and place of `rect`, in window coordinates, the
+    /// way the UI layout would.
+    pub fn lay_out(&mut self, entity: Entity, rect: Rect) {
+        let mut node = self.get_mut::<Node>(entity);
+        // 布局结果是私有字段，通过反射写入
+        let Some(size) = node
+            .field_mut("calculated_size")
+            .and_then(|size| size.downcast_mut::<Vec2>())
+        else {
+            panic!("Node has no calculated_size");
+        };
+        *size = rect.size();
+
+        // 布局给出相对父节点的位置，这里减去祖先节点的位置
+        let mut parent_translation = Vec3::ZERO;
+        let mut ancestor = self.world().get::<Parent>(entity).map(Parent::get);
+        while let Some(parent) = ancestor {
+            if let Some(transform) = self.world().get::<Transform>(parent) {
+                parent_translation += transform.translation;
+            }
+            ancestor = self.world().get::<Parent>(parent).map(Parent::get);
+        }
+        let center = rect.center().extend(0.0);
+        *self.get_mut::<Transform>(entity) =
+            Transform::from_translation(center - parent_translation);
+        *self.get_mut::<GlobalTransform>(entity) = GlobalTransform::from_translation(center);
+    }
+
+    /// Presses `button_type` of the first gamepad for one update, then
+    /// releases it.
+    pub fn tap_gamepad_button(&mut self, button_type: GamepadButtonType) {
+        for value in [1.0, 0.0] {
+            self.world_mut()
+                .send_event(GamepadEvent::Button(GamepadButtonChangedEvent::new(
+                    Gamepad::new(0),
+                    button_type,
+                    value,
+                )));
+            if value > 0.0 {
+                self.update();
+            }
+        }
+    }
+
+    /// Turns the mouse wheel by `x` and `y` lines; a positive `y` scrolls up.
+    pub fn scroll_lines(&mut self, x: f32, y: f32) {
+        self.world_mut().send_event(MouseWheel {
+            unit: MouseScrollUnit::Line,
+            x,
+            y,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+}
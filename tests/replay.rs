@@ -0,0 +1,38 @@
+//! Replays every recording in `tests/replays` headless and fails when the
+//! story ends in a different state than recorded.
+//!
+//! Record a new one with `cargo run --example demo -- --record tests/replays/<name>.toml`,
+//! and see why one fails with `cargo run --example demo -- --replay <path>`.
+
+use std::path::Path;
+
+use bevy::prelude::*;
+use avg_dsx::prelude::*;
+
+#[test]
+fn recorded_sessions_replay_to_the_same_state() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/replays");
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .expect("tests/replays is missing")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no recordings in {}", dir.display());
+
+    for path in paths {
+        let recording = InputRecording::load(&path)
+            .unwrap_or_else(|err| panic!("can not load {}: {}", path.display(), err));
+        let exit = headless_app()
+            .add_plugins(
+                AvgPlugin::new()
+                    .audio(false)
+                    .widgets(false)
+                    .scroll_view(false)
+                    .config(false),
+            )
+            .add_plugins(ReplayPlugin::Replay(recording))
+            .run();
+        assert_eq!(exit, AppExit::Success, "replay of {} failed", path.display());
+    }
+}